@@ -18,10 +18,20 @@ pub const OTEL_EXPORTER_OTLP_CLIENT_CERTIFICATE: &str = "OTEL_EXPORTER_OTLP_CLIE
 pub const OTEL_EXPORTER_OTLP_HEADERS: &str = "OTEL_EXPORTER_OTLP_HEADERS";
 /// Env key: OTEL_EXPORTER_OTLP_PROTOCOL
 pub const OTEL_EXPORTER_OTLP_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_PROTOCOL";
+/// Env key: OTEL_SDK_DISABLED
+pub const OTEL_SDK_DISABLED: &str = "OTEL_SDK_DISABLED";
+/// Env key: OTEL_SERVICE_NAME
+pub const OTEL_SERVICE_NAME: &str = "OTEL_SERVICE_NAME";
+/// Env key: OTEL_RESOURCE_ATTRIBUTES
+pub const OTEL_RESOURCE_ATTRIBUTES: &str = "OTEL_RESOURCE_ATTRIBUTES";
 /// TODO compression
 
 /// Default timeout is 10s.
 pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default number of consecutive failures before switching to the next endpoint.
+pub const DEFAULT_FAILOVER_THRESHOLD: usize = 3;
+/// Default interval of probing the primary endpoint after failing over.
+pub const DEFAULT_FAILBACK_PROBE_INTERVAL: Duration = Duration::from_secs(30);
 /// OTLP default http endpoint
 #[cfg(feature = "http")]
 pub const DEFAULT_HTTP_ENDPOINT: &str = "http://localhost:4318";
@@ -48,19 +58,81 @@ mod trace_envs {
     pub const OTEL_EXPORTER_OTLP_TRACES_HEADERS: &str = "OTEL_EXPORTER_OTLP_TRACES_HEADERS";
     /// Env key: OTEL_EXPORTER_OTLP_TRACES_PROTOCOL
     pub const OTEL_EXPORTER_OTLP_TRACES_PROTOCOL: &str = "OTEL_EXPORTER_OTLP_TRACES_PROTOCOL";
+    /// Env key: OTEL_BSP_SCHEDULE_DELAY
+    pub const OTEL_BSP_SCHEDULE_DELAY: &str = "OTEL_BSP_SCHEDULE_DELAY";
+    /// Env key: OTEL_BSP_EXPORT_TIMEOUT
+    pub const OTEL_BSP_EXPORT_TIMEOUT: &str = "OTEL_BSP_EXPORT_TIMEOUT";
+    /// Env key: OTEL_BSP_MAX_QUEUE_SIZE
+    pub const OTEL_BSP_MAX_QUEUE_SIZE: &str = "OTEL_BSP_MAX_QUEUE_SIZE";
+    /// Env key: OTEL_BSP_MAX_EXPORT_BATCH_SIZE
+    pub const OTEL_BSP_MAX_EXPORT_BATCH_SIZE: &str = "OTEL_BSP_MAX_EXPORT_BATCH_SIZE";
+    /// Env key: OTEL_TRACES_SAMPLER
+    pub const OTEL_TRACES_SAMPLER: &str = "OTEL_TRACES_SAMPLER";
+    /// Env key: OTEL_TRACES_SAMPLER_ARG
+    pub const OTEL_TRACES_SAMPLER_ARG: &str = "OTEL_TRACES_SAMPLER_ARG";
+    /// Env key: OTEL_PROPAGATORS
+    pub const OTEL_PROPAGATORS: &str = "OTEL_PROPAGATORS";
+    /// Env key: OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT
+    pub const OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT: &str = "OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT";
+    /// Env key: OTEL_ATTRIBUTE_COUNT_LIMIT
+    pub const OTEL_ATTRIBUTE_COUNT_LIMIT: &str = "OTEL_ATTRIBUTE_COUNT_LIMIT";
+    /// Env key: OTEL_SPAN_ATTRIBUTE_VALUE_LENGTH_LIMIT
+    pub const OTEL_SPAN_ATTRIBUTE_VALUE_LENGTH_LIMIT: &str =
+        "OTEL_SPAN_ATTRIBUTE_VALUE_LENGTH_LIMIT";
+    /// Env key: OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT
+    pub const OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT: &str = "OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT";
+    /// Env key: OTEL_SPAN_EVENT_COUNT_LIMIT
+    pub const OTEL_SPAN_EVENT_COUNT_LIMIT: &str = "OTEL_SPAN_EVENT_COUNT_LIMIT";
+    /// Env key: OTEL_SPAN_LINK_COUNT_LIMIT
+    pub const OTEL_SPAN_LINK_COUNT_LIMIT: &str = "OTEL_SPAN_LINK_COUNT_LIMIT";
+    /// Env key: OTEL_EVENT_ATTRIBUTE_COUNT_LIMIT
+    pub const OTEL_EVENT_ATTRIBUTE_COUNT_LIMIT: &str = "OTEL_EVENT_ATTRIBUTE_COUNT_LIMIT";
+    /// Env key: OTEL_LINK_ATTRIBUTE_COUNT_LIMIT
+    pub const OTEL_LINK_ATTRIBUTE_COUNT_LIMIT: &str = "OTEL_LINK_ATTRIBUTE_COUNT_LIMIT";
 }
 use std::{
     collections::HashMap,
     ffi::OsString,
     fmt::{self, Display},
     fs,
+    net::SocketAddr,
+    str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 
 use http::{uri::Scheme, Uri};
+use opentelemetry_api::KeyValue;
+use opentelemetry_sdk::Resource;
+use percent_encoding::percent_decode_str;
 #[cfg(feature = "traces")]
 pub use trace_envs::*;
 
+mod circuit_breaker;
+#[cfg(feature = "traces")]
+mod export_limits;
+#[cfg(feature = "_config-file")]
+pub mod file;
+mod rate_limit;
+mod redaction;
+mod source;
+mod tail_sampling;
+
+pub use circuit_breaker::{
+    CircuitBreaker, DEFAULT_CIRCUIT_FAILURE_THRESHOLD, DEFAULT_CIRCUIT_OPEN_DURATION,
+};
+#[cfg(feature = "traces")]
+pub use export_limits::ExportLimits;
+pub use rate_limit::{OverflowPolicy, RateLimit, DEFAULT_RATE_LIMIT_BURST};
+pub use redaction::RedactionHook;
+#[cfg(feature = "redaction")]
+pub use redaction::{MaskRule, RedactionRules, DEFAULT_MASK_REPLACEMENT};
+pub use source::{EnvSource, Prefixed, ProcessEnv};
+pub use tail_sampling::{
+    SamplingPolicy, SpanStatusCode, TailSampling, DEFAULT_TAIL_SAMPLING_DECISION_WAIT,
+    DEFAULT_TAIL_SAMPLING_MAX_SPANS, DEFAULT_TAIL_SAMPLING_MAX_TRACES,
+};
+
 #[cfg(feature = "metrics")]
 mod metric_envs {
     /// Env key: OTEL_EXPORTER_OTLP_METRICS_ENDPOINT
@@ -115,6 +187,8 @@ mod grpc {
     #[cfg(feature = "grpcio")]
     pub mod grpcio {
         #[derive(Clone, Debug, Eq, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(default))]
         pub struct GrpcioConfig {
             cq_count: usize,
         }
@@ -143,12 +217,17 @@ mod grpc {
     #[cfg(feature = "tonic")]
     pub mod tonic {
         #[derive(Clone, Debug, Default, Eq, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct TonicConfig {
+            #[cfg_attr(feature = "serde", serde(skip))]
             _holder: (),
         }
     }
 
+    /// It is tagged by `impl` in serde, like `{"impl": "grpcio", "cq_count": 2}`.
     #[derive(Clone, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(tag = "impl", rename_all = "lowercase"))]
     pub enum GrpcImpl {
         #[cfg(feature = "tonic")]
         Tonic(tonic::TonicConfig),
@@ -184,25 +263,20 @@ mod grpc {
 #[cfg(feature = "_grpc")]
 pub use grpc::GrpcImpl;
 
-use crate::error::{OtlpExporterError, OtlpExporterResult};
-
-macro_rules! set_from_env_with_default {
-    ($env_name:ident, $fn:expr, $default:expr $(,)?) => {
-        match std::env::var_os($env_name) {
-            Some(v) => match $fn(v) {
-                Some(v) => v,
-                None => $default,
-            },
-            None => $default,
-        }
-    };
-}
+#[cfg(all(feature = "traces", feature = "metrics"))]
+use crate::exporter::span_metrics::SpanMetrics;
+use crate::{
+    error::{OtlpExporterError, OtlpExporterResult},
+    exporter::self_metrics::ExporterMetrics,
+};
 
+/// An empty value is the same as unset, an invalid value is recorded in `$diagnostics`.
 macro_rules! set_from_env {
-    ($ident:expr, $env_name:ident, $fn:expr $(,)?) => {
-        if let Some(v) = std::env::var_os($env_name) {
-            if let Some(v) = $fn(v) {
-                $ident = v;
+    ($source:expr, $diagnostics:expr, $ident:expr, $env_name:ident, $fn:expr $(,)?) => {
+        if let Some(v) = $source.get($env_name).filter(|v| !v.is_empty()) {
+            match $fn(&v) {
+                Ok(v) => $ident = v,
+                Err(kind) => $diagnostics.push(EnvDiagnostic::new($env_name, &v, kind)),
             }
         }
     };
@@ -245,6 +319,263 @@ fn default_headers() -> HashMap<String, Vec<String>> {
     headers
 }
 
+/// Whether the SDK is disabled by env `OTEL_SDK_DISABLED`.
+pub fn sdk_disabled_from_env() -> bool {
+    sdk_disabled_from_source(&ProcessEnv)
+}
+
+/// Like [`sdk_disabled_from_env`], but read from `source`.
+pub fn sdk_disabled_from_source(source: &(impl EnvSource + ?Sized)) -> bool {
+    source
+        .get_string(OTEL_SDK_DISABLED)
+        .map(|v| v.trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Build a resource from env `OTEL_RESOURCE_ATTRIBUTES` and `OTEL_SERVICE_NAME`.
+///
+/// Keys and values of `OTEL_RESOURCE_ATTRIBUTES` are percent-decoded, invalid items are skipped.
+/// `OTEL_SERVICE_NAME` takes precedence over `service.name` in `OTEL_RESOURCE_ATTRIBUTES`.
+pub fn resource_from_env() -> Resource {
+    resource_from_source(&ProcessEnv)
+}
+
+/// Like [`resource_from_env`], but read from `source`.
+pub fn resource_from_source(source: &(impl EnvSource + ?Sized)) -> Resource {
+    let mut attributes = Vec::new();
+    if let Some(v) = source.get_string(OTEL_RESOURCE_ATTRIBUTES) {
+        for item in v.split(',') {
+            let Some((key, value)) = item.split_once('=') else {
+                continue;
+            };
+            let key = percent_decode_str(key.trim()).decode_utf8();
+            let value = percent_decode_str(value.trim()).decode_utf8();
+            if let (Ok(key), Ok(value)) = (key, value) {
+                if !key.is_empty() {
+                    attributes.push(KeyValue::new(key.into_owned(), value.into_owned()));
+                }
+            }
+        }
+    }
+    if let Some(service_name) = source.get_string(OTEL_SERVICE_NAME) {
+        if !service_name.is_empty() {
+            attributes.push(KeyValue::new("service.name", service_name));
+        }
+    }
+    // the latter one of duplicated keys wins
+    Resource::new(attributes)
+}
+
+/// Apply env `OTEL_BSP_SCHEDULE_DELAY`, `OTEL_BSP_EXPORT_TIMEOUT`(both in milliseconds),
+/// `OTEL_BSP_MAX_QUEUE_SIZE` and `OTEL_BSP_MAX_EXPORT_BATCH_SIZE` to `config`, invalid values are
+/// ignored.
+#[cfg(feature = "traces")]
+pub fn batch_config_from_env(
+    config: opentelemetry_sdk::trace::BatchConfig,
+) -> opentelemetry_sdk::trace::BatchConfig {
+    batch_config_from_source(&ProcessEnv, config)
+}
+
+/// Like [`batch_config_from_env`], but read from `source`.
+#[cfg(feature = "traces")]
+pub fn batch_config_from_source(
+    source: &(impl EnvSource + ?Sized),
+    config: opentelemetry_sdk::trace::BatchConfig,
+) -> opentelemetry_sdk::trace::BatchConfig {
+    batch_config_with_diagnostics(source, config, &mut Vec::new())
+}
+
+/// Like [`batch_config_from_source`], but invalid values are pushed to `diagnostics`.
+#[cfg(feature = "traces")]
+pub(crate) fn batch_config_with_diagnostics(
+    source: &(impl EnvSource + ?Sized),
+    mut config: opentelemetry_sdk::trace::BatchConfig,
+    diagnostics: &mut Vec<EnvDiagnostic>,
+) -> opentelemetry_sdk::trace::BatchConfig {
+    fn parse<T: FromStr>(
+        v: &OsString,
+        kind: EnvDiagnosticKind,
+    ) -> Result<Option<T>, EnvDiagnosticKind> {
+        v.to_str()
+            .ok_or(EnvDiagnosticKind::NotUnicode)?
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| kind)
+    }
+    fn parse_millis(v: &OsString) -> Result<Option<Duration>, EnvDiagnosticKind> {
+        parse(v, EnvDiagnosticKind::InvalidMillis).map(|ms| ms.map(Duration::from_millis))
+    }
+    fn parse_size(v: &OsString) -> Result<Option<usize>, EnvDiagnosticKind> {
+        parse(v, EnvDiagnosticKind::InvalidLimit)
+    }
+
+    let (mut delay, mut timeout, mut queue_size, mut batch_size) = (None, None, None, None);
+    set_from_env!(
+        source,
+        diagnostics,
+        delay,
+        OTEL_BSP_SCHEDULE_DELAY,
+        parse_millis
+    );
+    set_from_env!(
+        source,
+        diagnostics,
+        timeout,
+        OTEL_BSP_EXPORT_TIMEOUT,
+        parse_millis
+    );
+    set_from_env!(
+        source,
+        diagnostics,
+        queue_size,
+        OTEL_BSP_MAX_QUEUE_SIZE,
+        parse_size
+    );
+    set_from_env!(
+        source,
+        diagnostics,
+        batch_size,
+        OTEL_BSP_MAX_EXPORT_BATCH_SIZE,
+        parse_size
+    );
+    if let Some(delay) = delay {
+        config = config.with_scheduled_delay(delay);
+    }
+    if let Some(timeout) = timeout {
+        config = config.with_max_export_timeout(timeout);
+    }
+    if let Some(size) = queue_size {
+        config = config.with_max_queue_size(size);
+    }
+    if let Some(size) = batch_size {
+        config = config.with_max_export_batch_size(size);
+    }
+    config
+}
+
+/// Build a sampler from env `OTEL_TRACES_SAMPLER` and `OTEL_TRACES_SAMPLER_ARG`, `None` if it is
+/// not set.
+///
+/// Unsupported samplers(`jaeger_remote`, `parentbased_jaeger_remote`, `xray`) and unknown ones
+/// fall back to `parentbased_always_on`, an invalid ratio falls back to `1.0`. They are reported
+/// through the global error handler.
+#[cfg(feature = "traces")]
+pub fn sampler_from_env() -> Option<opentelemetry_sdk::trace::Sampler> {
+    sampler_from_source(&ProcessEnv)
+}
+
+/// Like [`sampler_from_env`], but read from `source`.
+#[cfg(feature = "traces")]
+pub fn sampler_from_source(
+    source: &(impl EnvSource + ?Sized),
+) -> Option<opentelemetry_sdk::trace::Sampler> {
+    use opentelemetry_api::global;
+
+    let mut diagnostics = Vec::new();
+    let sampler = sampler_with_diagnostics(source, &mut diagnostics);
+    for diagnostic in diagnostics {
+        global::handle_error(global::Error::Other(diagnostic.to_string()));
+    }
+    sampler
+}
+
+/// Like [`sampler_from_source`], but invalid values are pushed to `diagnostics`. An unset
+/// `OTEL_TRACES_SAMPLER_ARG` means `1.0`, it is not reported.
+#[cfg(feature = "traces")]
+pub(crate) fn sampler_with_diagnostics(
+    source: &(impl EnvSource + ?Sized),
+    diagnostics: &mut Vec<EnvDiagnostic>,
+) -> Option<opentelemetry_sdk::trace::Sampler> {
+    use opentelemetry_sdk::trace::Sampler;
+
+    fn parse_ratio(v: &OsString) -> Result<f64, EnvDiagnosticKind> {
+        match v
+            .to_str()
+            .ok_or(EnvDiagnosticKind::NotUnicode)?
+            .trim()
+            .parse()
+        {
+            Ok(r) if (0.0..=1.0).contains(&r) => Ok(r),
+            _ => Err(EnvDiagnosticKind::InvalidSamplerArg),
+        }
+    }
+
+    let sampler = source.get_string(OTEL_TRACES_SAMPLER)?;
+    let mut ratio = || {
+        let mut ratio = 1.0;
+        set_from_env!(
+            source,
+            diagnostics,
+            ratio,
+            OTEL_TRACES_SAMPLER_ARG,
+            parse_ratio
+        );
+        ratio
+    };
+    let sampler = match sampler.trim() {
+        "always_on" => Sampler::AlwaysOn,
+        "always_off" => Sampler::AlwaysOff,
+        "traceidratio" => Sampler::TraceIdRatioBased(ratio()),
+        "parentbased_always_on" => Sampler::ParentBased(Box::new(Sampler::AlwaysOn)),
+        "parentbased_always_off" => Sampler::ParentBased(Box::new(Sampler::AlwaysOff)),
+        "parentbased_traceidratio" => {
+            Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio())))
+        }
+        s => {
+            diagnostics.push(EnvDiagnostic::new(
+                OTEL_TRACES_SAMPLER,
+                s,
+                EnvDiagnosticKind::UnsupportedSampler,
+            ));
+            Sampler::ParentBased(Box::new(Sampler::AlwaysOn))
+        }
+    };
+    Some(sampler)
+}
+
+/// Build a composite propagator from env `OTEL_PROPAGATORS`, `None` if it is not set.
+///
+/// Only `tracecontext` and `baggage` are supported, others are skipped and reported through the
+/// global error handler. `none` means no propagator.
+#[cfg(feature = "traces")]
+pub fn propagator_from_env() -> Option<opentelemetry_sdk::propagation::TextMapCompositePropagator> {
+    propagator_from_source(&ProcessEnv)
+}
+
+/// Like [`propagator_from_env`], but read from `source`.
+#[cfg(feature = "traces")]
+pub fn propagator_from_source(
+    source: &(impl EnvSource + ?Sized),
+) -> Option<opentelemetry_sdk::propagation::TextMapCompositePropagator> {
+    use opentelemetry_api::{global, propagation::TextMapPropagator};
+    use opentelemetry_sdk::propagation::{
+        BaggagePropagator, TextMapCompositePropagator, TraceContextPropagator,
+    };
+
+    let names = source.get_string(OTEL_PROPAGATORS)?;
+    let mut seen = Vec::new();
+    let mut propagators: Vec<Box<dyn TextMapPropagator + Send + Sync>> = Vec::new();
+    for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        if seen.contains(&name) {
+            continue;
+        }
+        seen.push(name);
+        match name {
+            "tracecontext" => propagators.push(Box::new(TraceContextPropagator::new())),
+            "baggage" => propagators.push(Box::new(BaggagePropagator::new())),
+            "none" => {}
+            name => global::handle_error(global::Error::Other(format!(
+                "unsupported propagator[{name}] in {OTEL_PROPAGATORS}, skipped"
+            ))),
+        }
+    }
+    if seen.contains(&"none") {
+        propagators.clear();
+    }
+    Some(TextMapCompositePropagator::new(propagators))
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Protocol {
     #[cfg(feature = "_grpc")]
@@ -268,8 +599,27 @@ impl Display for Protocol {
     }
 }
 
+impl FromStr for Protocol {
+    type Err = OtlpExporterError;
+
+    /// Parse the protocol name defined in the specification, like `http/protobuf`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "http")]
+            "http/protobuf" => Ok(Protocol::HttpProtobuf),
+            #[cfg(feature = "http-json")]
+            "http/json" => Ok(Protocol::HttpJson),
+            #[cfg(feature = "_grpc")]
+            "grpc" => Ok(Protocol::Grpc),
+            _ => Err(OtlpExporterError::ConfigError(format!(
+                "protocol[{s}] is unknown or its feature is not enabled"
+            ))),
+        }
+    }
+}
+
 /// The type of data for the OTLP exporter.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum DataType {
     /// Trace data.
     #[cfg(feature = "traces")]
@@ -284,29 +634,172 @@ pub enum DataType {
     Log,
 }
 
+impl DataType {
+    /// The path appended to the generic endpoint for OTLP/HTTP.
+    #[cfg(feature = "http")]
+    fn signal_path(self) -> &'static str {
+        match self {
+            #[cfg(feature = "traces")]
+            DataType::Trace => "/v1/traces",
+            #[cfg(feature = "metrics")]
+            DataType::Metric => "/v1/metrics",
+            #[cfg(feature = "logs")]
+            DataType::Log => "/v1/logs",
+        }
+    }
+}
+
+/// Kinds of invalid env values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EnvDiagnosticKind {
+    /// The value is not valid unicode.
+    NotUnicode,
+    UnknownProtocol,
+    /// The protocol is known, but its feature is not enabled.
+    ProtocolNotEnabled,
+    /// It should be `true` or `false`, case insensitive.
+    InvalidBool,
+    /// It should be in milliseconds, or with a suffix of `ms`, `s` or `m`.
+    InvalidTimeout,
+    /// A header should be a `key=value` pair.
+    MalformedHeader,
+    /// The header name is not a valid HTTP header name.
+    InvalidHeaderName,
+    /// The header value is not valid percent-encoded UTF-8, or not a valid HTTP header value
+    /// after decoding.
+    InvalidHeaderValue,
+    /// It should be a non-negative integer.
+    InvalidLimit,
+    /// It should be a non-negative integer in milliseconds.
+    InvalidMillis,
+    /// The sampler is unknown or not supported, `parentbased_always_on` is used instead.
+    UnsupportedSampler,
+    /// It should be a ratio in `[0.0, 1.0]`, `1.0` is used instead.
+    InvalidSamplerArg,
+}
+
+impl Display for EnvDiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EnvDiagnosticKind::NotUnicode => "not unicode",
+            EnvDiagnosticKind::UnknownProtocol => "unknown protocol",
+            EnvDiagnosticKind::ProtocolNotEnabled => "the feature of the protocol is not enabled",
+            EnvDiagnosticKind::InvalidBool => "invalid bool",
+            EnvDiagnosticKind::InvalidTimeout => "invalid timeout",
+            EnvDiagnosticKind::MalformedHeader => "malformed header",
+            EnvDiagnosticKind::InvalidHeaderName => "invalid header name",
+            EnvDiagnosticKind::InvalidHeaderValue => "invalid header value",
+            EnvDiagnosticKind::InvalidLimit => "invalid limit",
+            EnvDiagnosticKind::InvalidMillis => "invalid milliseconds",
+            EnvDiagnosticKind::UnsupportedSampler => "unsupported sampler",
+            EnvDiagnosticKind::InvalidSamplerArg => "invalid sampler arg",
+        })
+    }
+}
+
+/// An invalid env value found by [`ConfigBuilder::with_env`], it is ignored unless in strict mode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EnvDiagnostic {
+    key: &'static str,
+    value: String,
+    kind: EnvDiagnosticKind,
+}
+
+impl EnvDiagnostic {
+    fn new(key: &'static str, value: impl AsRef<std::ffi::OsStr>, kind: EnvDiagnosticKind) -> Self {
+        Self {
+            key,
+            value: value.as_ref().to_string_lossy().into_owned(),
+            kind,
+        }
+    }
+
+    /// The env key.
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    /// The invalid value, or the invalid part of it.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn kind(&self) -> EnvDiagnosticKind {
+        self.kind
+    }
+}
+
+impl Display for EnvDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.key, self.value, self.kind)
+    }
+}
+
 /// Configuration builder for the OTLP exporter.
+///
+/// With feature `serde`, durations are human-readable strings like `10s`, the protocol is its
+/// spec name, and missing fields are set to default values. Use [`ConfigBuilder::redacted`] to
+/// serialize it without secrets.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ConfigBuilder {
+    /// The generic endpoint, the signal path is appended for OTLP/HTTP if the data type is set.
+    /// The default endpoint of the protocol is used if it is empty.
     endpoint: String,
 
+    /// The endpoint of the signal, it is used as is and takes precedence over `endpoint`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    signal_endpoint: Option<String>,
+
+    /// The type of data to export, it decides the signal path.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    data_type: Option<DataType>,
+
     /// Whether to enable client transport security for the exporter's gRPC connection. This option
     /// only applies to OTLP/gRPC when an endpoint is provided without the http or https scheme -
     /// OTLP/HTTP always uses the scheme provided for the endpoint.
     insecure: bool,
 
     /// Path of the trusted certificate(PEM format) to use when verifying a server's TLS credentials.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "serde_impl::os_string",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
     certificate_file: Option<OsString>,
 
     /// Path of the client's private key(PEM format) to use in mTLS communication.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "serde_impl::os_string",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
     client_key_file: Option<OsString>,
 
     /// Path of the client's certificate/chain to use in mTLS communication.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "serde_impl::os_string",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
     client_certificate_file: Option<OsString>,
 
     /// Key-value pairs to be used as headers associated with gRPC or HTTP requests.
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "serde_impl::deserialize_headers")
+    )]
     headers: HashMap<String, Vec<String>>,
 
     /// Maximum time the OTLP exporter will wait for each batch export.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
     timeout: Duration,
 
     /// The transport protocol.
@@ -317,106 +810,254 @@ pub struct ConfigBuilder {
     grpc_impl: GrpcImpl,
 
     /// Domain in the certificate.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     certificate_domain: Option<String>,
+
+    /// The address to connect to instead of resolving the host of the endpoint, the host is
+    /// still used for TLS and the authority of requests. It is set for resolved backends of
+    /// load balancing.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    connect_address: Option<SocketAddr>,
+
+    /// Endpoints to switch to in order when the primary endpoint is unavailable.
+    failover_endpoints: Vec<FailoverEndpoint>,
+
+    /// Number of consecutive failures before switching to the next endpoint.
+    failover_threshold: usize,
+
+    /// Interval of probing the primary endpoint after failing over.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    failback_probe_interval: Duration,
+
+    /// Limit of exported spans and bytes.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    rate_limit: Option<RateLimit>,
+
+    /// Fail fast while the collector keeps failing.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    circuit_breaker: Option<CircuitBreaker>,
+
+    /// Buffer spans by trace and export only kept traces.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    tail_sampling: Option<TailSampling>,
+
+    /// Built-in rules to redact attributes.
+    #[cfg(feature = "redaction")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    redaction: Option<RedactionRules>,
+
+    /// Limits of attributes, events and links enforced before spans are encoded.
+    #[cfg(feature = "traces")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    export_limits: Option<ExportLimits>,
+
+    /// A custom hook to redact attributes, it is applied after the built-in rules.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    redaction_hook: Option<Arc<dyn RedactionHook>>,
+
+    /// Invalid values found in env.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    diagnostics: Vec<EnvDiagnostic>,
+
+    /// Whether `build` fails if there is any diagnostic.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    strict: bool,
+
+    /// Self-observability metrics of exporters built from the config.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    metrics: Option<ExporterMetrics>,
+
+    /// RED metrics of spans passed to trace exporters built from the config.
+    #[cfg(all(feature = "traces", feature = "metrics"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span_metrics: Option<SpanMetrics>,
 }
 
 impl ConfigBuilder {
-    pub fn with_env(mut self, data_type: Option<DataType>) -> Self {
+    pub fn with_env(self, data_type: Option<DataType>) -> Self {
+        self.with_source(&ProcessEnv, data_type)
+    }
+
+    /// Like [`ConfigBuilder::with_env`], but read from `source` instead of the process env.
+    pub fn with_source(
+        mut self,
+        source: &(impl EnvSource + ?Sized),
+        data_type: Option<DataType>,
+    ) -> Self {
         macro_rules! set_all_from_env {
             ($c:ident, $timeout_key:ident, $insecure_key:ident, $certificate_key:ident, $client_key_key:ident, $client_certificate_key:ident, $headers_key:ident $(,)?) => {
-                set_from_env!($c.timeout, $timeout_key, parse_duration);
-                set_from_env!($c.insecure, $insecure_key, parse_bool);
-                set_from_env!($c.certificate_file, $certificate_key, |v| Some(Some(v)));
-                set_from_env!($c.client_key_file, $client_key_key, |v| Some(Some(v)));
-                set_from_env!($c.client_certificate_file, $client_certificate_key, |v| {
-                    Some(Some(v))
-                });
-                set_from_env!($c.headers, $headers_key, parse_headers);
+                set_from_env!(
+                    source,
+                    $c.diagnostics,
+                    $c.timeout,
+                    $timeout_key,
+                    parse_timeout
+                );
+                set_from_env!(
+                    source,
+                    $c.diagnostics,
+                    $c.insecure,
+                    $insecure_key,
+                    parse_bool
+                );
+                set_from_env!(
+                    source,
+                    $c.diagnostics,
+                    $c.certificate_file,
+                    $certificate_key,
+                    parse_path
+                );
+                set_from_env!(
+                    source,
+                    $c.diagnostics,
+                    $c.client_key_file,
+                    $client_key_key,
+                    parse_path
+                );
+                set_from_env!(
+                    source,
+                    $c.diagnostics,
+                    $c.client_certificate_file,
+                    $client_certificate_key,
+                    parse_path
+                );
+                if let Some(v) = source.get($headers_key).filter(|v| !v.is_empty()) {
+                    match v.to_str() {
+                        Some(h) => {
+                            let mut invalid = Vec::new();
+                            let headers = parse_headers(h, &mut invalid);
+                            merge_headers(&mut $c.headers, headers);
+                            $c.diagnostics.extend(
+                                invalid.into_iter().map(|(item, kind)| {
+                                    EnvDiagnostic::new($headers_key, item, kind)
+                                }),
+                            );
+                        }
+                        None => $c.diagnostics.push(EnvDiagnostic::new(
+                            $headers_key,
+                            &v,
+                            EnvDiagnosticKind::NotUnicode,
+                        )),
+                    }
+                }
             };
         }
 
-        fn parse_protocol(p: OsString) -> Option<Protocol> {
-            match p.to_str()? {
-                #[cfg(feature = "http")]
-                "http/protobuf" => Some(Protocol::HttpProtobuf),
-                #[cfg(feature = "http-json")]
-                "http/json" => Some(Protocol::HttpJson),
-                #[cfg(feature = "_grpc")]
-                "grpc" => Some(Protocol::Grpc),
-                _ => None,
-            }
+        fn to_str(v: &OsString) -> Result<&str, EnvDiagnosticKind> {
+            v.to_str().ok_or(EnvDiagnosticKind::NotUnicode)
+        }
+
+        fn parse_protocol(p: &OsString) -> Result<Protocol, EnvDiagnosticKind> {
+            let p = to_str(p)?.trim();
+            p.parse().map_err(|_| match p {
+                "grpc" | "http/protobuf" | "http/json" => EnvDiagnosticKind::ProtocolNotEnabled,
+                _ => EnvDiagnosticKind::UnknownProtocol,
+            })
+        }
+
+        fn parse_endpoint(e: &OsString) -> Result<String, EnvDiagnosticKind> {
+            to_str(e).map(ToString::to_string)
+        }
+
+        fn parse_path(p: &OsString) -> Result<Option<OsString>, EnvDiagnosticKind> {
+            Ok(Some(p.clone()))
         }
 
-        fn parse_duration(t: OsString) -> Option<Duration> {
-            let mut t = t.to_str()?;
-            // support format like: 10s
-            if t.ends_with(|c| c == 's' || c == 'S') {
-                t = &t[0..t.len() - 1];
+        /// Parse a timeout in milliseconds, suffixes `ms`, `s` and `m` are supported too.
+        fn parse_timeout(t: &OsString) -> Result<Duration, EnvDiagnosticKind> {
+            let t = to_str(t)?.trim().to_ascii_lowercase();
+            let (value, unit) = match t.find(|c: char| !c.is_ascii_digit()) {
+                Some(idx) => t.split_at(idx),
+                None => (t.as_str(), "ms"),
+            };
+            let value: u64 = value
+                .parse()
+                .map_err(|_| EnvDiagnosticKind::InvalidTimeout)?;
+            match unit {
+                "ms" => Ok(Duration::from_millis(value)),
+                "s" => Ok(Duration::from_secs(value)),
+                "m" => value
+                    .checked_mul(60)
+                    .map(Duration::from_secs)
+                    .ok_or(EnvDiagnosticKind::InvalidTimeout),
+                _ => Err(EnvDiagnosticKind::InvalidTimeout),
             }
-            t.parse().ok().map(Duration::from_secs)
         }
 
-        fn parse_bool(b: OsString) -> Option<bool> {
-            b.to_str()?.parse().ok()
+        fn parse_bool(b: &OsString) -> Result<bool, EnvDiagnosticKind> {
+            match to_str(b)?.trim().to_ascii_lowercase().as_str() {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(EnvDiagnosticKind::InvalidBool),
+            }
         }
 
-        fn parse_headers(h: OsString) -> Option<HashMap<String, Vec<String>>> {
-            // Parse headers according to:
+        /// Invalid items are pushed to `invalid`.
+        fn parse_headers<'a>(
+            h: &'a str,
+            invalid: &mut Vec<(&'a str, EnvDiagnosticKind)>,
+        ) -> HashMap<String, Vec<String>> {
+            // Parse headers in the W3C baggage format according to:
             // https://github.com/open-telemetry/opentelemetry-specification/blob/main/specification/protocol/exporter.md#specifying-headers-via-environment-variables
-            let items = h.to_str()?.split(',');
-            let mut res = default_headers();
-            for item in items {
-                if let Some((key, value)) = item.split_once('=') {
-                    let key = key.trim();
-                    if key.is_empty() {
-                        continue;
-                    }
-                    res.entry(key.to_string())
-                        .or_default()
-                        .push(value.trim().to_string());
+            let mut res: HashMap<String, Vec<String>> = HashMap::new();
+            for item in h.split(',') {
+                if item.trim().is_empty() {
+                    continue;
+                }
+                match parse_header(item) {
+                    Ok((key, value)) => res.entry(key).or_default().push(value),
+                    Err(kind) => invalid.push((item, kind)),
                 }
             }
-            Some(res)
+            res
         }
 
-        fn gen_endpoint(
-            cur_protocol: Protocol,
-            default_protocol: Protocol,
-            path: &str,
-            endpoint: &str,
-        ) -> String {
-            #[cfg(feature = "_grpc")]
-            #[allow(irrefutable_let_patterns)]
-            if let Protocol::Grpc = cur_protocol {
-                // there is no need to append path
-                if cur_protocol == default_protocol {
-                    return endpoint.to_owned();
-                } else {
-                    return default_endpoint(cur_protocol).to_owned();
-                }
+        /// Parse a `key=value;properties` item, properties are dropped.
+        fn parse_header(item: &str) -> Result<(String, String), EnvDiagnosticKind> {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or(EnvDiagnosticKind::MalformedHeader)?;
+            let key = key.trim();
+            if key.is_empty() {
+                return Err(EnvDiagnosticKind::MalformedHeader);
             }
-            if cur_protocol == default_protocol {
-                format!("{}{}", endpoint.trim_end_matches('/'), path)
-            } else {
-                format!(
-                    "{}{}",
-                    default_endpoint(cur_protocol).trim_end_matches('/'),
-                    path
-                )
+            http::HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| EnvDiagnosticKind::InvalidHeaderName)?;
+            let value = value.split(';').next().unwrap_or_default().trim();
+            let value = percent_encoding::percent_decode_str(value)
+                .decode_utf8()
+                .map_err(|_| EnvDiagnosticKind::InvalidHeaderValue)?;
+            http::HeaderValue::from_str(&value)
+                .map_err(|_| EnvDiagnosticKind::InvalidHeaderValue)?;
+            Ok((key.to_string(), value.into_owned()))
+        }
+
+        /// Headers from env replace the ones with the same name, case insensitive, others are
+        /// kept.
+        fn merge_headers(
+            headers: &mut HashMap<String, Vec<String>>,
+            env_headers: HashMap<String, Vec<String>>,
+        ) {
+            for (key, values) in env_headers {
+                headers.retain(|k, _| !k.eq_ignore_ascii_case(&key));
+                headers.insert(key, values);
             }
         }
 
-        let protocol = set_from_env_with_default!(
+        // Values which are not set in env are kept, the endpoint is resolved in `build`.
+        set_from_env!(
+            source,
+            self.diagnostics,
+            self.protocol,
             OTEL_EXPORTER_OTLP_PROTOCOL,
-            parse_protocol,
-            default_protocol(),
+            parse_protocol
         );
-        self.protocol = protocol;
-
-        self.endpoint = set_from_env_with_default!(
+        set_from_env!(
+            source,
+            self.diagnostics,
+            self.endpoint,
             OTEL_EXPORTER_OTLP_ENDPOINT,
-            |e: OsString| e.to_str().map(ToString::to_string),
-            default_endpoint(self.protocol).to_string(),
+            parse_endpoint
         );
 
         set_all_from_env!(
@@ -432,17 +1073,20 @@ impl ConfigBuilder {
         match data_type {
             #[cfg(feature = "traces")]
             Some(DataType::Trace) => {
-                let trace_protocol = set_from_env_with_default!(
+                self.data_type = Some(DataType::Trace);
+                set_from_env!(
+                    source,
+                    self.diagnostics,
+                    self.protocol,
                     OTEL_EXPORTER_OTLP_TRACES_PROTOCOL,
-                    parse_protocol,
-                    protocol
+                    parse_protocol
                 );
-                self.protocol = trace_protocol;
-
-                self.endpoint = set_from_env_with_default!(
+                set_from_env!(
+                    source,
+                    self.diagnostics,
+                    self.signal_endpoint,
                     OTEL_EXPORTER_OTLP_TRACES_ENDPOINT,
-                    |e: OsString| e.to_str().map(ToString::to_string),
-                    gen_endpoint(trace_protocol, protocol, "/v1/traces", &self.endpoint)
+                    |e| parse_endpoint(e).map(Some)
                 );
 
                 set_all_from_env!(
@@ -454,20 +1098,32 @@ impl ConfigBuilder {
                     OTEL_EXPORTER_OTLP_TRACES_CLIENT_CERTIFICATE,
                     OTEL_EXPORTER_OTLP_TRACES_HEADERS
                 );
+
+                let export_limits = self
+                    .export_limits
+                    .take()
+                    .unwrap_or_default()
+                    .with_source(source, &mut self.diagnostics);
+                if !export_limits.is_unlimited() {
+                    self.export_limits = Some(export_limits);
+                }
             },
             #[cfg(feature = "metrics")]
             Some(DataType::Metric) => {
-                let metric_protocol = set_from_env_with_default!(
+                self.data_type = Some(DataType::Metric);
+                set_from_env!(
+                    source,
+                    self.diagnostics,
+                    self.protocol,
                     OTEL_EXPORTER_OTLP_METRICS_PROTOCOL,
-                    parse_protocol,
-                    protocol
+                    parse_protocol
                 );
-                self.protocol = metric_protocol;
-
-                self.endpoint = set_from_env_with_default!(
+                set_from_env!(
+                    source,
+                    self.diagnostics,
+                    self.signal_endpoint,
                     OTEL_EXPORTER_OTLP_METRICS_ENDPOINT,
-                    |e: OsString| e.to_str().map(ToString::to_string),
-                    gen_endpoint(metric_protocol, protocol, "/v1/metrics", &self.endpoint)
+                    |e| parse_endpoint(e).map(Some)
                 );
 
                 set_all_from_env!(
@@ -482,17 +1138,20 @@ impl ConfigBuilder {
             },
             #[cfg(feature = "logs")]
             Some(DataType::Log) => {
-                let log_protocol = set_from_env_with_default!(
+                self.data_type = Some(DataType::Log);
+                set_from_env!(
+                    source,
+                    self.diagnostics,
+                    self.protocol,
                     OTEL_EXPORTER_OTLP_LOGS_PROTOCOL,
-                    parse_protocol,
-                    protocol
+                    parse_protocol
                 );
-                self.protocol = log_protocol;
-
-                self.endpoint = set_from_env_with_default!(
+                set_from_env!(
+                    source,
+                    self.diagnostics,
+                    self.signal_endpoint,
                     OTEL_EXPORTER_OTLP_LOGS_ENDPOINT,
-                    |e: OsString| e.to_str().map(ToString::to_string),
-                    gen_endpoint(log_protocol, protocol, "/v1/logs", &self.endpoint)
+                    |e| parse_endpoint(e).map(Some)
                 );
 
                 set_all_from_env!(
@@ -512,8 +1171,87 @@ impl ConfigBuilder {
         self
     }
 
+    /// Like [`ConfigBuilder::with_env`], but fails if there is any invalid env value.
+    pub fn try_with_env(self, data_type: Option<DataType>) -> OtlpExporterResult<Self> {
+        self.try_with_source(&ProcessEnv, data_type)
+    }
+
+    /// Like [`ConfigBuilder::with_source`], but fails if there is any invalid value.
+    pub fn try_with_source(
+        self,
+        source: &(impl EnvSource + ?Sized),
+        data_type: Option<DataType>,
+    ) -> OtlpExporterResult<Self> {
+        let count = self.diagnostics.len();
+        let builder = self.with_source(source, data_type);
+        if builder.diagnostics.len() > count {
+            return Err(OtlpExporterError::ConfigError(
+                builder.diagnostics[count..]
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+        Ok(builder)
+    }
+
+    /// Invalid env values found by [`ConfigBuilder::with_env`].
+    pub fn diagnostics(&self) -> &[EnvDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Record invalid env values read outside of the builder, e.g. `OTEL_BSP_*` of the pipeline,
+    /// so that they fail `build` in strict mode too.
+    #[cfg(feature = "traces")]
+    pub(crate) fn with_diagnostics(
+        mut self,
+        diagnostics: impl IntoIterator<Item = EnvDiagnostic>,
+    ) -> Self {
+        self.diagnostics.extend(diagnostics);
+        self
+    }
+
+    /// In strict mode, `build` fails if there is any invalid env value. Otherwise, only an
+    /// invalid timeout fails it, others are ignored.
+    pub fn with_strict_env(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set the generic endpoint like `OTEL_EXPORTER_OTLP_ENDPOINT`, the signal path like
+    /// `/v1/traces` is appended for OTLP/HTTP, it overrides the endpoint of the signal.
     pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
         self.endpoint = endpoint.into();
+        self.signal_endpoint = None;
+        self
+    }
+
+    /// Set the endpoint of the signal like `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`, it is used as
+    /// is.
+    pub fn with_signal_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.signal_endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Set the type of data to export, it decides the signal path appended to the generic
+    /// endpoint. It is set by `with_env` with a data type, and by pipelines.
+    pub fn with_data_type(mut self, data_type: DataType) -> Self {
+        self.data_type = Some(data_type);
+        self
+    }
+
+    /// Record self-observability metrics of exporters into `metrics`.
+    pub fn with_metrics(mut self, metrics: ExporterMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record RED metrics of spans into `span_metrics` after they are redacted, and before they
+    /// are sampled by tail sampling or exported.
+    #[cfg(all(feature = "traces", feature = "metrics"))]
+    pub fn with_span_metrics(mut self, span_metrics: SpanMetrics) -> Self {
+        self.span_metrics = Some(span_metrics);
         self
     }
 
@@ -580,16 +1318,187 @@ impl ConfigBuilder {
         self
     }
 
+    /// Connect to `address` instead of resolving the host of the endpoint.
+    #[cfg(feature = "traces")]
+    pub(crate) fn with_connect_address(mut self, address: SocketAddr) -> Self {
+        self.connect_address = Some(address);
+        self
+    }
+
+    /// Add an endpoint to switch to when the previous endpoints are unavailable. The endpoint of
+    /// [`ConfigBuilder::with_endpoint`] is the primary one. Like it, the signal path like
+    /// `/v1/traces` is appended for OTLP/HTTP.
+    pub fn add_failover_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.failover_endpoints
+            .push(FailoverEndpoint::Generic(endpoint.into()));
+        self
+    }
+
+    /// Add a failover endpoint which is used as is, like
+    /// [`ConfigBuilder::with_signal_endpoint`].
+    pub fn add_failover_signal_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.failover_endpoints.push(FailoverEndpoint::Signal {
+            signal_endpoint: endpoint.into(),
+        });
+        self
+    }
+
+    /// Replace failover endpoints with generic `endpoints`, see
+    /// [`ConfigBuilder::add_failover_endpoint`].
+    pub fn with_failover_endpoints<I, E>(mut self, endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<String>,
+    {
+        self.failover_endpoints = endpoints
+            .into_iter()
+            .map(|endpoint| FailoverEndpoint::Generic(endpoint.into()))
+            .collect();
+        self
+    }
+
+    pub fn with_failover_threshold(mut self, failover_threshold: usize) -> Self {
+        self.failover_threshold = failover_threshold;
+        self
+    }
+
+    pub fn with_failback_probe_interval(mut self, failback_probe_interval: Duration) -> Self {
+        self.failback_probe_interval = failback_probe_interval;
+        self
+    }
+
+    /// Limit spans and bytes exported per second, it applies to the failover endpoints as a whole.
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Fail batches fast after consecutive failures of an endpoint, each failover endpoint and
+    /// load-balanced backend has its own circuit.
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Buffer spans by their trace ids and export only traces kept by the policies of
+    /// `tail_sampling`. It applies before the rate limit.
+    pub fn with_tail_sampling(mut self, tail_sampling: TailSampling) -> Self {
+        self.tail_sampling = Some(tail_sampling);
+        self
+    }
+
+    /// Truncate attributes, events and links of spans over `export_limits` before they are
+    /// encoded, limits in env are read by [`ConfigBuilder::with_env`] too.
+    #[cfg(feature = "traces")]
+    pub fn with_export_limits(mut self, export_limits: ExportLimits) -> Self {
+        self.export_limits = Some(export_limits);
+        self
+    }
+
+    /// Redact attributes of spans, span events and span links with built-in rules before they
+    /// are encoded.
+    #[cfg(feature = "redaction")]
+    pub fn with_redaction(mut self, redaction: RedactionRules) -> Self {
+        self.redaction = Some(redaction);
+        self
+    }
+
+    /// Redact attributes with `hook` before they are encoded, after the built-in rules.
+    pub fn with_redaction_hook(mut self, hook: impl RedactionHook + 'static) -> Self {
+        self.redaction_hook = Some(Arc::new(hook));
+        self
+    }
+
     pub fn build(self) -> OtlpExporterResult<Config> {
         self.try_into()
     }
 }
 
+#[cfg(feature = "serde")]
+impl ConfigBuilder {
+    /// Serialize values of headers as [`REDACTED`], they usually carry credentials.
+    pub fn redacted(&self) -> Redacted<'_> {
+        Redacted(self)
+    }
+}
+
+/// Placeholder of redacted values.
+#[cfg(feature = "serde")]
+pub const REDACTED: &str = "<redacted>";
+
+/// A [`ConfigBuilder`] serialized without secrets, created by [`ConfigBuilder::redacted`].
+#[cfg(feature = "serde")]
+pub struct Redacted<'a>(&'a ConfigBuilder);
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Redacted<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut builder = self.0.clone();
+        builder
+            .headers
+            .values_mut()
+            .flatten()
+            .for_each(|v| *v = REDACTED.to_owned());
+        builder.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use std::collections::HashMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{default_headers, Protocol};
+
+    pub(super) mod os_string {
+        use std::ffi::OsString;
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(v: &Option<OsString>, s: S) -> Result<S::Ok, S::Error> {
+            match v {
+                Some(v) => s.serialize_some(&v.to_string_lossy()),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<OsString>, D::Error> {
+            Ok(Option::<String>::deserialize(d)?.map(Into::into))
+        }
+    }
+
+    /// Headers are merged into the default headers.
+    pub(super) fn deserialize_headers<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<HashMap<String, Vec<String>>, D::Error> {
+        let mut headers = default_headers();
+        headers.extend(HashMap::<String, Vec<String>>::deserialize(d)?);
+        Ok(headers)
+    }
+
+    impl Serialize for Protocol {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Protocol {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer)?
+                .parse()
+                .map_err(D::Error::custom)
+        }
+    }
+}
+
 impl Default for ConfigBuilder {
     fn default() -> Self {
         let protocol = default_protocol();
         Self {
-            endpoint: default_endpoint(protocol).to_string(),
+            endpoint: String::new(),
+            signal_endpoint: None,
+            data_type: None,
             insecure: false,
             certificate_file: None,
             client_key_file: None,
@@ -600,14 +1509,44 @@ impl Default for ConfigBuilder {
             #[cfg(feature = "_grpc")]
             grpc_impl: Default::default(),
             certificate_domain: None,
+            connect_address: None,
+            failover_endpoints: Vec::new(),
+            failover_threshold: DEFAULT_FAILOVER_THRESHOLD,
+            failback_probe_interval: DEFAULT_FAILBACK_PROBE_INTERVAL,
+            rate_limit: None,
+            circuit_breaker: None,
+            tail_sampling: None,
+            #[cfg(feature = "redaction")]
+            redaction: None,
+            redaction_hook: None,
+            #[cfg(feature = "traces")]
+            export_limits: None,
+            diagnostics: Vec::new(),
+            strict: false,
+            metrics: None,
+            #[cfg(all(feature = "traces", feature = "metrics"))]
+            span_metrics: None,
         }
     }
 }
 
+/// A failover endpoint, generic ones are serialized as strings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+enum FailoverEndpoint {
+    /// The signal path is appended for OTLP/HTTP.
+    Generic(String),
+
+    /// Used as is.
+    Signal { signal_endpoint: String },
+}
+
 /// Configuration for the OTLP exporter.
 #[derive(Clone, Debug)]
 pub struct Config {
     endpoint: Uri,
+    failover_endpoints: Vec<Uri>,
     builder: ConfigBuilder,
 }
 
@@ -647,6 +1586,26 @@ impl Config {
         Ok(res)
     }
 
+    pub fn metrics(&self) -> Option<&ExporterMetrics> {
+        self.builder.metrics.as_ref()
+    }
+
+    #[cfg(all(feature = "traces", feature = "metrics"))]
+    pub fn span_metrics(&self) -> Option<&SpanMetrics> {
+        self.builder.span_metrics.as_ref()
+    }
+
+    /// The config without span metrics, for the exporter wrapped by the recorder.
+    #[cfg(feature = "traces")]
+    #[cfg_attr(not(feature = "metrics"), allow(unused_mut))]
+    pub(crate) fn without_span_metrics(mut self) -> Self {
+        #[cfg(feature = "metrics")]
+        {
+            self.builder.span_metrics = None;
+        }
+        self
+    }
+
     pub fn headers(&self) -> &HashMap<String, Vec<String>> {
         &self.builder.headers
     }
@@ -667,6 +1626,199 @@ impl Config {
     pub fn certificate_domain(&self) -> Option<&str> {
         self.builder.certificate_domain.as_deref()
     }
+
+    /// The address to connect to instead of resolving the host of the endpoint.
+    pub(crate) fn connect_address(&self) -> Option<SocketAddr> {
+        self.builder.connect_address
+    }
+
+    pub fn failover_endpoints(&self) -> &[Uri] {
+        &self.failover_endpoints
+    }
+
+    pub fn failover_threshold(&self) -> usize {
+        self.builder.failover_threshold
+    }
+
+    pub fn failback_probe_interval(&self) -> Duration {
+        self.builder.failback_probe_interval
+    }
+
+    pub fn rate_limit(&self) -> Option<&RateLimit> {
+        self.builder.rate_limit.as_ref()
+    }
+
+    /// The config without the rate limit, for the exporter wrapped by the rate limiter.
+    #[cfg(feature = "traces")]
+    pub(crate) fn without_rate_limit(mut self) -> Self {
+        self.builder.rate_limit = None;
+        self
+    }
+
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.builder.circuit_breaker.as_ref()
+    }
+
+    pub fn tail_sampling(&self) -> Option<&TailSampling> {
+        self.builder.tail_sampling.as_ref()
+    }
+
+    /// The config without tail sampling, for the exporter wrapped by the sampler.
+    #[cfg(feature = "traces")]
+    pub(crate) fn without_tail_sampling(mut self) -> Self {
+        self.builder.tail_sampling = None;
+        self
+    }
+
+    #[cfg(feature = "redaction")]
+    pub fn redaction(&self) -> Option<&RedactionRules> {
+        self.builder.redaction.as_ref()
+    }
+
+    pub fn redaction_hook(&self) -> Option<&Arc<dyn RedactionHook>> {
+        self.builder.redaction_hook.as_ref()
+    }
+
+    #[cfg(feature = "traces")]
+    pub(crate) fn has_redaction(&self) -> bool {
+        #[cfg(feature = "redaction")]
+        if self.builder.redaction.is_some() {
+            return true;
+        }
+        self.builder.redaction_hook.is_some()
+    }
+
+    /// Hooks to apply in order, the built-in rules are compiled first.
+    #[cfg(feature = "traces")]
+    pub(crate) fn redaction_hooks(&self) -> OtlpExporterResult<Vec<Arc<dyn RedactionHook>>> {
+        let mut hooks: Vec<Arc<dyn RedactionHook>> = Vec::new();
+        #[cfg(feature = "redaction")]
+        if let Some(redaction) = &self.builder.redaction {
+            hooks.push(Arc::new(redaction.compile()?));
+        }
+        hooks.extend(self.builder.redaction_hook.clone());
+        Ok(hooks)
+    }
+
+    /// The config without redaction, for the exporter wrapped by the redactor.
+    #[cfg(feature = "traces")]
+    pub(crate) fn without_redaction(mut self) -> Self {
+        #[cfg(feature = "redaction")]
+        {
+            self.builder.redaction = None;
+        }
+        self.builder.redaction_hook = None;
+        self
+    }
+
+    #[cfg(feature = "traces")]
+    pub fn export_limits(&self) -> Option<&ExportLimits> {
+        self.builder.export_limits.as_ref()
+    }
+
+    /// The config without export limits, for the exporter wrapped by the truncator.
+    #[cfg(feature = "traces")]
+    pub(crate) fn without_export_limits(mut self) -> Self {
+        self.builder.export_limits = None;
+        self
+    }
+
+    /// The config without the circuit breaker, for the exporter wrapped by the breaker.
+    #[cfg(feature = "traces")]
+    pub(crate) fn without_circuit_breaker(mut self) -> Self {
+        self.builder.circuit_breaker = None;
+        self
+    }
+
+    /// Split into a config for each endpoint without failover endpoints, the primary one is the
+    /// first.
+    pub fn split_failover_endpoints(&self) -> Vec<Config> {
+        std::iter::once(&self.endpoint)
+            .chain(&self.failover_endpoints)
+            .map(|endpoint| {
+                let mut builder = self.builder.clone();
+                builder.signal_endpoint = Some(endpoint.to_string());
+                builder.failover_endpoints.clear();
+                Config {
+                    endpoint: endpoint.clone(),
+                    failover_endpoints: Vec::new(),
+                    builder,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parse `endpoint`, the scheme is set according to `insecure` if it is not http or https.
+fn parse_endpoint(endpoint: &str, insecure: bool) -> OtlpExporterResult<Uri> {
+    let mut endpoint_parts = Uri::try_from(endpoint)
+        .map_err(|e| {
+            OtlpExporterError::ConfigError(format!(
+                "endpoint[{}] is not a valid uri: {}",
+                endpoint, e
+            ))
+        })?
+        .into_parts();
+
+    let scheme = endpoint_parts.scheme.as_ref().map(Scheme::as_str);
+    if scheme != Some("http") && scheme != Some("https") {
+        if insecure {
+            endpoint_parts.scheme = Some(Scheme::HTTP);
+        } else {
+            endpoint_parts.scheme = Some(Scheme::HTTPS);
+        }
+    }
+    if endpoint_parts.path_and_query.is_none() {
+        // It is insane, parts without path will failed to construct a uri
+        endpoint_parts.path_and_query = "/".try_into().ok();
+    }
+
+    endpoint_parts.try_into().map_err(|e| {
+        OtlpExporterError::UnknownError(format!(
+            "internal error! endpoint_parts should be valid: {}",
+            e
+        ))
+    })
+}
+
+/// Resolve the endpoint according to:
+/// https://github.com/open-telemetry/opentelemetry-specification/blob/v1.21.0/specification/protocol/exporter.md#endpoint-urls-for-otlphttp
+///
+/// The endpoint of the signal is used as is. Otherwise the signal path is appended to the generic
+/// endpoint(or the default one of the protocol) for OTLP/HTTP, gRPC has no signal path.
+fn resolve_endpoint(builder: &ConfigBuilder) -> String {
+    match &builder.signal_endpoint {
+        Some(endpoint) => endpoint.clone(),
+        None => resolve_generic_endpoint(builder, &builder.endpoint),
+    }
+}
+
+/// Resolve a failover endpoint like the primary one.
+fn resolve_failover_endpoint(builder: &ConfigBuilder, endpoint: &FailoverEndpoint) -> String {
+    match endpoint {
+        FailoverEndpoint::Generic(endpoint) => resolve_generic_endpoint(builder, endpoint),
+        FailoverEndpoint::Signal { signal_endpoint } => signal_endpoint.clone(),
+    }
+}
+
+/// Append the signal path of the protocol and the data type to the generic `endpoint`.
+fn resolve_generic_endpoint(builder: &ConfigBuilder, endpoint: &str) -> String {
+    let endpoint = match endpoint {
+        "" => default_endpoint(builder.protocol),
+        endpoint => endpoint,
+    };
+    let signal_path: Option<&str> = match builder.protocol {
+        #[cfg(feature = "_grpc")]
+        Protocol::Grpc => None,
+        #[cfg(feature = "http")]
+        Protocol::HttpProtobuf => builder.data_type.map(DataType::signal_path),
+        #[cfg(feature = "http-json")]
+        Protocol::HttpJson => builder.data_type.map(DataType::signal_path),
+    };
+    match signal_path {
+        Some(path) => format!("{}{path}", endpoint.trim_end_matches('/')),
+        None => endpoint.to_owned(),
+    }
 }
 
 /// Check if values in ConfigBuilder are valid
@@ -674,35 +1826,46 @@ impl TryFrom<ConfigBuilder> for Config {
     type Error = OtlpExporterError;
 
     fn try_from(builder: ConfigBuilder) -> Result<Self, Self::Error> {
-        let mut endpoint_parts = Uri::try_from(&builder.endpoint)
-            .map_err(|e| {
-                OtlpExporterError::ConfigError(format!(
-                    "endpoint[{}] is not a valid uri: {}",
-                    builder.endpoint, e
-                ))
-            })?
-            .into_parts();
-
-        let scheme = endpoint_parts.scheme.as_ref().map(Scheme::as_str);
-        if scheme != Some("http") && scheme != Some("https") {
-            if builder.insecure {
-                endpoint_parts.scheme = Some(Scheme::HTTP);
-            } else {
-                endpoint_parts.scheme = Some(Scheme::HTTPS);
-            }
+        // an invalid timeout is always an error
+        let diagnostics: Vec<_> = builder
+            .diagnostics
+            .iter()
+            .filter(|d| builder.strict || d.kind == EnvDiagnosticKind::InvalidTimeout)
+            .map(ToString::to_string)
+            .collect();
+        if !diagnostics.is_empty() {
+            return Err(OtlpExporterError::ConfigError(diagnostics.join(", ")));
         }
-        if endpoint_parts.path_and_query.is_none() {
-            // It is insane, parts without path will failed to construct a uri
-            endpoint_parts.path_and_query = "/".try_into().ok();
+        if !builder.failover_endpoints.is_empty() && builder.failover_threshold == 0 {
+            return Err(OtlpExporterError::ConfigError(
+                "failover_threshold should be greater than 0".to_owned(),
+            ));
+        }
+        if let Some(rate_limit) = &builder.rate_limit {
+            rate_limit.validate()?;
+        }
+        if let Some(circuit_breaker) = &builder.circuit_breaker {
+            circuit_breaker.validate()?;
+        }
+        if let Some(tail_sampling) = &builder.tail_sampling {
+            tail_sampling.validate()?;
+        }
+        #[cfg(feature = "redaction")]
+        if let Some(redaction) = &builder.redaction {
+            redaction.compile()?;
         }
-
         Ok(Self {
-            endpoint: endpoint_parts.try_into().map_err(|e| {
-                OtlpExporterError::UnknownError(format!(
-                    "internal error! endpoint_parts should be valid: {}",
-                    e
-                ))
-            })?,
+            endpoint: parse_endpoint(&resolve_endpoint(&builder), builder.insecure)?,
+            failover_endpoints: builder
+                .failover_endpoints
+                .iter()
+                .map(|endpoint| {
+                    parse_endpoint(
+                        &resolve_failover_endpoint(&builder, endpoint),
+                        builder.insecure,
+                    )
+                })
+                .collect::<OtlpExporterResult<_>>()?,
             builder,
         })
     }
@@ -934,6 +2097,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_baggage_headers_from_env() {
+        use super::EnvDiagnosticKind;
+
+        temp_env::with_var(
+            OTEL_EXPORTER_OTLP_HEADERS,
+            Some("api-key = abc%2C%3Ddef==;prop=1, bad name=1,x-bad=%FF,x-kept=a%20b"),
+            || {
+                let builder = ConfigBuilder::default()
+                    .add_header("X-Custom", "custom")
+                    .add_header("API-Key", "replaced")
+                    .with_env(None);
+                let kinds: Vec<_> = builder
+                    .diagnostics()
+                    .iter()
+                    .map(|d| (d.value(), d.kind()))
+                    .collect();
+                assert_eq!(
+                    kinds,
+                    [
+                        (" bad name=1", EnvDiagnosticKind::InvalidHeaderName),
+                        ("x-bad=%FF", EnvDiagnosticKind::InvalidHeaderValue),
+                    ]
+                );
+
+                let mut expected = default_headers();
+                expected.insert("X-Custom".to_owned(), vec!["custom".to_owned()]);
+                expected.insert("api-key".to_owned(), vec!["abc,=def==".to_owned()]);
+                expected.insert("x-kept".to_owned(), vec!["a b".to_owned()]);
+                assert_eq!(builder.build().unwrap().headers(), &expected);
+            },
+        );
+    }
+
     #[cfg(feature = "_grpc")]
     #[test]
     fn test_insecure_from_env() {
@@ -991,4 +2188,667 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_timeout_from_env() {
+        use std::time::Duration;
+
+        use super::OTEL_EXPORTER_OTLP_TIMEOUT;
+
+        let cases = [
+            ("10000", Some(Duration::from_secs(10))),
+            ("250ms", Some(Duration::from_millis(250))),
+            (" 3S ", Some(Duration::from_secs(3))),
+            ("2m", Some(Duration::from_secs(120))),
+            ("", Some(super::DEFAULT_TIMEOUT)),
+            ("1.5s", None),
+            ("10h", None),
+            ("ms", None),
+        ];
+        for (timeout, expected) in cases {
+            temp_env::with_var(OTEL_EXPORTER_OTLP_TIMEOUT, Some(timeout), || {
+                let res = ConfigBuilder::default().with_env(None).build();
+                match expected {
+                    Some(expected) => assert_eq!(res.unwrap().timeout(), expected),
+                    None => {
+                        let err = res.unwrap_err().to_string();
+                        assert!(err.contains(OTEL_EXPORTER_OTLP_TIMEOUT), "{err}");
+                    }
+                }
+            });
+        }
+    }
+
+    #[cfg(all(feature = "http-json", feature = "_grpc"))]
+    #[test]
+    fn test_endpoint_resolution() {
+        use std::collections::HashMap;
+
+        use super::Protocol;
+
+        type Customizer = fn(ConfigBuilder) -> ConfigBuilder;
+        /// Env, the customizer applied after env, the data type and the expected endpoint.
+        type Case = (
+            &'static [(&'static str, &'static str)],
+            Customizer,
+            Option<DataType>,
+            &'static str,
+        );
+
+        const TRACE: Option<DataType> = Some(DataType::Trace);
+        let keep: Customizer = |b| b;
+        let cases: &[Case] = &[
+            // defaults
+            (&[], keep, None, "http://localhost:4318/"),
+            (&[], keep, TRACE, "http://localhost:4318/v1/traces"),
+            (
+                &[("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc")],
+                keep,
+                TRACE,
+                "https://localhost:4317/",
+            ),
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc"),
+                    ("OTEL_EXPORTER_OTLP_INSECURE", "true"),
+                ],
+                keep,
+                TRACE,
+                "http://localhost:4317/",
+            ),
+            // the generic endpoint with signal paths
+            (
+                &[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318")],
+                keep,
+                None,
+                "http://collector:4318/",
+            ),
+            (
+                &[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318")],
+                keep,
+                TRACE,
+                "http://collector:4318/v1/traces",
+            ),
+            (
+                &[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318/")],
+                keep,
+                Some(DataType::Metric),
+                "http://collector:4318/v1/metrics",
+            ),
+            (
+                &[("OTEL_EXPORTER_OTLP_ENDPOINT", "https://collector/otlp/")],
+                keep,
+                Some(DataType::Log),
+                "https://collector/otlp/v1/logs",
+            ),
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_PROTOCOL", "http/json"),
+                    ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318"),
+                ],
+                keep,
+                TRACE,
+                "http://collector:4318/v1/traces",
+            ),
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc"),
+                    ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4317/"),
+                ],
+                keep,
+                TRACE,
+                "http://collector:4317/",
+            ),
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc"),
+                    ("OTEL_EXPORTER_OTLP_ENDPOINT", "collector:4317"),
+                ],
+                keep,
+                TRACE,
+                "https://collector:4317/",
+            ),
+            // the host of the generic endpoint is kept if the protocol of the signal differs
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4317"),
+                    ("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL", "grpc"),
+                ],
+                keep,
+                TRACE,
+                "http://collector:4317/",
+            ),
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc"),
+                    ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318"),
+                    ("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL", "http/protobuf"),
+                ],
+                keep,
+                TRACE,
+                "http://collector:4318/v1/traces",
+            ),
+            (
+                &[("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL", "grpc")],
+                keep,
+                TRACE,
+                "https://localhost:4317/",
+            ),
+            // the endpoint of the signal is used as is
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318"),
+                    ("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "http://traces:4318"),
+                ],
+                keep,
+                TRACE,
+                "http://traces:4318/",
+            ),
+            (
+                &[(
+                    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+                    "http://traces:4318/custom/",
+                )],
+                keep,
+                TRACE,
+                "http://traces:4318/custom/",
+            ),
+            (
+                &[("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT", "http://metrics:4318")],
+                keep,
+                TRACE,
+                "http://localhost:4318/v1/traces",
+            ),
+            (
+                &[
+                    ("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc"),
+                    ("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "traces:4317"),
+                    ("OTEL_EXPORTER_OTLP_TRACES_INSECURE", "true"),
+                ],
+                keep,
+                TRACE,
+                "http://traces:4317/",
+            ),
+            // programmatic endpoints
+            (
+                &[],
+                |b| b.with_endpoint("http://code:4318"),
+                TRACE,
+                "http://code:4318/v1/traces",
+            ),
+            (
+                &[],
+                |b| b.with_signal_endpoint("http://code:4318/v1/spans"),
+                TRACE,
+                "http://code:4318/v1/spans",
+            ),
+            (
+                &[("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "http://traces:4318")],
+                |b| b.with_endpoint("http://code:4318/"),
+                TRACE,
+                "http://code:4318/v1/traces",
+            ),
+            (
+                &[],
+                |b| {
+                    b.with_protocol(Protocol::Grpc)
+                        .with_endpoint("http://code:4317")
+                },
+                TRACE,
+                "http://code:4317/",
+            ),
+            (
+                &[],
+                |b| b.with_protocol(Protocol::Grpc),
+                None,
+                "https://localhost:4317/",
+            ),
+        ];
+        for (env, customizer, data_type, expected) in cases {
+            let source: HashMap<_, _> = env.iter().copied().collect();
+            let config = customizer(ConfigBuilder::default().with_source(&source, *data_type))
+                .build()
+                .unwrap();
+            assert_eq!(config.endpoint().to_string(), *expected, "{env:?}");
+        }
+
+        // the endpoint from env overrides the programmatic one
+        let config = ConfigBuilder::default()
+            .with_endpoint("http://code:4318")
+            .with_source(
+                &HashMap::from([(OTEL_EXPORTER_OTLP_ENDPOINT, "http://env:4318")]),
+                TRACE,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(config.endpoint().to_string(), "http://env:4318/v1/traces");
+
+        // failover endpoints are resolved like the primary one
+        let config = ConfigBuilder::default()
+            .with_data_type(DataType::Trace)
+            .add_failover_endpoint("http://failover:4318")
+            .add_failover_signal_endpoint("http://failover:4318/v1/spans")
+            .build()
+            .unwrap();
+        let endpoints: Vec<_> = config
+            .failover_endpoints()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            endpoints,
+            [
+                "http://failover:4318/v1/traces",
+                "http://failover:4318/v1/spans"
+            ]
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_source() {
+        use std::{collections::HashMap, time::Duration};
+
+        use super::{Prefixed, OTEL_EXPORTER_OTLP_TIMEOUT};
+
+        let source = HashMap::from([
+            (
+                "MYAPP_OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+                "http://tenant-a:4318/v1/traces",
+            ),
+            ("MYAPP_OTEL_EXPORTER_OTLP_TIMEOUT", "2s"),
+            (OTEL_EXPORTER_OTLP_TIMEOUT, "3s"),
+        ]);
+        let config = ConfigBuilder::default()
+            .with_source(&Prefixed::new("MYAPP_", &source), Some(DataType::Trace))
+            .build()
+            .unwrap();
+        assert_eq!(
+            config.endpoint().to_string(),
+            "http://tenant-a:4318/v1/traces"
+        );
+        assert_eq!(config.timeout(), Duration::from_secs(2));
+
+        let config = ConfigBuilder::default()
+            .with_source(&source, None)
+            .build()
+            .unwrap();
+        assert_eq!(config.timeout(), Duration::from_secs(3));
+        assert_eq!(config.endpoint().to_string(), "http://localhost:4318/");
+    }
+
+    #[cfg(feature = "traces")]
+    #[test]
+    fn test_export_limits_from_source() {
+        use std::collections::HashMap;
+
+        use super::{
+            EnvDiagnosticKind, ExportLimits, OTEL_ATTRIBUTE_COUNT_LIMIT,
+            OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT, OTEL_LINK_ATTRIBUTE_COUNT_LIMIT,
+            OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT, OTEL_SPAN_EVENT_COUNT_LIMIT,
+        };
+
+        let source = HashMap::from([
+            (OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT, "4096"),
+            (OTEL_ATTRIBUTE_COUNT_LIMIT, "64"),
+            (OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT, "32"),
+            (OTEL_LINK_ATTRIBUTE_COUNT_LIMIT, "many"),
+            (OTEL_SPAN_EVENT_COUNT_LIMIT, ""),
+        ]);
+        let builder = ConfigBuilder::default()
+            .with_export_limits(ExportLimits::default().with_event_count(10))
+            .with_source(&source, Some(DataType::Trace));
+        assert_eq!(
+            builder.diagnostics()[0].kind(),
+            EnvDiagnosticKind::InvalidLimit
+        );
+        assert_eq!(
+            builder.build().unwrap().export_limits(),
+            Some(
+                &ExportLimits::default()
+                    .with_attribute_value_length(4096)
+                    .with_attribute_count(32)
+                    .with_event_count(10)
+                    .with_event_attribute_count(64)
+                    .with_link_attribute_count(64)
+            )
+        );
+
+        let config = ConfigBuilder::default()
+            .with_source(&HashMap::<&str, &str>::new(), Some(DataType::Trace))
+            .build()
+            .unwrap();
+        assert!(config.export_limits().is_none());
+    }
+
+    #[test]
+    fn test_env_diagnostics() {
+        use super::{
+            EnvDiagnosticKind, OTEL_EXPORTER_OTLP_HEADERS, OTEL_EXPORTER_OTLP_INSECURE,
+            OTEL_EXPORTER_OTLP_PROTOCOL,
+        };
+
+        temp_env::with_vars(
+            [
+                (OTEL_EXPORTER_OTLP_PROTOCOL, Some("grpc/json")),
+                (OTEL_EXPORTER_OTLP_INSECURE, Some("yes")),
+                (OTEL_EXPORTER_OTLP_HEADERS, Some("a=1,bad,=2")),
+            ],
+            || {
+                let builder = ConfigBuilder::default().with_env(None);
+                let kinds: Vec<_> = builder
+                    .diagnostics()
+                    .iter()
+                    .map(|d| (d.key(), d.value(), d.kind()))
+                    .collect();
+                assert_eq!(
+                    kinds,
+                    [
+                        (
+                            OTEL_EXPORTER_OTLP_PROTOCOL,
+                            "grpc/json",
+                            EnvDiagnosticKind::UnknownProtocol
+                        ),
+                        (
+                            OTEL_EXPORTER_OTLP_INSECURE,
+                            "yes",
+                            EnvDiagnosticKind::InvalidBool
+                        ),
+                        (
+                            OTEL_EXPORTER_OTLP_HEADERS,
+                            "bad",
+                            EnvDiagnosticKind::MalformedHeader
+                        ),
+                        (
+                            OTEL_EXPORTER_OTLP_HEADERS,
+                            "=2",
+                            EnvDiagnosticKind::MalformedHeader
+                        ),
+                    ]
+                );
+                assert!(builder.clone().build().is_ok());
+                let err = builder.with_strict_env(true).build().unwrap_err();
+                assert!(
+                    err.to_string().contains(OTEL_EXPORTER_OTLP_INSECURE),
+                    "{err}"
+                );
+
+                assert!(ConfigBuilder::default().try_with_env(None).is_err());
+            },
+        );
+    }
+
+    #[cfg(feature = "traces")]
+    #[test]
+    fn test_sampler_and_propagator_from_env() {
+        use opentelemetry_api::propagation::TextMapPropagator;
+
+        use super::{
+            propagator_from_env, sampler_from_env, OTEL_PROPAGATORS, OTEL_TRACES_SAMPLER,
+            OTEL_TRACES_SAMPLER_ARG,
+        };
+
+        let cases = [
+            (None, None, None),
+            (Some("always_off"), None, Some("AlwaysOff")),
+            (
+                Some("traceidratio"),
+                Some("0.25"),
+                Some("TraceIdRatioBased(0.25)"),
+            ),
+            (
+                Some("parentbased_traceidratio"),
+                Some("2"),
+                Some("ParentBased(TraceIdRatioBased(1.0))"),
+            ),
+            (Some("xray"), None, Some("ParentBased(AlwaysOn)")),
+        ];
+        for (sampler, arg, expected) in cases {
+            temp_env::with_vars(
+                [
+                    (OTEL_TRACES_SAMPLER, sampler),
+                    (OTEL_TRACES_SAMPLER_ARG, arg),
+                ],
+                || {
+                    assert_eq!(
+                        sampler_from_env().map(|s| format!("{s:?}")).as_deref(),
+                        expected
+                    );
+                },
+            );
+        }
+
+        let cases = [
+            (None, None),
+            (Some("tracecontext,baggage,b3,tracecontext"), Some(3)),
+            (Some("baggage"), Some(1)),
+            (Some("tracecontext,none"), Some(0)),
+        ];
+        for (propagators, expected) in cases {
+            temp_env::with_var(OTEL_PROPAGATORS, propagators, || {
+                // traceparent, tracestate and baggage
+                assert_eq!(
+                    propagator_from_env().map(|p| p.fields().count()),
+                    expected,
+                    "{propagators:?}"
+                );
+            });
+        }
+    }
+
+    #[cfg(feature = "traces")]
+    #[test]
+    fn test_sampler_with_diagnostics() {
+        use std::collections::HashMap;
+
+        use super::{
+            sampler_with_diagnostics, EnvDiagnosticKind, OTEL_TRACES_SAMPLER,
+            OTEL_TRACES_SAMPLER_ARG,
+        };
+
+        let cases = [
+            (None, "TraceIdRatioBased(1.0)", None),
+            (Some(""), "TraceIdRatioBased(1.0)", None),
+            (Some(" 0.5 "), "TraceIdRatioBased(0.5)", None),
+            (
+                Some("half"),
+                "TraceIdRatioBased(1.0)",
+                Some(EnvDiagnosticKind::InvalidSamplerArg),
+            ),
+            (
+                Some("-0.1"),
+                "TraceIdRatioBased(1.0)",
+                Some(EnvDiagnosticKind::InvalidSamplerArg),
+            ),
+        ];
+        for (arg, expected, kind) in cases {
+            let mut source = HashMap::from([(OTEL_TRACES_SAMPLER, "traceidratio")]);
+            source.extend(arg.map(|arg| (OTEL_TRACES_SAMPLER_ARG, arg)));
+            let mut diagnostics = Vec::new();
+            let sampler = sampler_with_diagnostics(&source, &mut diagnostics).unwrap();
+            assert_eq!(format!("{sampler:?}"), expected);
+            assert_eq!(
+                diagnostics.iter().map(|d| d.kind()).collect::<Vec<_>>(),
+                Vec::from_iter(kind),
+                "{arg:?}"
+            );
+        }
+
+        let mut diagnostics = Vec::new();
+        let source = HashMap::from([(OTEL_TRACES_SAMPLER, "xray")]);
+        assert!(sampler_with_diagnostics(&source, &mut diagnostics).is_some());
+        assert_eq!(diagnostics[0].kind(), EnvDiagnosticKind::UnsupportedSampler);
+        assert_eq!(diagnostics[0].value(), "xray");
+    }
+
+    #[cfg(feature = "traces")]
+    #[test]
+    fn test_batch_config_from_env() {
+        use super::{
+            batch_config_from_env, OTEL_BSP_EXPORT_TIMEOUT, OTEL_BSP_MAX_EXPORT_BATCH_SIZE,
+            OTEL_BSP_MAX_QUEUE_SIZE, OTEL_BSP_SCHEDULE_DELAY,
+        };
+
+        temp_env::with_vars(
+            [
+                (OTEL_BSP_SCHEDULE_DELAY, Some("200")),
+                (OTEL_BSP_EXPORT_TIMEOUT, Some("invalid")),
+                (OTEL_BSP_MAX_QUEUE_SIZE, Some("100")),
+                (OTEL_BSP_MAX_EXPORT_BATCH_SIZE, Some(" 10 ")),
+            ],
+            || {
+                let config = format!(
+                    "{:?}",
+                    batch_config_from_env(
+                        opentelemetry_sdk::trace::BatchConfig::default()
+                            .with_max_export_timeout(std::time::Duration::from_secs(3))
+                    )
+                );
+                assert!(config.contains("scheduled_delay: 200ms"), "{config}");
+                assert!(config.contains("max_export_timeout: 3s"), "{config}");
+                assert!(config.contains("max_queue_size: 100"), "{config}");
+                assert!(config.contains("max_export_batch_size: 10,"), "{config}");
+            },
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "http"))]
+    #[test]
+    fn test_serde() {
+        use std::time::Duration;
+
+        use super::{Protocol, REDACTED};
+
+        let builder: ConfigBuilder = serde_json::from_str(
+            r#"{
+                "protocol": "http/protobuf",
+                "timeout": "1m 30s",
+                "certificate_file": "/tmp/ca.pem",
+                "headers": {"api-key": ["secret"]},
+                "failover_endpoints": [
+                    "http://failover:4318",
+                    {"signal_endpoint": "http://failover:4318/v1/spans"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let config = builder.clone().build().unwrap();
+        assert_eq!(config.protocol(), Protocol::HttpProtobuf);
+        assert_eq!(config.endpoint().to_string(), "http://localhost:4318/");
+        assert_eq!(config.timeout(), Duration::from_secs(90));
+        assert_eq!(config.headers()["api-key"], vec!["secret".to_string()]);
+        assert!(config.headers().contains_key("User-Agent"));
+
+        let value = serde_json::to_value(&builder).unwrap();
+        assert_eq!(value["protocol"], "http/protobuf");
+        assert_eq!(value["timeout"], "1m 30s");
+        assert_eq!(value["certificate_file"], "/tmp/ca.pem");
+        assert_eq!(value["headers"]["api-key"][0], "secret");
+        assert_eq!(value["failover_endpoints"][0], "http://failover:4318");
+        assert_eq!(
+            value["failover_endpoints"][1]["signal_endpoint"],
+            "http://failover:4318/v1/spans"
+        );
+        let round_trip: ConfigBuilder = serde_json::from_value(value).unwrap();
+        assert_eq!(round_trip.timeout, builder.timeout);
+        assert_eq!(round_trip.failover_endpoints, builder.failover_endpoints);
+
+        let redacted = serde_json::to_value(builder.redacted()).unwrap();
+        assert_eq!(redacted["headers"]["api-key"][0], REDACTED);
+
+        assert!(serde_json::from_str::<ConfigBuilder>(r#"{"protocol": "udp"}"#).is_err());
+        assert!(serde_json::from_str::<ConfigBuilder>(r#"{"timeout": "ten"}"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_tail_sampling() {
+        use std::time::Duration;
+
+        use super::{SamplingPolicy, SpanStatusCode, TailSampling};
+
+        let builder: ConfigBuilder = serde_json::from_str(
+            r#"{
+                "tail_sampling": {
+                    "decision_wait": "5s",
+                    "policies": [
+                        {"type": "status_code", "status_codes": ["error"]},
+                        {"type": "latency", "threshold": "2s"},
+                        {"type": "probabilistic", "ratio": 0.05}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            builder.build().unwrap().tail_sampling(),
+            Some(
+                &TailSampling::default()
+                    .with_decision_wait(Duration::from_secs(5))
+                    .add_policy(SamplingPolicy::StatusCode {
+                        status_codes: vec![SpanStatusCode::Error]
+                    })
+                    .add_policy(SamplingPolicy::Latency {
+                        threshold: Duration::from_secs(2)
+                    })
+                    .add_policy(SamplingPolicy::Probabilistic { ratio: 0.05 })
+            )
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "redaction"))]
+    #[test]
+    fn test_serde_redaction() {
+        use super::{MaskRule, RedactionRules, DEFAULT_MASK_REPLACEMENT};
+
+        let builder: ConfigBuilder = serde_json::from_str(
+            r#"{
+                "redaction": {
+                    "deny_keys": ["password"],
+                    "masks": [{"pattern": "\\d{16}"}],
+                    "hash_keys": ["user.id"],
+                    "hash_secret": "secret"
+                }
+            }"#,
+        )
+        .unwrap();
+        let config = builder.clone().build().unwrap();
+        assert_eq!(
+            config.redaction(),
+            Some(
+                &RedactionRules::default()
+                    .add_deny_key("password")
+                    .add_mask(MaskRule::new(r"\d{16}"))
+                    .add_hash_key("user.id")
+                    .with_hash_secret("secret")
+            )
+        );
+        assert_eq!(
+            config.redaction().unwrap().masks()[0].replacement(),
+            DEFAULT_MASK_REPLACEMENT
+        );
+
+        let value = serde_json::to_value(&builder).unwrap();
+        assert!(value["redaction"].get("hash_secret").is_none());
+
+        assert!(serde_json::from_str::<ConfigBuilder>(
+            r#"{"redaction": {"masks": [{"pattern": "("}]}}"#
+        )
+        .unwrap()
+        .build()
+        .is_err());
+    }
+
+    #[cfg(all(feature = "serde", feature = "grpcio"))]
+    #[test]
+    fn test_serde_grpc_impl() {
+        use super::{grpc::grpcio::GrpcioConfig, GrpcImpl};
+
+        let grpc_impl: GrpcImpl =
+            serde_json::from_str(r#"{"impl": "grpcio", "cq_count": 4}"#).unwrap();
+        assert_eq!(
+            grpc_impl,
+            GrpcImpl::Grpcio(GrpcioConfig::default().with_cq_count(4))
+        );
+    }
 }
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+/// Default number of consecutive failures before the circuit opens.
+pub const DEFAULT_CIRCUIT_FAILURE_THRESHOLD: usize = 5;
+/// Default duration the circuit stays open before probing the collector.
+pub const DEFAULT_CIRCUIT_OPEN_DURATION: Duration = Duration::from_secs(30);
+
+/// Settings of the circuit breaker around each transport, set it with
+/// [`crate::config::ConfigBuilder::with_circuit_breaker`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CircuitBreaker {
+    /// Number of consecutive failures before the circuit opens.
+    failure_threshold: usize,
+
+    /// How long batches fail fast before a batch is sent to probe the collector.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    open_duration: Duration,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_CIRCUIT_FAILURE_THRESHOLD,
+            open_duration: DEFAULT_CIRCUIT_OPEN_DURATION,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn failure_threshold(&self) -> usize {
+        self.failure_threshold
+    }
+
+    pub fn open_duration(&self) -> Duration {
+        self.open_duration
+    }
+
+    pub fn with_failure_threshold(mut self, failure_threshold: usize) -> Self {
+        self.failure_threshold = failure_threshold;
+        self
+    }
+
+    pub fn with_open_duration(mut self, open_duration: Duration) -> Self {
+        self.open_duration = open_duration;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> OtlpExporterResult<()> {
+        if self.failure_threshold == 0 {
+            return Err(OtlpExporterError::ConfigError(
+                "failure_threshold of the circuit breaker should be greater than 0".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
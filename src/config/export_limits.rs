@@ -0,0 +1,143 @@
+use super::{
+    EnvDiagnostic, EnvDiagnosticKind, EnvSource, OTEL_ATTRIBUTE_COUNT_LIMIT,
+    OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT, OTEL_EVENT_ATTRIBUTE_COUNT_LIMIT,
+    OTEL_LINK_ATTRIBUTE_COUNT_LIMIT, OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT,
+    OTEL_SPAN_ATTRIBUTE_VALUE_LENGTH_LIMIT, OTEL_SPAN_EVENT_COUNT_LIMIT,
+    OTEL_SPAN_LINK_COUNT_LIMIT,
+};
+
+/// Limits of attributes, events and links enforced when spans are exported, independent of the
+/// span limits of the SDK. Set it with [`crate::config::ConfigBuilder::with_export_limits`], or
+/// by env `OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT`, `OTEL_ATTRIBUTE_COUNT_LIMIT` and their `OTEL_SPAN_*`,
+/// `OTEL_EVENT_*` and `OTEL_LINK_*` variants.
+///
+/// Unset limits are unlimited. String values and elements of string arrays longer than the value
+/// length limit are truncated in characters. Attributes, events and links over count limits are
+/// removed and counted as dropped, the latest events and links are kept, which attributes are
+/// kept is unspecified.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ExportLimits {
+    /// Max characters of string values of spans, events and links.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    attribute_value_length: Option<usize>,
+
+    /// Max attributes of a span.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    attribute_count: Option<usize>,
+
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    event_count: Option<usize>,
+
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    link_count: Option<usize>,
+
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    event_attribute_count: Option<usize>,
+
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    link_attribute_count: Option<usize>,
+}
+
+impl ExportLimits {
+    pub fn attribute_value_length(&self) -> Option<usize> {
+        self.attribute_value_length
+    }
+
+    pub fn attribute_count(&self) -> Option<usize> {
+        self.attribute_count
+    }
+
+    pub fn event_count(&self) -> Option<usize> {
+        self.event_count
+    }
+
+    pub fn link_count(&self) -> Option<usize> {
+        self.link_count
+    }
+
+    pub fn event_attribute_count(&self) -> Option<usize> {
+        self.event_attribute_count
+    }
+
+    pub fn link_attribute_count(&self) -> Option<usize> {
+        self.link_attribute_count
+    }
+
+    pub fn with_attribute_value_length(mut self, attribute_value_length: usize) -> Self {
+        self.attribute_value_length = Some(attribute_value_length);
+        self
+    }
+
+    pub fn with_attribute_count(mut self, attribute_count: usize) -> Self {
+        self.attribute_count = Some(attribute_count);
+        self
+    }
+
+    pub fn with_event_count(mut self, event_count: usize) -> Self {
+        self.event_count = Some(event_count);
+        self
+    }
+
+    pub fn with_link_count(mut self, link_count: usize) -> Self {
+        self.link_count = Some(link_count);
+        self
+    }
+
+    pub fn with_event_attribute_count(mut self, event_attribute_count: usize) -> Self {
+        self.event_attribute_count = Some(event_attribute_count);
+        self
+    }
+
+    pub fn with_link_attribute_count(mut self, link_attribute_count: usize) -> Self {
+        self.link_attribute_count = Some(link_attribute_count);
+        self
+    }
+
+    /// Whether all limits are unset.
+    pub fn is_unlimited(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Read limits from `source`, limits not set in it are kept. Specific limits like
+    /// `OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT` take precedence over general ones like
+    /// `OTEL_ATTRIBUTE_COUNT_LIMIT`. Invalid values are pushed to `diagnostics`.
+    pub(super) fn with_source(
+        mut self,
+        source: &(impl EnvSource + ?Sized),
+        diagnostics: &mut Vec<EnvDiagnostic>,
+    ) -> Self {
+        let mut limit = |key: &'static str| {
+            let v = source.get(key).filter(|v| !v.is_empty())?;
+            let limit = match v.to_str() {
+                Some(l) => l
+                    .trim()
+                    .parse()
+                    .map_err(|_| EnvDiagnosticKind::InvalidLimit),
+                None => Err(EnvDiagnosticKind::NotUnicode),
+            };
+            limit
+                .map_err(|kind| diagnostics.push(EnvDiagnostic::new(key, &v, kind)))
+                .ok()
+        };
+
+        let value_length = limit(OTEL_ATTRIBUTE_VALUE_LENGTH_LIMIT);
+        let count = limit(OTEL_ATTRIBUTE_COUNT_LIMIT);
+        self.attribute_value_length = limit(OTEL_SPAN_ATTRIBUTE_VALUE_LENGTH_LIMIT)
+            .or(value_length)
+            .or(self.attribute_value_length);
+        self.attribute_count = limit(OTEL_SPAN_ATTRIBUTE_COUNT_LIMIT)
+            .or(count)
+            .or(self.attribute_count);
+        self.event_count = limit(OTEL_SPAN_EVENT_COUNT_LIMIT).or(self.event_count);
+        self.link_count = limit(OTEL_SPAN_LINK_COUNT_LIMIT).or(self.link_count);
+        self.event_attribute_count = limit(OTEL_EVENT_ATTRIBUTE_COUNT_LIMIT)
+            .or(count)
+            .or(self.event_attribute_count);
+        self.link_attribute_count = limit(OTEL_LINK_ATTRIBUTE_COUNT_LIMIT)
+            .or(count)
+            .or(self.link_attribute_count);
+        self
+    }
+}
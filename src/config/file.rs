@@ -0,0 +1,819 @@
+//! Declarative configuration file.
+//!
+//! Refer: `<https://github.com/open-telemetry/opentelemetry-configuration/tree/v0.1.0>`
+//!
+//! Only `disabled`, `resource` and `tracer_provider` are applied, `meter_provider`,
+//! `logger_provider`, `propagator` and `attribute_limits` are accepted but ignored. The
+//! `endpoint` of an otlp exporter is used as is, no signal path is appended.
+//!
+//! `${NAME}` and `${NAME:-default}` in string values of the file are replaced with the value of
+//! env `NAME`, an undefined env without a default is replaced with an empty value. Use `$$` for a
+//! literal `$`. The file is parsed before the replacement, so env values can't inject structure.
+//! A replaced value stays a string, numbers and bools are parsed from it, and an empty one is
+//! the same as unset for optional fields.
+
+use std::{
+    collections::BTreeMap, ffi::OsString, fmt::Display, fs, path::Path, str::FromStr,
+    time::Duration,
+};
+
+use opentelemetry_api::{KeyValue, Value};
+use opentelemetry_sdk::{
+    trace::{BatchConfig, Config as TracerConfig, Sampler, SpanLimits},
+    Resource,
+};
+use serde::{de::IgnoredAny, Deserialize, Deserializer};
+
+use super::{ConfigBuilder, EnvSource, ProcessEnv, Protocol};
+use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+/// Env key: OTEL_CONFIG_FILE
+pub const OTEL_CONFIG_FILE: &str = "OTEL_CONFIG_FILE";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileModel {
+    file_format: String,
+    #[serde(default, deserialize_with = "scalar")]
+    disabled: bool,
+    resource: Option<ResourceModel>,
+    tracer_provider: Option<TracerProviderModel>,
+    // ignored
+    #[allow(dead_code)]
+    meter_provider: Option<IgnoredAny>,
+    #[allow(dead_code)]
+    logger_provider: Option<IgnoredAny>,
+    #[allow(dead_code)]
+    propagator: Option<IgnoredAny>,
+    #[allow(dead_code)]
+    attribute_limits: Option<IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResourceModel {
+    #[serde(default)]
+    attributes: BTreeMap<String, AttributeModel>,
+    schema_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AttributeModel {
+    Bool(bool),
+    I64(i64),
+    F64(f64),
+    String(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TracerProviderModel {
+    #[serde(default)]
+    processors: Vec<ProcessorModel>,
+    limits: Option<LimitsModel>,
+    sampler: Option<SamplerModel>,
+}
+
+/// Exactly one of the fields should be set.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProcessorModel {
+    batch: Option<BatchProcessorModel>,
+    simple: Option<SimpleProcessorModel>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchProcessorModel {
+    /// In milliseconds.
+    #[serde(default, deserialize_with = "optional_scalar")]
+    schedule_delay: Option<u64>,
+    /// In milliseconds.
+    #[serde(default, deserialize_with = "optional_scalar")]
+    export_timeout: Option<u64>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    max_queue_size: Option<usize>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    max_export_batch_size: Option<usize>,
+    exporter: ExporterModel,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SimpleProcessorModel {
+    exporter: ExporterModel,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExporterModel {
+    otlp: Option<OtlpModel>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OtlpModel {
+    protocol: String,
+    endpoint: String,
+    certificate: Option<String>,
+    client_key: Option<String>,
+    client_certificate: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    compression: Option<String>,
+    /// In milliseconds.
+    #[serde(default, deserialize_with = "optional_scalar")]
+    timeout: Option<u64>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    insecure: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LimitsModel {
+    #[serde(default, deserialize_with = "optional_scalar")]
+    attribute_count_limit: Option<u32>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    event_count_limit: Option<u32>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    link_count_limit: Option<u32>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    event_attribute_count_limit: Option<u32>,
+    #[serde(default, deserialize_with = "optional_scalar")]
+    link_attribute_count_limit: Option<u32>,
+}
+
+/// Exactly one of the fields should be set.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SamplerModel {
+    #[serde(default, deserialize_with = "present")]
+    always_on: bool,
+    #[serde(default, deserialize_with = "present")]
+    always_off: bool,
+    trace_id_ratio_based: Option<RatioModel>,
+    parent_based: Option<Box<ParentBasedModel>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RatioModel {
+    #[serde(deserialize_with = "scalar")]
+    ratio: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParentBasedModel {
+    root: SamplerModel,
+}
+
+/// A field without a value, like `always_on:`, is present.
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    IgnoredAny::deserialize(deserializer).map(|_| true)
+}
+
+/// A native value, or a string replaced from env.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScalarModel<T> {
+    Value(T),
+    String(String),
+}
+
+/// Accept a string for a number or a bool, since values replaced from env are strings.
+fn scalar<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    match ScalarModel::deserialize(deserializer)? {
+        ScalarModel::Value(v) => Ok(v),
+        ScalarModel::String(s) => s.trim().parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Like [`scalar`], an empty string is `None`.
+fn optional_scalar<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    match Option::<ScalarModel<T>>::deserialize(deserializer)? {
+        Some(ScalarModel::Value(v)) => Ok(Some(v)),
+        Some(ScalarModel::String(s)) if !s.trim().is_empty() => {
+            s.trim().parse().map(Some).map_err(serde::de::Error::custom)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Trace settings loaded from a config file.
+pub(crate) struct TraceSettings {
+    pub(crate) disabled: bool,
+    /// One for each processor, the first one is the primary destination.
+    pub(crate) config_builders: Vec<ConfigBuilder>,
+    pub(crate) batch_config: Option<BatchConfig>,
+    pub(crate) tracer_config: TracerConfig,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FileFormat {
+    #[cfg(feature = "config-file-yaml")]
+    Yaml,
+    #[cfg(feature = "config-file-toml")]
+    Toml,
+}
+
+impl FileFormat {
+    fn from_path(path: &Path) -> OtlpExporterResult<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "config-file-yaml")]
+            Some("yaml" | "yml") => Ok(FileFormat::Yaml),
+            #[cfg(feature = "config-file-toml")]
+            Some("toml") => Ok(FileFormat::Toml),
+            _ => Err(OtlpExporterError::ConfigError(format!(
+                "{}: unknown file format or its feature is not enabled",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Load trace settings from the file of env `OTEL_CONFIG_FILE`, `None` if it is not set.
+pub(crate) fn load_trace_settings_from_env() -> OtlpExporterResult<Option<TraceSettings>> {
+    match std::env::var_os(OTEL_CONFIG_FILE) {
+        Some(path) if !path.is_empty() => {
+            load_trace_settings(Path::new(&path), &ProcessEnv).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Load trace settings from `path`, env references are read from `source`.
+pub(crate) fn load_trace_settings(
+    path: &Path,
+    source: &(impl EnvSource + ?Sized),
+) -> OtlpExporterResult<TraceSettings> {
+    let format = FileFormat::from_path(path)?;
+    let content = fs::read_to_string(path)?;
+    parse_trace_settings(&content, format, source).map_err(|e| match e {
+        OtlpExporterError::ConfigError(msg) => {
+            OtlpExporterError::ConfigError(format!("{}: {msg}", path.display()))
+        }
+        e => e,
+    })
+}
+
+fn parse_trace_settings(
+    content: &str,
+    format: FileFormat,
+    source: &(impl EnvSource + ?Sized),
+) -> OtlpExporterResult<TraceSettings> {
+    let model: FileModel = match format {
+        #[cfg(feature = "config-file-yaml")]
+        FileFormat::Yaml => {
+            let mut value: serde_yaml::Value = serde_yaml::from_str(content)
+                .map_err(|e| OtlpExporterError::ConfigError(e.to_string()))?;
+            substitute_yaml(&mut value, "", source)?;
+            serde_path_to_error::deserialize(value).map_err(path_error)?
+        }
+        #[cfg(feature = "config-file-toml")]
+        FileFormat::Toml => {
+            let mut value: toml::Value = toml::from_str(content)
+                .map_err(|e| OtlpExporterError::ConfigError(e.to_string()))?;
+            substitute_toml(&mut value, "", source)?;
+            serde_path_to_error::deserialize(value).map_err(path_error)?
+        }
+    };
+    model.into_trace_settings()
+}
+
+fn path_error<E: Display>(e: serde_path_to_error::Error<E>) -> OtlpExporterError {
+    let path = e.path().to_string();
+    let msg = e.inner().to_string();
+    // some deserializers have put the path into the message
+    let msg = msg.strip_prefix(&format!("{path}: ")).unwrap_or(&msg);
+    invalid(path, msg)
+}
+
+fn invalid(path: impl Display, msg: impl Display) -> OtlpExporterError {
+    OtlpExporterError::ConfigError(format!("{path}: {msg}"))
+}
+
+/// The path of `key` in the mapping at `path`, like the paths of `serde_path_to_error`.
+fn field_path(path: &str, key: impl Display) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// Replace env references in all string values of `value`.
+#[cfg(feature = "config-file-yaml")]
+fn substitute_yaml(
+    value: &mut serde_yaml::Value,
+    path: &str,
+    source: &(impl EnvSource + ?Sized),
+) -> OtlpExporterResult<()> {
+    use serde_yaml::Value;
+
+    match value {
+        Value::String(s) => *s = substitute_env(s, source).map_err(|msg| invalid(path, msg))?,
+        Value::Sequence(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                substitute_yaml(value, &format!("{path}[{i}]"), source)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping.iter_mut() {
+                let path = match key {
+                    Value::String(key) => field_path(path, key),
+                    key => field_path(path, format_args!("{key:?}")),
+                };
+                substitute_yaml(value, &path, source)?;
+            }
+        }
+        Value::Tagged(tagged) => substitute_yaml(&mut tagged.value, path, source)?,
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+    Ok(())
+}
+
+/// Replace env references in all string values of `value`.
+#[cfg(feature = "config-file-toml")]
+fn substitute_toml(
+    value: &mut toml::Value,
+    path: &str,
+    source: &(impl EnvSource + ?Sized),
+) -> OtlpExporterResult<()> {
+    use toml::Value;
+
+    match value {
+        Value::String(s) => *s = substitute_env(s, source).map_err(|msg| invalid(path, msg))?,
+        Value::Array(values) => {
+            for (i, value) in values.iter_mut().enumerate() {
+                substitute_toml(value, &format!("{path}[{i}]"), source)?;
+            }
+        }
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                substitute_toml(value, &field_path(path, key), source)?;
+            }
+        }
+        Value::Integer(_) | Value::Float(_) | Value::Boolean(_) | Value::Datetime(_) => {}
+    }
+    Ok(())
+}
+
+/// Replace `${NAME}` and `${NAME:-default}` in a string value with values of `source`, return
+/// the message if a reference is invalid.
+fn substitute_env(value: &str, source: &(impl EnvSource + ?Sized)) -> Result<String, String> {
+    let mut res = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(idx) = rest.find('$') {
+        res.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+        if let Some(r) = rest.strip_prefix('$') {
+            res.push('$');
+            rest = r;
+            continue;
+        }
+        let Some(r) = rest.strip_prefix('{') else {
+            res.push('$');
+            continue;
+        };
+        let end = r
+            .find('}')
+            .ok_or_else(|| "env reference is not closed".to_owned())?;
+        let reference = &r[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        let name = name.strip_prefix("env:").unwrap_or(name);
+        let valid = name
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_alphabetic() || c == '_')
+            .unwrap_or(false)
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("invalid env reference[${{{reference}}}]"));
+        }
+        match source.get_string(name) {
+            Some(v) => res.push_str(&v),
+            None => res.push_str(default.unwrap_or_default()),
+        }
+        rest = &r[end + 1..];
+    }
+    res.push_str(rest);
+    Ok(res)
+}
+
+impl FileModel {
+    fn into_trace_settings(self) -> OtlpExporterResult<TraceSettings> {
+        if !self.file_format.starts_with("0.") {
+            return Err(invalid(
+                "file_format",
+                format_args!("unsupported version[{}]", self.file_format),
+            ));
+        }
+
+        let mut tracer_config = TracerConfig::default();
+        if let Some(resource) = self.resource {
+            tracer_config = tracer_config.with_resource(resource.into_resource());
+        }
+
+        let Some(tracer_provider) = self.tracer_provider else {
+            return Ok(TraceSettings {
+                disabled: true,
+                config_builders: Vec::new(),
+                batch_config: None,
+                tracer_config,
+            });
+        };
+        if let Some(sampler) = tracer_provider.sampler {
+            tracer_config =
+                tracer_config.with_sampler(sampler.into_sampler("tracer_provider.sampler")?);
+        }
+        if let Some(limits) = tracer_provider.limits {
+            tracer_config = tracer_config.with_span_limits(limits.into_span_limits());
+        }
+
+        let mut config_builders = Vec::new();
+        let mut batch_config = None;
+        let mut batch = None;
+        for (i, processor) in tracer_provider.processors.into_iter().enumerate() {
+            let path = format!("tracer_provider.processors[{i}]");
+            let (exporter, path) = match (processor.batch, processor.simple) {
+                (Some(processor), None) => {
+                    if batch_config.is_none() {
+                        batch_config = Some(processor.batch_config());
+                    }
+                    if *batch.get_or_insert(true) {
+                        (processor.exporter, format!("{path}.batch.exporter"))
+                    } else {
+                        return Err(invalid(
+                            path,
+                            "all processors should be either batch or simple",
+                        ));
+                    }
+                }
+                (None, Some(processor)) => {
+                    if !*batch.get_or_insert(false) {
+                        (processor.exporter, format!("{path}.simple.exporter"))
+                    } else {
+                        return Err(invalid(
+                            path,
+                            "all processors should be either batch or simple",
+                        ));
+                    }
+                }
+                _ => {
+                    return Err(invalid(
+                        path,
+                        "exactly one of batch and simple should be set",
+                    ))
+                }
+            };
+            match exporter.otlp {
+                Some(otlp) => {
+                    config_builders.push(otlp.into_config_builder(&format!("{path}.otlp"))?)
+                }
+                None => return Err(invalid(path, "otlp exporter is required")),
+            }
+        }
+
+        Ok(TraceSettings {
+            disabled: self.disabled || config_builders.is_empty(),
+            config_builders,
+            batch_config,
+            tracer_config,
+        })
+    }
+}
+
+impl ResourceModel {
+    fn into_resource(self) -> Resource {
+        let attributes = self.attributes.into_iter().map(|(key, value)| {
+            let value = match value {
+                AttributeModel::Bool(v) => Value::Bool(v),
+                AttributeModel::I64(v) => Value::I64(v),
+                AttributeModel::F64(v) => Value::F64(v),
+                AttributeModel::String(v) => Value::String(v.into()),
+            };
+            KeyValue::new(key, value)
+        });
+        match self.schema_url {
+            Some(schema_url) => Resource::from_schema_url(attributes, schema_url),
+            None => Resource::new(attributes),
+        }
+    }
+}
+
+impl BatchProcessorModel {
+    fn batch_config(&self) -> BatchConfig {
+        let mut config = BatchConfig::default();
+        if let Some(delay) = self.schedule_delay {
+            config = config.with_scheduled_delay(Duration::from_millis(delay));
+        }
+        if let Some(timeout) = self.export_timeout {
+            config = config.with_max_export_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(size) = self.max_queue_size {
+            config = config.with_max_queue_size(size);
+        }
+        if let Some(size) = self.max_export_batch_size {
+            config = config.with_max_export_batch_size(size);
+        }
+        config
+    }
+}
+
+impl OtlpModel {
+    fn into_config_builder(self, path: &str) -> OtlpExporterResult<ConfigBuilder> {
+        let protocol: Protocol = self
+            .protocol
+            .parse()
+            .map_err(|e| invalid(format_args!("{path}.protocol"), e))?;
+        if let Some(compression) = self.compression.filter(|c| c != "none") {
+            return Err(invalid(
+                format_args!("{path}.compression"),
+                format_args!("compression[{compression}] is not supported"),
+            ));
+        }
+
+        let mut builder = ConfigBuilder::default()
+            .with_protocol(protocol)
+            .with_signal_endpoint(self.endpoint);
+        if let Some(certificate) = self.certificate {
+            builder = builder.with_certificate_file(OsString::from(certificate));
+        }
+        if let Some(client_key) = self.client_key {
+            builder = builder.with_client_key_file(OsString::from(client_key));
+        }
+        if let Some(client_certificate) = self.client_certificate {
+            builder = builder.with_client_certificate_file(OsString::from(client_certificate));
+        }
+        for (name, value) in self.headers {
+            builder = builder.add_header(name, value);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.with_timeout(Duration::from_millis(timeout));
+        }
+        if let Some(insecure) = self.insecure {
+            builder = builder.with_insecure(insecure);
+        }
+        // validate it here, so the error points at the exporter
+        builder
+            .clone()
+            .build()
+            .map_err(|e| invalid(format_args!("{path}.endpoint"), e))?;
+        Ok(builder)
+    }
+}
+
+impl LimitsModel {
+    fn into_span_limits(self) -> SpanLimits {
+        let mut limits = SpanLimits::default();
+        if let Some(v) = self.attribute_count_limit {
+            limits.max_attributes_per_span = v;
+        }
+        if let Some(v) = self.event_count_limit {
+            limits.max_events_per_span = v;
+        }
+        if let Some(v) = self.link_count_limit {
+            limits.max_links_per_span = v;
+        }
+        if let Some(v) = self.event_attribute_count_limit {
+            limits.max_attributes_per_event = v;
+        }
+        if let Some(v) = self.link_attribute_count_limit {
+            limits.max_attributes_per_link = v;
+        }
+        limits
+    }
+}
+
+impl SamplerModel {
+    fn into_sampler(self, path: &str) -> OtlpExporterResult<Sampler> {
+        Ok(
+            match (
+                self.always_on,
+                self.always_off,
+                self.trace_id_ratio_based,
+                self.parent_based,
+            ) {
+                (true, false, None, None) => Sampler::AlwaysOn,
+                (false, true, None, None) => Sampler::AlwaysOff,
+                (false, false, Some(RatioModel { ratio }), None) => {
+                    if !(0.0..=1.0).contains(&ratio) {
+                        return Err(invalid(
+                            format_args!("{path}.trace_id_ratio_based.ratio"),
+                            format_args!("ratio[{ratio}] should be in [0, 1]"),
+                        ));
+                    }
+                    Sampler::TraceIdRatioBased(ratio)
+                }
+                (false, false, None, Some(parent_based)) => Sampler::ParentBased(Box::new(
+                    parent_based
+                        .root
+                        .into_sampler(&format!("{path}.parent_based.root"))?,
+                )),
+                _ => return Err(invalid(path, "exactly one sampler should be set")),
+            },
+        )
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "config-file-yaml",
+    feature = "config-file-toml",
+    feature = "http"
+))]
+mod tests {
+    use std::collections::HashMap;
+
+    use opentelemetry_api::Key;
+
+    use super::{parse_trace_settings, substitute_env, FileFormat};
+
+    const SERVICE_NAME: Key = Key::from_static_str("service.name");
+
+    const YAML: &str = r#"
+file_format: "0.1"
+resource:
+  attributes:
+    service.name: ${TEST_FILE_SERVICE_NAME:-unknown}
+    replicas: 3
+tracer_provider:
+  processors:
+    - batch:
+        schedule_delay: 1000
+        exporter:
+          otlp:
+            protocol: http/protobuf
+            endpoint: http://localhost:4318/v1/traces
+            headers:
+              api-key: ${env:TEST_FILE_API_KEY}
+            timeout: 5000
+    - batch:
+        exporter:
+          otlp:
+            protocol: http/protobuf
+            endpoint: http://backup:4318/v1/traces
+  sampler:
+    parent_based:
+      root:
+        trace_id_ratio_based:
+          ratio: 0.5
+  limits:
+    attribute_count_limit: 64
+meter_provider:
+  readers: []
+"#;
+
+    #[test]
+    fn test_substitute_env() {
+        let source = HashMap::from([("TEST_SUBSTITUTE", "v")]);
+        assert_eq!(
+            substitute_env("${TEST_SUBSTITUTE}/${env:TEST_SUBSTITUTE}", &source).unwrap(),
+            "v/v"
+        );
+        assert_eq!(
+            substitute_env(
+                "${TEST_SUBSTITUTE_MISSING:-d} ${TEST_SUBSTITUTE_MISSING}|",
+                &source
+            )
+            .unwrap(),
+            "d |"
+        );
+        assert_eq!(
+            substitute_env("$$ $1 ${TEST_SUBSTITUTE}$", &source).unwrap(),
+            "$ $1 v$"
+        );
+        assert!(substitute_env("${TEST_SUBSTITUTE", &source).is_err());
+        assert!(substitute_env("${1A}", &source).is_err());
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let source = HashMap::from([
+            ("TEST_FILE_SERVICE_NAME", "svc"),
+            ("TEST_FILE_API_KEY", "secret"),
+        ]);
+        let settings = parse_trace_settings(YAML, FileFormat::Yaml, &source).unwrap();
+        assert!(!settings.disabled);
+        assert!(settings.batch_config.is_some());
+        assert_eq!(settings.config_builders.len(), 2);
+        let config = settings.config_builders[0].clone().build().unwrap();
+        assert_eq!(config.endpoint(), "http://localhost:4318/v1/traces");
+        assert_eq!(config.headers()["api-key"], vec!["secret".to_string()]);
+        assert_eq!(config.timeout().as_millis(), 5000);
+        assert_eq!(
+            settings.tracer_config.resource.get(SERVICE_NAME),
+            Some("svc".into())
+        );
+        assert_eq!(
+            settings.tracer_config.span_limits.max_attributes_per_span,
+            64
+        );
+    }
+
+    #[test]
+    fn test_substitute_env_without_injection() {
+        let injected = "x\nexporter: {endpoint: http://evil}\ntracer_provider: {}";
+        let source = HashMap::from([
+            ("TEST_FILE_SERVICE_NAME", injected),
+            ("TEST_FILE_API_KEY", "secret"),
+            ("TEST_FILE_TIMEOUT", " 2000"),
+            ("TEST_FILE_DISABLED", "false"),
+        ]);
+        let yaml = YAML
+            .replace("timeout: 5000", "timeout: ${TEST_FILE_TIMEOUT}")
+            .replace(
+                "file_format: \"0.1\"",
+                "file_format: \"0.1\"\ndisabled: ${TEST_FILE_DISABLED}",
+            );
+        let settings = parse_trace_settings(&yaml, FileFormat::Yaml, &source).unwrap();
+        assert!(!settings.disabled);
+        assert_eq!(settings.config_builders.len(), 2);
+        let config = settings.config_builders[0].clone().build().unwrap();
+        assert_eq!(config.endpoint(), "http://localhost:4318/v1/traces");
+        assert_eq!(config.timeout().as_millis(), 2000);
+        // the value is a string, not a part of the file
+        assert_eq!(
+            settings.tracer_config.resource.get(SERVICE_NAME),
+            Some(injected.into())
+        );
+
+        let settings = parse_trace_settings(
+            "file_format = \"0.1\"\ndisabled = \"${TEST_FILE_INJECTED}\"\n",
+            FileFormat::Toml,
+            &HashMap::from([("TEST_FILE_INJECTED", "true\"\n[tracer_provider]\nx = \"")]),
+        );
+        assert!(settings.is_err());
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let settings = parse_trace_settings(
+            r#"
+file_format = "0.1"
+disabled = true
+
+[[tracer_provider.processors]]
+[tracer_provider.processors.simple.exporter.otlp]
+protocol = "http/protobuf"
+endpoint = "http://localhost:4318/v1/traces"
+"#,
+            FileFormat::Toml,
+            &HashMap::<&str, &str>::new(),
+        )
+        .unwrap();
+        assert!(settings.disabled);
+        assert!(settings.batch_config.is_none());
+        assert_eq!(settings.config_builders.len(), 1);
+    }
+
+    #[test]
+    fn test_error_path() {
+        let cases = [
+            (
+                "file_format: '0.1'\ntracer_provider:\n  processors:\n    - batch:\n        exporter:\n          otlp:\n            protocol: http/protobuf\n            endpoint: http://localhost:4318\n            timeout: ten\n",
+                "tracer_provider.processors[0].batch.exporter.otlp.timeout",
+            ),
+            (
+                "file_format: '0.1'\ntracer_provider:\n  processors:\n    - simple:\n        exporter:\n          console: {}\n",
+                "tracer_provider.processors[0].simple.exporter.console",
+            ),
+            (
+                "file_format: '0.1'\ntracer_provider:\n  processors:\n    - simple:\n        exporter:\n          otlp:\n            protocol: udp\n            endpoint: http://localhost:4318\n",
+                "tracer_provider.processors[0].simple.exporter.otlp.protocol",
+            ),
+            (
+                "file_format: '0.1'\ntracer_provider:\n  sampler:\n    parent_based:\n      root:\n        trace_id_ratio_based:\n          ratio: 2\n",
+                "tracer_provider.sampler.parent_based.root.trace_id_ratio_based.ratio",
+            ),
+            ("file_format: '1.0'\n", "file_format"),
+            ("file_format: '0.1'\nunknown: 1\n", "unknown field `unknown`"),
+        ];
+        for (content, expected) in cases {
+            let err = match parse_trace_settings(
+                content,
+                FileFormat::Yaml,
+                &HashMap::<&str, &str>::new(),
+            ) {
+                Ok(_) => panic!("{content} should be invalid"),
+                Err(e) => e.to_string(),
+            };
+            assert!(err.contains(expected), "{err} should contain {expected}");
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+/// Default duration the rate can be exceeded, the bucket holds 1 second of tokens.
+pub const DEFAULT_RATE_LIMIT_BURST: Duration = Duration::from_secs(1);
+
+/// What to do with spans exceeding the rate limit.
+///
+/// It is tagged by `policy` in serde, like `{"policy": "delay", "max_delay": "5s"}`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "policy", rename_all = "snake_case"))]
+pub enum OverflowPolicy {
+    /// Export spans in the order of the batch until the budget runs out, drop the rest.
+    #[default]
+    Drop,
+    /// Keep spans by their trace ids in the ratio of the budget to the batch, so that kept
+    /// traces are complete.
+    Sample,
+    /// Wait for the budget up to `max_delay`, spans which need to wait longer are dropped.
+    Delay {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        max_delay: Duration,
+    },
+}
+
+/// A token-bucket limit of exported spans and bytes, set it with
+/// [`crate::config::ConfigBuilder::with_rate_limit`].
+///
+/// Bytes are estimated from names, attributes, events and links of spans, the resource and
+/// the protocol overhead are not counted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RateLimit {
+    /// Unlimited if it is not set.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    spans_per_second: Option<u64>,
+
+    /// Unlimited if it is not set.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    bytes_per_second: Option<u64>,
+
+    /// How long the rate can be exceeded after being idle, buckets hold `rate * burst` tokens.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    burst: Duration,
+
+    overflow: OverflowPolicy,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            spans_per_second: None,
+            bytes_per_second: None,
+            burst: DEFAULT_RATE_LIMIT_BURST,
+            overflow: Default::default(),
+        }
+    }
+}
+
+impl RateLimit {
+    pub fn spans_per_second(&self) -> Option<u64> {
+        self.spans_per_second
+    }
+
+    pub fn bytes_per_second(&self) -> Option<u64> {
+        self.bytes_per_second
+    }
+
+    pub fn burst(&self) -> Duration {
+        self.burst
+    }
+
+    pub fn overflow(&self) -> &OverflowPolicy {
+        &self.overflow
+    }
+
+    pub fn with_spans_per_second(mut self, spans_per_second: u64) -> Self {
+        self.spans_per_second = Some(spans_per_second);
+        self
+    }
+
+    pub fn with_bytes_per_second(mut self, bytes_per_second: u64) -> Self {
+        self.bytes_per_second = Some(bytes_per_second);
+        self
+    }
+
+    pub fn with_burst(mut self, burst: Duration) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    pub(crate) fn validate(&self) -> OtlpExporterResult<()> {
+        if self.spans_per_second == Some(0) || self.bytes_per_second == Some(0) {
+            return Err(OtlpExporterError::ConfigError(
+                "rate limits should be greater than 0".to_owned(),
+            ));
+        }
+        if self.burst.is_zero() {
+            return Err(OtlpExporterError::ConfigError(
+                "burst of the rate limit should be greater than 0".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
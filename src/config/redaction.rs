@@ -0,0 +1,271 @@
+use std::fmt;
+
+use opentelemetry_api::KeyValue;
+
+/// A hook applied to each attribute of spans, span events and span links before they are
+/// encoded, set it with [`crate::config::ConfigBuilder::with_redaction_hook`].
+pub trait RedactionHook: fmt::Debug + Send + Sync {
+    /// Return the attribute to export, or `None` to remove it. Removed attributes are counted as
+    /// dropped ones. If span attributes are renamed to the same key, only one of them is kept and
+    /// the others are counted as dropped.
+    fn redact(&self, attribute: KeyValue) -> Option<KeyValue>;
+}
+
+#[cfg(feature = "redaction")]
+pub use self::rules::{MaskRule, RedactionRules, DEFAULT_MASK_REPLACEMENT};
+
+#[cfg(feature = "redaction")]
+mod rules {
+    use std::fmt::Write;
+
+    use opentelemetry_api::{Array, KeyValue, StringValue, Value};
+    use regex::Regex;
+    use ring::hmac;
+
+    use super::RedactionHook;
+    use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+    /// Default replacement of [`MaskRule`].
+    pub const DEFAULT_MASK_REPLACEMENT: &str = "[REDACTED]";
+
+    /// Replace matches of `pattern` in string values with `replacement`, which can refer to
+    /// capture groups like `$1`.
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct MaskRule {
+        pattern: String,
+        #[cfg_attr(feature = "serde", serde(default = "default_replacement"))]
+        replacement: String,
+    }
+
+    #[cfg(feature = "serde")]
+    fn default_replacement() -> String {
+        DEFAULT_MASK_REPLACEMENT.to_owned()
+    }
+
+    impl MaskRule {
+        pub fn new(pattern: impl Into<String>) -> Self {
+            Self {
+                pattern: pattern.into(),
+                replacement: DEFAULT_MASK_REPLACEMENT.to_owned(),
+            }
+        }
+
+        pub fn pattern(&self) -> &str {
+            &self.pattern
+        }
+
+        pub fn replacement(&self) -> &str {
+            &self.replacement
+        }
+
+        pub fn with_replacement(mut self, replacement: impl Into<String>) -> Self {
+            self.replacement = replacement.into();
+            self
+        }
+    }
+
+    /// Built-in redaction rules, set them with [`crate::config::ConfigBuilder::with_redaction`].
+    ///
+    /// Attributes with denied keys are removed, then values of hashed keys are replaced with the
+    /// hex HMAC-SHA256 of their string form, and masks apply to remaining string values in order.
+    /// A hash secret is required with hashed keys, since hashes without a secret are reversed by a
+    /// dictionary of emails, user ids or card numbers. With feature `serde`, the hash secret is not
+    /// serialized.
+    #[derive(Clone, Debug, Default, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub struct RedactionRules {
+        deny_keys: Vec<String>,
+        masks: Vec<MaskRule>,
+        hash_keys: Vec<String>,
+        /// Key of HMAC, hashes are comparable only between exporters with the same secret.
+        #[cfg_attr(feature = "serde", serde(skip_serializing))]
+        hash_secret: Option<String>,
+    }
+
+    impl RedactionRules {
+        pub fn deny_keys(&self) -> &[String] {
+            &self.deny_keys
+        }
+
+        pub fn masks(&self) -> &[MaskRule] {
+            &self.masks
+        }
+
+        pub fn hash_keys(&self) -> &[String] {
+            &self.hash_keys
+        }
+
+        /// Remove attributes with `key`.
+        pub fn add_deny_key(mut self, key: impl Into<String>) -> Self {
+            self.deny_keys.push(key.into());
+            self
+        }
+
+        pub fn add_mask(mut self, mask: MaskRule) -> Self {
+            self.masks.push(mask);
+            self
+        }
+
+        /// Replace values of `key` with their hashes.
+        pub fn add_hash_key(mut self, key: impl Into<String>) -> Self {
+            self.hash_keys.push(key.into());
+            self
+        }
+
+        pub fn with_hash_secret(mut self, hash_secret: impl Into<String>) -> Self {
+            self.hash_secret = Some(hash_secret.into());
+            self
+        }
+
+        /// Compile patterns of masks, it fails if any of them is invalid, or there are hashed keys
+        /// without a hash secret.
+        pub(crate) fn compile(&self) -> OtlpExporterResult<CompiledRules> {
+            let hash_secret = self.hash_secret.as_deref().unwrap_or_default();
+            if !self.hash_keys.is_empty() && hash_secret.is_empty() {
+                return Err(OtlpExporterError::ConfigError(
+                    "hash_secret of redaction should be set with hash_keys".to_owned(),
+                ));
+            }
+            let masks = self
+                .masks
+                .iter()
+                .map(|mask| {
+                    let regex = Regex::new(&mask.pattern).map_err(|e| {
+                        OtlpExporterError::ConfigError(format!(
+                            "invalid mask pattern[{}]: {e}",
+                            mask.pattern
+                        ))
+                    })?;
+                    Ok((regex, mask.replacement.clone()))
+                })
+                .collect::<OtlpExporterResult<_>>()?;
+            Ok(CompiledRules {
+                deny_keys: self.deny_keys.clone(),
+                masks,
+                hash_keys: self.hash_keys.clone(),
+                hash_key: hmac::Key::new(hmac::HMAC_SHA256, hash_secret.as_bytes()),
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct CompiledRules {
+        deny_keys: Vec<String>,
+        masks: Vec<(Regex, String)>,
+        hash_keys: Vec<String>,
+        hash_key: hmac::Key,
+    }
+
+    impl CompiledRules {
+        fn hash(&self, value: &Value) -> Value {
+            let tag = hmac::sign(&self.hash_key, value.as_str().as_bytes());
+            let mut hex = String::with_capacity(tag.as_ref().len() * 2);
+            for byte in tag.as_ref() {
+                let _ = write!(hex, "{byte:02x}");
+            }
+            hex.into()
+        }
+
+        fn mask(&self, s: &str) -> String {
+            self.masks
+                .iter()
+                .fold(s.to_owned(), |s, (regex, replacement)| {
+                    regex.replace_all(&s, replacement.as_str()).into_owned()
+                })
+        }
+
+        fn mask_string(&self, s: StringValue) -> StringValue {
+            let masked = self.mask(s.as_str());
+            if masked == s.as_str() {
+                s
+            } else {
+                masked.into()
+            }
+        }
+    }
+
+    impl RedactionHook for CompiledRules {
+        fn redact(&self, attribute: KeyValue) -> Option<KeyValue> {
+            let KeyValue { key, value } = attribute;
+            if self.deny_keys.iter().any(|k| k == key.as_str()) {
+                return None;
+            }
+            if self.hash_keys.iter().any(|k| k == key.as_str()) {
+                let value = self.hash(&value);
+                return Some(KeyValue { key, value });
+            }
+            if self.masks.is_empty() {
+                return Some(KeyValue { key, value });
+            }
+            let value = match value {
+                Value::String(s) => Value::String(self.mask_string(s)),
+                Value::Array(Array::String(values)) => Value::Array(Array::String(
+                    values.into_iter().map(|s| self.mask_string(s)).collect(),
+                )),
+                value => value,
+            };
+            Some(KeyValue { key, value })
+        }
+    }
+}
+
+#[cfg(all(test, feature = "redaction"))]
+mod tests {
+    use opentelemetry_api::{KeyValue, Value};
+
+    use super::{MaskRule, RedactionRules};
+    use crate::config::RedactionHook;
+
+    #[test]
+    fn test_redaction_rules() {
+        let rules = RedactionRules::default()
+            .add_deny_key("password")
+            .add_hash_key("user.id")
+            .add_mask(MaskRule::new(r"[\w.+-]+@[\w-]+\.[\w.]+"))
+            .add_mask(MaskRule::new(r"\b(\d{4})\d{8}(\d{4})\b").with_replacement("$1****$2"))
+            .with_hash_secret("secret")
+            .compile()
+            .unwrap();
+
+        assert!(rules.redact(KeyValue::new("password", "p")).is_none());
+        let hashed = rules.redact(KeyValue::new("user.id", 42)).unwrap().value;
+        assert_eq!(hashed.as_str().len(), 64);
+        assert_ne!(hashed, Value::from(42));
+        // hashes are stable
+        assert_eq!(
+            rules.redact(KeyValue::new("user.id", "42")).unwrap().value,
+            hashed
+        );
+        assert_eq!(
+            rules
+                .redact(KeyValue::new(
+                    "message",
+                    "mail a@b.com, card 4111111111111111"
+                ))
+                .unwrap()
+                .value,
+            Value::from("mail [REDACTED], card 4111****1111")
+        );
+        assert_eq!(
+            rules.redact(KeyValue::new("count", 1)).unwrap().value,
+            Value::from(1)
+        );
+
+        assert!(RedactionRules::default()
+            .add_mask(MaskRule::new("("))
+            .compile()
+            .is_err());
+        // hashes without a secret are reversible
+        assert!(RedactionRules::default()
+            .add_hash_key("user.id")
+            .compile()
+            .is_err());
+        assert!(RedactionRules::default()
+            .add_hash_key("user.id")
+            .with_hash_secret("")
+            .compile()
+            .is_err());
+    }
+}
@@ -0,0 +1,71 @@
+//! Sources of env values.
+//!
+//! `with_env` reads the process env, `with_source` reads any [`EnvSource`], e.g. a `HashMap` or a
+//! [`Prefixed`] source, so that several pipelines can be configured from separate maps.
+
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    hash::{BuildHasher, Hash},
+};
+
+/// A source of env values, keys are the spec names like `OTEL_EXPORTER_OTLP_ENDPOINT`.
+pub trait EnvSource {
+    fn get(&self, key: &str) -> Option<OsString>;
+
+    /// Return `None` if the value is not unicode.
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.get(key)?.into_string().ok()
+    }
+}
+
+/// The process env, it is the default source.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn get(&self, key: &str) -> Option<OsString> {
+        std::env::var_os(key)
+    }
+}
+
+impl<K, V, S> EnvSource for HashMap<K, V, S>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: AsRef<OsStr>,
+    S: BuildHasher,
+{
+    fn get(&self, key: &str) -> Option<OsString> {
+        HashMap::get(self, key).map(|v| v.as_ref().to_os_string())
+    }
+}
+
+impl<E: EnvSource + ?Sized> EnvSource for &E {
+    fn get(&self, key: &str) -> Option<OsString> {
+        (**self).get(key)
+    }
+}
+
+/// Read `{prefix}{key}` from the inner source, e.g. `MYAPP_OTEL_EXPORTER_OTLP_ENDPOINT` with
+/// prefix `MYAPP_`.
+#[derive(Clone, Debug)]
+pub struct Prefixed<E> {
+    prefix: String,
+    inner: E,
+}
+
+impl<E: EnvSource> Prefixed<E> {
+    pub fn new(prefix: impl Into<String>, inner: E) -> Self {
+        Self {
+            prefix: prefix.into(),
+            inner,
+        }
+    }
+}
+
+impl<E: EnvSource> EnvSource for Prefixed<E> {
+    fn get(&self, key: &str) -> Option<OsString> {
+        self.inner.get(&format!("{}{key}", self.prefix))
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+/// Default duration spans of a trace are buffered before the trace is decided.
+pub const DEFAULT_TAIL_SAMPLING_DECISION_WAIT: Duration = Duration::from_secs(10);
+/// Default max number of traces being buffered.
+pub const DEFAULT_TAIL_SAMPLING_MAX_TRACES: usize = 10_000;
+/// Default max number of spans being buffered.
+pub const DEFAULT_TAIL_SAMPLING_MAX_SPANS: usize = 100_000;
+
+/// Status code of a span, matched by [`SamplingPolicy::StatusCode`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SpanStatusCode {
+    Unset,
+    Ok,
+    Error,
+}
+
+/// A policy to keep a trace after its decision window.
+///
+/// It is tagged by `type` in serde, like `{"type": "latency", "threshold": "2s"}`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum SamplingPolicy {
+    /// Keep traces with any span in one of `status_codes`.
+    StatusCode { status_codes: Vec<SpanStatusCode> },
+    /// Keep traces lasting at least `threshold`, from the earliest start to the latest end of
+    /// their spans, which is the duration of the root span once it is buffered.
+    Latency {
+        #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+        threshold: Duration,
+    },
+    /// Keep traces with any span having attribute `key`, whose value in string is one of
+    /// `values`. Any value matches if `values` is empty.
+    Attribute {
+        key: String,
+        #[cfg_attr(feature = "serde", serde(default))]
+        values: Vec<String>,
+    },
+    /// Keep traces by their trace ids in `ratio`, like `Sampler::TraceIdRatioBased`.
+    Probabilistic { ratio: f64 },
+    /// Keep traces until spans kept by this policy reach `spans_per_second` in a second.
+    RateLimiting { spans_per_second: u64 },
+}
+
+/// Settings of tail-based sampling, set it with
+/// [`crate::config::ConfigBuilder::with_tail_sampling`].
+///
+/// Spans are buffered by their trace ids for `decision_wait` since the first span of the trace
+/// arrives. Policies are evaluated in order, a trace is exported if any of them keeps it. Late
+/// spans of a decided trace follow the decision as long as it is remembered, up to `max_traces`
+/// decisions are remembered.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TailSampling {
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    decision_wait: Duration,
+
+    /// The oldest traces are decided early when buffered traces exceed it.
+    max_traces: usize,
+
+    /// The oldest traces are decided early when buffered spans exceed it.
+    max_spans: usize,
+
+    policies: Vec<SamplingPolicy>,
+}
+
+impl Default for TailSampling {
+    fn default() -> Self {
+        Self {
+            decision_wait: DEFAULT_TAIL_SAMPLING_DECISION_WAIT,
+            max_traces: DEFAULT_TAIL_SAMPLING_MAX_TRACES,
+            max_spans: DEFAULT_TAIL_SAMPLING_MAX_SPANS,
+            policies: Vec::new(),
+        }
+    }
+}
+
+impl TailSampling {
+    pub fn decision_wait(&self) -> Duration {
+        self.decision_wait
+    }
+
+    pub fn max_traces(&self) -> usize {
+        self.max_traces
+    }
+
+    pub fn max_spans(&self) -> usize {
+        self.max_spans
+    }
+
+    pub fn policies(&self) -> &[SamplingPolicy] {
+        &self.policies
+    }
+
+    pub fn with_decision_wait(mut self, decision_wait: Duration) -> Self {
+        self.decision_wait = decision_wait;
+        self
+    }
+
+    pub fn with_max_traces(mut self, max_traces: usize) -> Self {
+        self.max_traces = max_traces;
+        self
+    }
+
+    pub fn with_max_spans(mut self, max_spans: usize) -> Self {
+        self.max_spans = max_spans;
+        self
+    }
+
+    pub fn add_policy(mut self, policy: SamplingPolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    pub(crate) fn validate(&self) -> OtlpExporterResult<()> {
+        let error = |message: &str| OtlpExporterError::ConfigError(message.to_owned());
+        if self.decision_wait.is_zero() {
+            return Err(error(
+                "decision_wait of tail sampling should be greater than 0",
+            ));
+        }
+        if self.max_traces == 0 || self.max_spans == 0 {
+            return Err(error(
+                "max_traces and max_spans of tail sampling should be greater than 0",
+            ));
+        }
+        if self.policies.is_empty() {
+            return Err(error("tail sampling should have at least one policy"));
+        }
+        for policy in &self.policies {
+            match policy {
+                SamplingPolicy::Probabilistic { ratio } if !(0.0..=1.0).contains(ratio) => {
+                    return Err(error(
+                        "ratio of the probabilistic policy should be in [0, 1]",
+                    ));
+                }
+                SamplingPolicy::RateLimiting {
+                    spans_per_second: 0,
+                } => {
+                    return Err(error(
+                        "spans_per_second of the rate limiting policy should be greater than 0",
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
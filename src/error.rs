@@ -21,6 +21,20 @@ pub enum OtlpExporterError {
     #[cfg(feature = "http")]
     #[error("unsupported: {0}")]
     Unsupported(String),
+    #[error("fan-out error: {0}")]
+    FanOutError(String),
+    #[error("failover: {0}")]
+    FailoverError(String),
+    #[error("load balancing error: {0}")]
+    LoadBalancingError(String),
+    #[error("circuit breaker: {0}")]
+    CircuitBreakerError(String),
+    #[error("rate limited: {0}")]
+    RateLimitError(String),
+    #[error("tail sampling: {0}")]
+    TailSamplingError(String),
+    #[error("shutdown error: {0}")]
+    ShutdownError(String),
     #[error("unknown error: {0}")]
     UnknownError(String),
 }
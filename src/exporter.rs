@@ -8,6 +8,10 @@ use crate::error::{OtlpExporterError, OtlpExporterResult};
 pub mod log;
 #[cfg(feature = "metrics")]
 pub mod metric;
+pub mod self_metrics;
+#[cfg(all(feature = "traces", feature = "metrics"))]
+pub mod span_metrics;
+pub mod suppression;
 #[cfg(feature = "traces")]
 pub mod trace;
 
@@ -37,6 +41,7 @@ fn gen_header_map(headers: &HashMap<String, Vec<String>>) -> OtlpExporterResult<
 pub(crate) mod tonic {
     use std::{collections::HashMap, time::Duration};
 
+    use http::Uri;
     #[cfg(feature = "traces")]
     use opentelemetry_api::trace::TraceError;
     use tonic::{metadata::MetadataMap, transport::Channel, Status};
@@ -74,8 +79,26 @@ pub(crate) mod tonic {
                 }
             }
 
+            let channel_builder = match config.connect_address() {
+                // connect to the address, the endpoint is still the origin of requests
+                Some(address) => {
+                    let mut parts = config.endpoint().clone().into_parts();
+                    parts.authority = Some(address.to_string().parse().map_err(|e| {
+                        OtlpExporterError::ConfigError(format!(
+                            "address[{address}] is not a valid authority: {e}"
+                        ))
+                    })?);
+                    let uri = Uri::from_parts(parts).map_err(|e| {
+                        OtlpExporterError::UnknownError(format!(
+                            "internal error! endpoint_parts should be valid: {e}"
+                        ))
+                    })?;
+                    Channel::builder(uri).origin(config.endpoint().clone())
+                }
+                None => Channel::builder(config.endpoint().clone()),
+            };
             #[allow(unused_mut)]
-            let mut channel_builder = Channel::builder(config.endpoint().clone())
+            let mut channel_builder = channel_builder
                 .connect_timeout(config.timeout())
                 .timeout(config.timeout());
 
@@ -90,7 +113,13 @@ pub(crate) mod tonic {
                 {
                     tls_config = tls_config.identity(Identity::from_pem(cert, key));
                 }
-                if let Some(domain) = config.certificate_domain() {
+                // the host of the endpoint is verified rather than the address connected to
+                let domain = config.certificate_domain().or_else(|| {
+                    config
+                        .connect_address()
+                        .and_then(|_| config.endpoint().host())
+                });
+                if let Some(domain) = domain {
                     tls_config = tls_config.domain_name(domain.to_owned());
                 }
                 channel_builder = channel_builder.tls_config(tls_config).map_err(|e| {
@@ -146,6 +175,10 @@ mod grpcio {
 
             let host = config.endpoint().host().expect("endpoint should have host");
             let port = config.endpoint().port_u16().unwrap_or(4317);
+            let target = match config.connect_address() {
+                Some(address) => address.to_string(),
+                None => format!("{}:{}", host, port),
+            };
 
             #[allow(unused_mut)]
             let mut channel_builder = match config.grpc_impl() {
@@ -177,9 +210,16 @@ mod grpcio {
                 }
                 channel_builder =
                     channel_builder.set_credentials(channel_credentials_builder.build());
+                if config.connect_address().is_some() {
+                    channel_builder = channel_builder.override_ssl_target(host);
+                }
+            }
+            if config.connect_address().is_some() {
+                // the host of the endpoint is still the authority of requests
+                channel_builder = channel_builder.default_authority(format!("{}:{}", host, port));
             }
 
-            Ok(channel_builder.connect(&format!("{}:{}", host, port)))
+            Ok(channel_builder.connect(&target))
         }
     }
 
@@ -239,11 +279,17 @@ mod http {
                 }
             }
 
-            #[allow(unused_mut)]
             let mut builder = Client::builder()
                 .connect_timeout(config.timeout())
                 .timeout(config.timeout())
                 .default_headers(gen_header_map(config.headers())?);
+            // connect to the address, the host of the endpoint is still used for TLS and the
+            // `Host` header
+            if let (Some(address), Some(host)) =
+                (config.connect_address(), config.endpoint().host())
+            {
+                builder = builder.resolve(host, address);
+            }
 
             #[cfg(feature = "_http-tls")]
             if !config.insecure() {
@@ -0,0 +1,418 @@
+//! Self-observability metrics of exporters.
+//!
+//! Instruments follow the semantic conventions of OTel SDK metrics:
+//!
+//! - `otel.sdk.exporter.span.exported`: counter of spans, failed ones have attribute
+//!   `error.type`.
+//! - `otel.sdk.exporter.span.inflight`: up-down counter of spans being exported.
+//! - `otel.sdk.exporter.operation.duration`: histogram of export latency in seconds.
+//!
+//! There are no conventions for the following ones yet:
+//!
+//! - `otlp_exporter.request.inflight`: up-down counter of requests being sent.
+//! - `otlp_exporter.request.uncompressed_size`: counter of request bytes before compression.
+//!   Compression is not supported yet, a counter of bytes after it will be added with it.
+//! - `otlp_exporter.request.retries`: counter of retried requests. Requests are not retried
+//!   yet, so it is always 0.
+//! - `otlp_exporter.span.rate_limited`: counter of spans dropped by the rate limit.
+//! - `otlp_exporter.span.sampled_out`: counter of spans dropped by tail sampling.
+//! - `otlp_exporter.trace.evicted`: counter of traces decided before their decision windows end,
+//!   because the buffer of tail sampling is full.
+//!
+//! All of them have attributes `otel.component.type`(e.g. `otlp_grpc_span_exporter`),
+//! `server.address` and `server.port`.
+
+// only trace exporters record metrics so far
+#![cfg_attr(not(feature = "traces"), allow(dead_code))]
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "metrics")]
+use opentelemetry_api::KeyValue;
+
+use crate::config::Config;
+
+/// Collect metrics of exporters, clones share the same metrics.
+///
+/// Set it with [`crate::config::ConfigBuilder::with_metrics`], all exporters built from the
+/// config record into it.
+#[derive(Clone, Default)]
+pub struct ExporterMetrics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    counters: Counters,
+    #[cfg(feature = "metrics")]
+    instruments: Option<Instruments>,
+}
+
+#[derive(Default)]
+struct Counters {
+    exported_spans: AtomicU64,
+    failed_spans: AtomicU64,
+    in_flight_spans: AtomicI64,
+    requests: AtomicU64,
+    failed_requests: AtomicU64,
+    in_flight_requests: AtomicI64,
+    uncompressed_bytes: AtomicU64,
+    retries: AtomicU64,
+    rate_limited_spans: AtomicU64,
+    sampled_out_spans: AtomicU64,
+    evicted_traces: AtomicU64,
+    duration_nanos: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+struct Instruments {
+    exported_spans: opentelemetry_api::metrics::Counter<u64>,
+    in_flight_spans: opentelemetry_api::metrics::UpDownCounter<i64>,
+    duration: opentelemetry_api::metrics::Histogram<f64>,
+    in_flight_requests: opentelemetry_api::metrics::UpDownCounter<i64>,
+    uncompressed_size: opentelemetry_api::metrics::Counter<u64>,
+    retries: opentelemetry_api::metrics::Counter<u64>,
+    rate_limited_spans: opentelemetry_api::metrics::Counter<u64>,
+    sampled_out_spans: opentelemetry_api::metrics::Counter<u64>,
+    evicted_traces: opentelemetry_api::metrics::Counter<u64>,
+}
+
+/// A point-in-time copy of [`ExporterMetrics`], for those without a metrics pipeline.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExporterMetricsSnapshot {
+    pub exported_spans: u64,
+    pub failed_spans: u64,
+    pub in_flight_spans: u64,
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub in_flight_requests: u64,
+    pub uncompressed_bytes: u64,
+    pub retries: u64,
+    /// Spans dropped by the rate limit.
+    pub rate_limited_spans: u64,
+    /// Spans dropped by tail sampling.
+    pub sampled_out_spans: u64,
+    /// Traces decided early because the buffer of tail sampling is full.
+    pub evicted_traces: u64,
+    /// Total latency of finished requests.
+    pub export_duration: Duration,
+}
+
+impl ExporterMetrics {
+    /// Metrics are only available by [`ExporterMetrics::snapshot`].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Publish metrics through `meter` too.
+    #[cfg(feature = "metrics")]
+    pub fn with_meter(meter: &opentelemetry_api::metrics::Meter) -> Self {
+        use opentelemetry_api::metrics::Unit;
+
+        let instruments = Instruments {
+            exported_spans: meter
+                .u64_counter("otel.sdk.exporter.span.exported")
+                .with_description("The number of spans for which the export has finished")
+                .with_unit(Unit::new("{span}"))
+                .init(),
+            in_flight_spans: meter
+                .i64_up_down_counter("otel.sdk.exporter.span.inflight")
+                .with_description("The number of spans which were passed to the exporter, but that have not been exported yet")
+                .with_unit(Unit::new("{span}"))
+                .init(),
+            duration: meter
+                .f64_histogram("otel.sdk.exporter.operation.duration")
+                .with_description("The duration of exporting a batch of telemetry records")
+                .with_unit(Unit::new("s"))
+                .init(),
+            in_flight_requests: meter
+                .i64_up_down_counter("otlp_exporter.request.inflight")
+                .with_description("The number of requests being sent")
+                .with_unit(Unit::new("{request}"))
+                .init(),
+            uncompressed_size: meter
+                .u64_counter("otlp_exporter.request.uncompressed_size")
+                .with_description("Request bytes before compression")
+                .with_unit(Unit::new("By"))
+                .init(),
+            retries: meter
+                .u64_counter("otlp_exporter.request.retries")
+                .with_description("The number of retried requests")
+                .with_unit(Unit::new("{request}"))
+                .init(),
+            rate_limited_spans: meter
+                .u64_counter("otlp_exporter.span.rate_limited")
+                .with_description("The number of spans dropped by the rate limit")
+                .with_unit(Unit::new("{span}"))
+                .init(),
+            sampled_out_spans: meter
+                .u64_counter("otlp_exporter.span.sampled_out")
+                .with_description("The number of spans dropped by tail sampling")
+                .with_unit(Unit::new("{span}"))
+                .init(),
+            evicted_traces: meter
+                .u64_counter("otlp_exporter.trace.evicted")
+                .with_description("The number of traces decided early because the buffer of tail sampling is full")
+                .with_unit(Unit::new("{trace}"))
+                .init(),
+        };
+        Self {
+            inner: Arc::new(Inner {
+                counters: Default::default(),
+                instruments: Some(instruments),
+            }),
+        }
+    }
+
+    pub fn snapshot(&self) -> ExporterMetricsSnapshot {
+        let counters = &self.inner.counters;
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let load_gauge = |gauge: &AtomicI64| gauge.load(Ordering::Relaxed).max(0) as u64;
+        ExporterMetricsSnapshot {
+            exported_spans: load(&counters.exported_spans),
+            failed_spans: load(&counters.failed_spans),
+            in_flight_spans: load_gauge(&counters.in_flight_spans),
+            requests: load(&counters.requests),
+            failed_requests: load(&counters.failed_requests),
+            in_flight_requests: load_gauge(&counters.in_flight_requests),
+            uncompressed_bytes: load(&counters.uncompressed_bytes),
+            retries: load(&counters.retries),
+            rate_limited_spans: load(&counters.rate_limited_spans),
+            sampled_out_spans: load(&counters.sampled_out_spans),
+            evicted_traces: load(&counters.evicted_traces),
+            export_duration: Duration::from_nanos(load(&counters.duration_nanos)),
+        }
+    }
+
+    /// Record the start of a request of `spans` spans, finish it with the returned recorder.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn start(
+        &self,
+        component_type: &'static str,
+        config: &Config,
+        spans: usize,
+        uncompressed_bytes: usize,
+    ) -> ExportRecorder {
+        let spans = spans as u64;
+        let counters = &self.inner.counters;
+        counters
+            .in_flight_spans
+            .fetch_add(spans as i64, Ordering::Relaxed);
+        counters.in_flight_requests.fetch_add(1, Ordering::Relaxed);
+        counters
+            .uncompressed_bytes
+            .fetch_add(uncompressed_bytes as u64, Ordering::Relaxed);
+
+        #[cfg(feature = "metrics")]
+        let attributes = component_attributes(component_type, config);
+        #[cfg(feature = "metrics")]
+        if let Some(instruments) = &self.inner.instruments {
+            instruments.in_flight_spans.add(spans as i64, &attributes);
+            instruments.in_flight_requests.add(1, &attributes);
+            instruments
+                .uncompressed_size
+                .add(uncompressed_bytes as u64, &attributes);
+            // there is no retry yet, make the counter visible anyway
+            instruments.retries.add(0, &attributes);
+        }
+        ExportRecorder {
+            metrics: self.clone(),
+            #[cfg(feature = "metrics")]
+            attributes,
+            spans,
+            start: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// Record `spans` spans dropped by the rate limit of `config`.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn record_rate_limited(&self, config: &Config, spans: usize) {
+        self.inner
+            .counters
+            .rate_limited_spans
+            .fetch_add(spans as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        if let Some(instruments) = &self.inner.instruments {
+            instruments.rate_limited_spans.add(
+                spans as u64,
+                &component_attributes("otlp_span_rate_limiter", config),
+            );
+        }
+    }
+
+    /// Record `spans` spans dropped and `traces` traces evicted by tail sampling of `config`.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn record_tail_sampling(&self, config: &Config, spans: usize, traces: usize) {
+        let counters = &self.inner.counters;
+        counters
+            .sampled_out_spans
+            .fetch_add(spans as u64, Ordering::Relaxed);
+        counters
+            .evicted_traces
+            .fetch_add(traces as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        if let Some(instruments) = &self.inner.instruments {
+            let attributes = component_attributes("otlp_span_tail_sampler", config);
+            instruments.sampled_out_spans.add(spans as u64, &attributes);
+            instruments.evicted_traces.add(traces as u64, &attributes);
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn component_attributes(component_type: &'static str, config: &Config) -> Vec<KeyValue> {
+    let mut attributes = vec![KeyValue::new("otel.component.type", component_type)];
+    if let Some(host) = config.endpoint().host() {
+        attributes.push(KeyValue::new("server.address", host.to_owned()));
+    }
+    if let Some(port) = config.endpoint().port_u16() {
+        attributes.push(KeyValue::new("server.port", i64::from(port)));
+    }
+    attributes
+}
+
+impl fmt::Debug for ExporterMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ExporterMetrics")
+            .field(&self.snapshot())
+            .finish()
+    }
+}
+
+/// Record a request started by [`ExporterMetrics::start`], a dropped request is recorded as
+/// cancelled.
+pub(crate) struct ExportRecorder {
+    metrics: ExporterMetrics,
+    #[cfg(feature = "metrics")]
+    attributes: Vec<KeyValue>,
+    spans: u64,
+    start: Instant,
+    finished: bool,
+}
+
+impl ExportRecorder {
+    /// `error_type` is the value of attribute `error.type` if the request failed.
+    pub(crate) fn finish(mut self, error_type: Option<String>) {
+        self.record(error_type);
+    }
+
+    fn record(&mut self, error_type: Option<String>) {
+        self.finished = true;
+        let elapsed = self.start.elapsed();
+        let counters = &self.metrics.inner.counters;
+        counters
+            .in_flight_spans
+            .fetch_sub(self.spans as i64, Ordering::Relaxed);
+        counters.in_flight_requests.fetch_sub(1, Ordering::Relaxed);
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        counters
+            .duration_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        if error_type.is_some() {
+            counters.failed_requests.fetch_add(1, Ordering::Relaxed);
+            counters
+                .failed_spans
+                .fetch_add(self.spans, Ordering::Relaxed);
+        } else {
+            counters
+                .exported_spans
+                .fetch_add(self.spans, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(instruments) = &self.metrics.inner.instruments {
+            instruments
+                .in_flight_spans
+                .add(-(self.spans as i64), &self.attributes);
+            instruments.in_flight_requests.add(-1, &self.attributes);
+            let mut attributes = self.attributes.clone();
+            if let Some(error_type) = error_type {
+                attributes.push(KeyValue::new("error.type", error_type));
+            }
+            instruments.exported_spans.add(self.spans, &attributes);
+            instruments
+                .duration
+                .record(elapsed.as_secs_f64(), &attributes);
+        }
+    }
+}
+
+impl Drop for ExportRecorder {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.record(Some("cancelled".to_owned()));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock-collector", feature = "http", feature = "tonic"))]
+mod tests {
+    use opentelemetry_sdk::export::trace::SpanExporter;
+
+    use super::ExporterMetrics;
+    use crate::{
+        config::Protocol,
+        exporter::trace::TraceExporter,
+        testing::{
+            collector::{MockBehavior, MockCollector},
+            test_span,
+        },
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_exporter_metrics() {
+        let collector = MockCollector::start().await.unwrap();
+        let metrics = ExporterMetrics::new();
+        let new_exporter = |protocol| {
+            TraceExporter::try_from(
+                collector
+                    .config_builder(protocol)
+                    .with_metrics(metrics.clone())
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+
+        let mut exporter = new_exporter(Protocol::HttpProtobuf);
+        exporter
+            .export(vec![test_span("1"), test_span("2")])
+            .await
+            .unwrap();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.exported_spans, 2);
+        assert_eq!(snapshot.requests, 1);
+        assert_eq!(snapshot.in_flight_spans, 0);
+        assert_eq!(snapshot.in_flight_requests, 0);
+        assert_eq!(
+            snapshot.uncompressed_bytes,
+            collector.requests()[0].body.len() as u64
+        );
+
+        collector.set_behavior(
+            MockBehavior::default().with_http_status(http::StatusCode::SERVICE_UNAVAILABLE),
+        );
+        assert!(exporter.export(vec![test_span("3")]).await.is_err());
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.exported_spans, 2);
+        assert_eq!(snapshot.failed_spans, 1);
+        assert_eq!(snapshot.failed_requests, 1);
+
+        collector.set_behavior(MockBehavior::default());
+        let mut exporter = new_exporter(Protocol::Grpc);
+        exporter.export(vec![test_span("4")]).await.unwrap();
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.exported_spans, 3);
+        assert_eq!(snapshot.requests, 3);
+        assert_eq!(snapshot.retries, 0);
+        assert!(snapshot.export_duration > std::time::Duration::ZERO);
+    }
+}
@@ -0,0 +1,164 @@
+//! Request rate, errors and duration (RED) metrics generated from spans.
+//!
+//! Instruments follow the `spanmetrics` connector of the OpenTelemetry Collector:
+//!
+//! - `traces.span.metrics.calls`: counter of spans, errors are those with `status.code`
+//!   `STATUS_CODE_ERROR`.
+//! - `traces.span.metrics.duration`: histogram of span durations in seconds.
+//!
+//! Both have attributes `service.name`, `span.name`, `span.kind`, `status.code` and the
+//! dimensions of [`SpanMetrics`].
+
+use std::fmt;
+
+use opentelemetry_api::{
+    metrics::{Counter, Histogram, Meter, Unit},
+    trace::{SpanKind, Status},
+    Key, KeyValue,
+};
+use opentelemetry_sdk::export::trace::SpanData;
+
+const SERVICE_NAME: Key = Key::from_static_str("service.name");
+
+/// Record RED metrics of spans through a meter, after they are redacted and before they are
+/// sampled by tail sampling or exported, so dimensions carry redacted values. Spans dropped by
+/// the sampler of the tracer provider never reach exporters, so they are not counted.
+///
+/// Set it with [`crate::config::ConfigBuilder::with_span_metrics`], clones share the same
+/// instruments.
+#[derive(Clone)]
+pub struct SpanMetrics {
+    calls: Counter<u64>,
+    duration: Histogram<f64>,
+    dimensions: Vec<Key>,
+}
+
+impl SpanMetrics {
+    /// Create instruments with `meter`, the meter provider decides where metrics go.
+    pub fn new(meter: &Meter) -> Self {
+        Self {
+            calls: meter
+                .u64_counter("traces.span.metrics.calls")
+                .with_description("The number of spans")
+                .with_unit(Unit::new("{call}"))
+                .init(),
+            duration: meter
+                .f64_histogram("traces.span.metrics.duration")
+                .with_description("The duration of spans")
+                .with_unit(Unit::new("s"))
+                .init(),
+            dimensions: Vec::new(),
+        }
+    }
+
+    pub fn dimensions(&self) -> &[Key] {
+        &self.dimensions
+    }
+
+    /// Add the value of attribute `key` of spans as an attribute of metrics, it falls back to
+    /// the resource of spans, and is omitted if neither has it.
+    pub fn add_dimension(mut self, key: impl Into<Key>) -> Self {
+        self.dimensions.push(key.into());
+        self
+    }
+
+    pub(crate) fn record(&self, span: &SpanData) {
+        let attributes = self.attributes(span);
+        let duration = span
+            .end_time
+            .duration_since(span.start_time)
+            .unwrap_or_default();
+        self.calls.add(1, &attributes);
+        self.duration.record(duration.as_secs_f64(), &attributes);
+    }
+
+    fn attributes(&self, span: &SpanData) -> Vec<KeyValue> {
+        let mut attributes = Vec::with_capacity(4 + self.dimensions.len());
+        if let Some(service_name) = span.resource.get(SERVICE_NAME) {
+            attributes.push(KeyValue::new(SERVICE_NAME, service_name));
+        }
+        attributes.push(KeyValue::new("span.name", span.name.clone()));
+        attributes.push(KeyValue::new("span.kind", span_kind(&span.span_kind)));
+        attributes.push(KeyValue::new("status.code", status_code(&span.status)));
+        for key in &self.dimensions {
+            let value = span
+                .attributes
+                .get(key)
+                .cloned()
+                .or_else(|| span.resource.get(key.clone()));
+            if let Some(value) = value {
+                attributes.push(KeyValue::new(key.clone(), value));
+            }
+        }
+        attributes
+    }
+}
+
+impl fmt::Debug for SpanMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpanMetrics")
+            .field("dimensions", &self.dimensions)
+            .finish()
+    }
+}
+
+fn span_kind(span_kind: &SpanKind) -> &'static str {
+    match span_kind {
+        SpanKind::Client => "SPAN_KIND_CLIENT",
+        SpanKind::Server => "SPAN_KIND_SERVER",
+        SpanKind::Producer => "SPAN_KIND_PRODUCER",
+        SpanKind::Consumer => "SPAN_KIND_CONSUMER",
+        SpanKind::Internal => "SPAN_KIND_INTERNAL",
+    }
+}
+
+fn status_code(status: &Status) -> &'static str {
+    match status {
+        Status::Unset => "STATUS_CODE_UNSET",
+        Status::Ok => "STATUS_CODE_OK",
+        Status::Error { .. } => "STATUS_CODE_ERROR",
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::borrow::Cow;
+
+    use opentelemetry_api::{
+        metrics::{noop::NoopMeterProvider, MeterProvider},
+        trace::{SpanKind, Status},
+        KeyValue,
+    };
+    use opentelemetry_sdk::Resource;
+
+    use super::SpanMetrics;
+    use crate::testing::test_span;
+
+    #[test]
+    fn test_span_metrics_attributes() {
+        let metrics = SpanMetrics::new(&NoopMeterProvider::new().meter("test"))
+            .add_dimension("http.method")
+            .add_dimension("deployment.environment")
+            .add_dimension("missing");
+        let mut span = test_span("GET /users");
+        span.span_kind = SpanKind::Server;
+        span.status = Status::error("failed");
+        span.attributes.insert(KeyValue::new("http.method", "GET"));
+        span.resource = Cow::Owned(Resource::new([
+            KeyValue::new("service.name", "users"),
+            KeyValue::new("deployment.environment", "prod"),
+        ]));
+
+        assert_eq!(
+            metrics.attributes(&span),
+            [
+                KeyValue::new("service.name", "users"),
+                KeyValue::new("span.name", "GET /users"),
+                KeyValue::new("span.kind", "SPAN_KIND_SERVER"),
+                KeyValue::new("status.code", "STATUS_CODE_ERROR"),
+                KeyValue::new("http.method", "GET"),
+                KeyValue::new("deployment.environment", "prod"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Suppress telemetry generated by exporters themselves.
+//!
+//! Exports run inside a [`Context`] carrying [`SuppressTelemetry`], so instrumented clients(e.g.
+//! reqwest, hyper or tonic middlewares) called by exporters can skip recording, otherwise spans of
+//! export requests are exported again and create more spans endlessly.
+//!
+//! Integrations check the key with [`is_telemetry_suppressed`], or
+//! `cx.get::<otlp_exporter::SuppressTelemetry>().is_some()` for a given context. Tasks spawned by
+//! clients(e.g. connection tasks of hyper) don't inherit the context.
+
+use opentelemetry_api::Context;
+
+/// The context key marking telemetry generated while exporting, it has no data.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SuppressTelemetry;
+
+/// Whether the current context is inside an export.
+pub fn is_telemetry_suppressed() -> bool {
+    Context::map_current(|cx| cx.get::<SuppressTelemetry>().is_some())
+}
+
+/// The current context with [`SuppressTelemetry`].
+#[cfg(feature = "traces")]
+pub(crate) fn suppressed_context() -> Context {
+    Context::current_with_value(SuppressTelemetry)
+}
+
+#[cfg(feature = "traces")]
+pub use self::sampler::SuppressionSampler;
+
+#[cfg(feature = "traces")]
+mod sampler {
+    use opentelemetry_api::{
+        trace::{Link, SamplingDecision, SamplingResult, SpanKind, TraceId},
+        Context, Key, OrderMap, Value,
+    };
+    use opentelemetry_sdk::trace::ShouldSample;
+
+    use super::SuppressTelemetry;
+
+    /// A sampler drops spans started inside exports, and delegates others to the inner sampler.
+    ///
+    /// Tracer providers built by [`crate::TracePipeline`] wrap their samplers with it, wrap your
+    /// own sampler if you build the provider yourself.
+    #[derive(Clone, Debug)]
+    pub struct SuppressionSampler {
+        inner: Box<dyn ShouldSample>,
+    }
+
+    impl SuppressionSampler {
+        pub fn new(inner: Box<dyn ShouldSample>) -> Self {
+            Self { inner }
+        }
+    }
+
+    impl ShouldSample for SuppressionSampler {
+        fn should_sample(
+            &self,
+            parent_context: Option<&Context>,
+            trace_id: TraceId,
+            name: &str,
+            span_kind: &SpanKind,
+            attributes: &OrderMap<Key, Value>,
+            links: &[Link],
+        ) -> SamplingResult {
+            if parent_context.is_some_and(|cx| cx.get::<SuppressTelemetry>().is_some()) {
+                return SamplingResult {
+                    decision: SamplingDecision::Drop,
+                    attributes: Vec::new(),
+                    trace_state: Default::default(),
+                };
+            }
+            self.inner
+                .should_sample(parent_context, trace_id, name, span_kind, attributes, links)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "traces"))]
+mod tests {
+    use opentelemetry_api::{
+        trace::{SamplingDecision, SpanKind, TraceId},
+        Context,
+    };
+    use opentelemetry_sdk::trace::{Sampler, ShouldSample};
+
+    use super::{is_telemetry_suppressed, suppressed_context, SuppressionSampler};
+
+    #[test]
+    fn test_suppression_sampler() {
+        let sampler = SuppressionSampler::new(Box::new(Sampler::AlwaysOn));
+        let should_sample = |cx: &Context| {
+            sampler
+                .should_sample(
+                    Some(cx),
+                    TraceId::from_bytes([1; 16]),
+                    "span",
+                    &SpanKind::Client,
+                    &Default::default(),
+                    &[],
+                )
+                .decision
+        };
+
+        assert!(!is_telemetry_suppressed());
+        assert_eq!(
+            should_sample(&Context::current()),
+            SamplingDecision::RecordAndSample
+        );
+        let _guard = suppressed_context().attach();
+        assert!(is_telemetry_suppressed());
+        assert_eq!(should_sample(&Context::current()), SamplingDecision::Drop);
+    }
+}
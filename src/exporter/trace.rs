@@ -1,4 +1,8 @@
-use futures::{future::BoxFuture, FutureExt};
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
+use opentelemetry_api::trace::FutureExt as _;
 use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
 
 #[cfg(feature = "_grpc")]
@@ -6,10 +10,41 @@ use crate::config::GrpcImpl;
 
 use crate::{
     config::{Config, Protocol},
-    error::OtlpExporterError,
+    error::{OtlpExporterError, OtlpExporterResult},
+    exporter::suppression::suppressed_context,
 };
 
+mod circuit_breaker;
+mod failover;
+mod fan_out;
+mod load_balancing;
+mod rate_limit;
+mod redaction;
+#[cfg(feature = "metrics")]
+mod span_metrics;
+mod tail_sampling;
+mod timer;
+mod tracker;
+mod truncation;
+
+pub use self::circuit_breaker::{CircuitBreakerTraceExporter, CircuitState};
+pub use self::failover::FailoverTraceExporter;
+pub use self::fan_out::{FanOutPolicy, FanOutTraceExporter};
+pub use self::load_balancing::{
+    LoadBalancing, LoadBalancingTraceExporter, RoutingKey, DEFAULT_RESOLVE_INTERVAL,
+};
+pub use self::rate_limit::RateLimitedTraceExporter;
+pub use self::redaction::RedactingTraceExporter;
+#[cfg(feature = "metrics")]
+pub use self::span_metrics::SpanMetricsTraceExporter;
+pub use self::tail_sampling::TailSamplingTraceExporter;
+pub use self::truncation::TruncatingTraceExporter;
+
+/// An OTLP span exporter, build it from a [`Config`] with `TraceExporter::try_from` to use it in
+/// your own span processors. Variants are transports and the stages wrapping them, more may be
+/// added in minor releases.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum TraceExporter {
     #[cfg(feature = "tonic")]
     Tonic(TonicTraceExporter),
@@ -17,12 +52,74 @@ pub enum TraceExporter {
     Grpcio(GrpcioTraceExporter),
     #[cfg(feature = "http")]
     Http(HttpTraceExporter),
+    FanOut(FanOutTraceExporter),
+    Failover(FailoverTraceExporter),
+    LoadBalancing(LoadBalancingTraceExporter),
+    RateLimited(RateLimitedTraceExporter),
+    CircuitBreaker(CircuitBreakerTraceExporter),
+    Redacting(RedactingTraceExporter),
+    Truncating(TruncatingTraceExporter),
+    TailSampling(TailSamplingTraceExporter),
+    #[cfg(feature = "metrics")]
+    SpanMetrics(SpanMetricsTraceExporter),
+    #[cfg(feature = "testing")]
+    InMemory(crate::testing::InMemoryTraceExporter),
 }
 
 impl TryFrom<Config> for TraceExporter {
     type Error = OtlpExporterError;
 
     fn try_from(config: Config) -> Result<Self, Self::Error> {
+        Self::try_with_stages(config, Self::try_transport)
+    }
+}
+
+impl TraceExporter {
+    /// Build the stages enabled in `config` around the exporter which `transport` builds from the
+    /// config without them. From the outermost, they are redaction, truncation, span metrics,
+    /// tail sampling and the rate limit.
+    pub(crate) fn try_with_stages(
+        config: Config,
+        transport: impl FnOnce(Config) -> OtlpExporterResult<TraceExporter>,
+    ) -> OtlpExporterResult<TraceExporter> {
+        // redaction is outermost, so span metrics never see unredacted values, and truncation is
+        // before the rate limit, so bytes are estimated after values are truncated
+        let mut exporter = transport(
+            config
+                .clone()
+                .without_redaction()
+                .without_export_limits()
+                .without_span_metrics()
+                .without_tail_sampling()
+                .without_rate_limit(),
+        )?;
+        if config.rate_limit().is_some() {
+            exporter = RateLimitedTraceExporter::new(exporter, config.clone()).into();
+        }
+        if config.tail_sampling().is_some() {
+            exporter = TailSamplingTraceExporter::new(exporter, config.clone()).into();
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(span_metrics) = config.span_metrics() {
+            exporter = SpanMetricsTraceExporter::new(exporter, span_metrics.clone()).into();
+        }
+        if let Some(limits) = config.export_limits() {
+            exporter = TruncatingTraceExporter::new(exporter, limits.clone()).into();
+        }
+        if config.has_redaction() {
+            exporter = RedactingTraceExporter::new(exporter, config.redaction_hooks()?).into();
+        }
+        Ok(exporter)
+    }
+
+    /// Build failover, the circuit breaker and the transport of `config`.
+    fn try_transport(config: Config) -> OtlpExporterResult<TraceExporter> {
+        if !config.failover_endpoints().is_empty() {
+            return Ok(FailoverTraceExporter::try_new(config)?.into());
+        }
+        if config.circuit_breaker().is_some() {
+            return Ok(CircuitBreakerTraceExporter::try_new(config)?.into());
+        }
         let exporter = match config.protocol() {
             #[cfg(feature = "_grpc")]
             Protocol::Grpc => match config.grpc_impl() {
@@ -45,18 +142,24 @@ mod tonic {
     use opentelemetry_proto::tonic::collector::trace::v1::{
         trace_service_client::TraceServiceClient, ExportTraceServiceRequest,
     };
+    use std::sync::Arc;
+
     use opentelemetry_sdk::export::trace::SpanData;
     use tonic::{metadata::MetadataMap, transport::Channel, Request};
 
     use crate::{config::Config, error::OtlpExporterResult};
 
-    use super::TraceExporter;
+    use super::{
+        tracker::{shut_down_error, ExportTracker, InFlight},
+        TraceExporter,
+    };
 
     #[derive(Debug)]
     pub struct TonicTraceExporter {
         config: Config,
         metadata_map: MetadataMap,
-        client: TraceServiceClient<Channel>,
+        client: Option<TraceServiceClient<Channel>>,
+        tracker: Arc<ExportTracker>,
     }
 
     impl TonicTraceExporter {
@@ -64,13 +167,35 @@ mod tonic {
             // TODO client.accept_compression().send_compression()
             Ok(Self {
                 metadata_map: crate::exporter::tonic::gen_metadata_map(config.headers())?,
-                client: TraceServiceClient::new(Channel::try_from(&config)?),
+                client: Some(TraceServiceClient::new(Channel::try_from(&config)?)),
                 config,
+                tracker: Default::default(),
             })
         }
 
-        pub fn client(&self) -> &TraceServiceClient<Channel> {
-            &self.client
+        /// It is `None` after shutdown.
+        pub fn client(&self) -> Option<&TraceServiceClient<Channel>> {
+            self.client.as_ref()
+        }
+
+        /// Start an export with a clone of the client, it fails after shutdown.
+        pub(super) fn start_export(
+            &self,
+        ) -> OtlpExporterResult<(InFlight, TraceServiceClient<Channel>)> {
+            let in_flight = self.tracker.start()?;
+            let client = self.client.clone().ok_or_else(shut_down_error)?;
+            Ok((in_flight, client))
+        }
+
+        /// Wait for in-flight exports until the timeout outside a tokio runtime, then close the
+        /// channel.
+        pub(super) fn shutdown(&mut self) {
+            self.tracker.shutdown(self.config.timeout());
+            self.client = None;
+        }
+
+        pub(super) fn is_shut_down(&self) -> bool {
+            self.tracker.is_shut_down()
         }
 
         pub fn config(&self) -> &Config {
@@ -97,7 +222,7 @@ pub use self::tonic::TonicTraceExporter;
 
 #[cfg(feature = "grpcio")]
 mod grpcio {
-    use std::fmt;
+    use std::{fmt, sync::Arc};
 
     use grpcio::{Channel, Metadata};
     use opentelemetry_proto::grpcio::{
@@ -107,25 +232,49 @@ mod grpcio {
 
     use crate::{config::Config, error::OtlpExporterResult};
 
-    use super::TraceExporter;
+    use super::{
+        tracker::{shut_down_error, ExportTracker, InFlight},
+        TraceExporter,
+    };
 
     pub struct GrpcioTraceExporter {
         config: Config,
-        client: TraceServiceClient,
+        client: Option<TraceServiceClient>,
         metadata: Metadata,
+        tracker: Arc<ExportTracker>,
     }
 
     impl GrpcioTraceExporter {
         pub(super) fn try_new(config: Config) -> OtlpExporterResult<Self> {
             Ok(Self {
-                client: TraceServiceClient::new(Channel::try_from(&config)?),
+                client: Some(TraceServiceClient::new(Channel::try_from(&config)?)),
                 metadata: crate::exporter::grpcio::gen_metadata(config.headers())?,
                 config,
+                tracker: Default::default(),
             })
         }
 
-        pub fn client(&self) -> &TraceServiceClient {
-            &self.client
+        /// It is `None` after shutdown.
+        pub fn client(&self) -> Option<&TraceServiceClient> {
+            self.client.as_ref()
+        }
+
+        /// Start an export with a clone of the client, it fails after shutdown.
+        pub(super) fn start_export(&self) -> OtlpExporterResult<(InFlight, TraceServiceClient)> {
+            let in_flight = self.tracker.start()?;
+            let client = self.client.clone().ok_or_else(shut_down_error)?;
+            Ok((in_flight, client))
+        }
+
+        /// Wait for in-flight exports until the timeout outside a tokio runtime, then drop the
+        /// channel, the environment is dropped with its last channel.
+        pub(super) fn shutdown(&mut self) {
+            self.tracker.shutdown(self.config.timeout());
+            self.client = None;
+        }
+
+        pub(super) fn is_shut_down(&self) -> bool {
+            self.tracker.is_shut_down()
         }
 
         pub fn config(&self) -> &Config {
@@ -166,6 +315,8 @@ pub use self::grpcio::GrpcioTraceExporter;
 
 #[cfg(feature = "http")]
 mod http {
+    use std::sync::Arc;
+
     use http::header::CONTENT_TYPE;
     use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
     use opentelemetry_sdk::export::trace::SpanData;
@@ -174,43 +325,71 @@ mod http {
 
     use crate::{config::Config, error::OtlpExporterResult};
 
-    use super::TraceExporter;
+    use super::{
+        tracker::{shut_down_error, ExportTracker, InFlight},
+        TraceExporter,
+    };
 
     #[derive(Debug)]
     pub struct HttpTraceExporter {
         config: Config,
         encoder: crate::exporter::http::Encoder,
-        client: Client,
+        client: Option<Client>,
+        tracker: Arc<ExportTracker>,
     }
 
     impl HttpTraceExporter {
         pub(super) fn try_new_in_protobuf(config: Config) -> OtlpExporterResult<Self> {
             Ok(Self {
-                client: Client::try_from(&config)?,
+                client: Some(Client::try_from(&config)?),
                 encoder: crate::exporter::http::Encoder::Protobuf,
                 config,
+                tracker: Default::default(),
             })
         }
 
         #[cfg(feature = "http-json")]
         pub(super) fn try_new_in_json(config: Config) -> OtlpExporterResult<Self> {
             let _ = Self {
-                client: Client::try_from(&config)?,
+                client: Some(Client::try_from(&config)?),
                 encoder: crate::exporter::http::Encoder::Json,
                 config,
+                tracker: Default::default(),
             };
             unimplemented!("it needs time to find out how to serialize to json, refer: https://opentelemetry.io/docs/specs/otlp/#json-protobuf-encoding")
         }
 
+        pub fn config(&self) -> &Config {
+            &self.config
+        }
+
+        /// Start an export, it fails after shutdown.
+        pub(super) fn start_export(&self) -> OtlpExporterResult<InFlight> {
+            self.tracker.start()
+        }
+
+        /// Wait for in-flight exports until the timeout outside a tokio runtime, then drop the
+        /// connection pool.
+        pub(super) fn shutdown(&mut self) {
+            self.tracker.shutdown(self.config.timeout());
+            self.client = None;
+        }
+
+        pub(super) fn is_shut_down(&self) -> bool {
+            self.tracker.is_shut_down()
+        }
+
+        /// It fails after shutdown.
         pub fn gen_request_builder(
             &self,
             batch: Vec<SpanData>,
         ) -> OtlpExporterResult<RequestBuilder> {
+            let client = self.client.as_ref().ok_or_else(shut_down_error)?;
             let payload = ExportTraceServiceRequest {
                 resource_spans: batch.into_iter().map(Into::into).collect(),
             };
 
-            let mut request_builder = self.client.post(self.config.endpoint().to_string());
+            let mut request_builder = client.post(self.config.endpoint().to_string());
 
             match self.encoder {
                 crate::exporter::http::Encoder::Protobuf => {
@@ -246,16 +425,112 @@ mod http {
 #[cfg(feature = "http")]
 pub use self::http::HttpTraceExporter;
 
+impl From<FanOutTraceExporter> for TraceExporter {
+    fn from(exporter: FanOutTraceExporter) -> Self {
+        TraceExporter::FanOut(exporter)
+    }
+}
+
+impl From<FailoverTraceExporter> for TraceExporter {
+    fn from(exporter: FailoverTraceExporter) -> Self {
+        TraceExporter::Failover(exporter)
+    }
+}
+
+impl From<LoadBalancingTraceExporter> for TraceExporter {
+    fn from(exporter: LoadBalancingTraceExporter) -> Self {
+        TraceExporter::LoadBalancing(exporter)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl From<crate::testing::InMemoryTraceExporter> for TraceExporter {
+    fn from(exporter: crate::testing::InMemoryTraceExporter) -> Self {
+        TraceExporter::InMemory(exporter)
+    }
+}
+
 impl SpanExporter for TraceExporter {
+    /// The export runs inside a context with [`crate::SuppressTelemetry`], so that requests of the
+    /// export are not traced by instrumented clients.
     fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let cx = suppressed_context();
+        let _guard = cx.clone().attach();
+        self.export_batch(batch).with_context(cx).boxed()
+    }
+
+    /// Wait for in-flight exports until the timeout of the config, then close transports.
+    /// Inside a tokio runtime it doesn't wait, in-flight exports keep running on the runtime.
+    /// Further exports fail. Composite exporters shut down all of their exporters.
+    fn shutdown(&mut self) {
+        match self {
+            #[cfg(feature = "tonic")]
+            TraceExporter::Tonic(exporter) => exporter.shutdown(),
+            #[cfg(feature = "grpcio")]
+            TraceExporter::Grpcio(exporter) => exporter.shutdown(),
+            #[cfg(feature = "http")]
+            TraceExporter::Http(exporter) => exporter.shutdown(),
+            TraceExporter::FanOut(exporter) => exporter.shutdown(),
+            TraceExporter::Failover(exporter) => exporter.shutdown(),
+            TraceExporter::LoadBalancing(exporter) => exporter.shutdown(),
+            TraceExporter::RateLimited(exporter) => exporter.shutdown(),
+            TraceExporter::CircuitBreaker(exporter) => exporter.shutdown(),
+            TraceExporter::Redacting(exporter) => exporter.shutdown(),
+            TraceExporter::Truncating(exporter) => exporter.shutdown(),
+            TraceExporter::TailSampling(exporter) => exporter.shutdown(),
+            #[cfg(feature = "metrics")]
+            TraceExporter::SpanMetrics(exporter) => exporter.shutdown(),
+            #[cfg(feature = "testing")]
+            TraceExporter::InMemory(_) => {}
+        }
+    }
+
+    /// Only tail sampling buffers spans, its buffered traces are decided and kept ones are
+    /// exported. There is nothing to flush for other exporters. It fails after shutdown.
+    fn force_flush(&mut self) -> BoxFuture<'static, ExportResult> {
+        if self.is_shut_down() {
+            return future::ready(Err(tracker::shut_down_error().into())).boxed();
+        }
+        match self {
+            TraceExporter::TailSampling(exporter) => exporter.force_flush(),
+            #[cfg(feature = "metrics")]
+            TraceExporter::SpanMetrics(exporter) => exporter.force_flush(),
+            _ => future::ready(Ok(())).boxed(),
+        }
+    }
+}
+
+impl TraceExporter {
+    /// Export `batch` by the variant, [`SpanExporter::export`] wraps it in a suppression context.
+    fn export_batch(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
         match self {
             #[cfg(feature = "tonic")]
             TraceExporter::Tonic(exporter) => {
+                use prost::Message;
+
+                let (in_flight, mut client) = match exporter.start_export() {
+                    Ok(started) => started,
+                    Err(e) => return future::ready(Err(e.into())).boxed(),
+                };
+                let spans = batch.len();
                 let request = exporter.gen_request(batch);
-                let mut client = exporter.client().clone();
+                let recorder = exporter.config().metrics().map(|metrics| {
+                    let size = request.get_ref().encoded_len();
+                    metrics.start("otlp_grpc_span_exporter", exporter.config(), spans, size)
+                });
                 let timeout = exporter.config().timeout();
                 async move {
-                    if let Err(status) = client.export(request).await {
+                    let _in_flight = in_flight;
+                    let result = client.export(request).await;
+                    if let Some(recorder) = recorder {
+                        recorder.finish(
+                            result
+                                .as_ref()
+                                .err()
+                                .map(|status| format!("{:?}", status.code())),
+                        );
+                    }
+                    if let Err(status) = result {
                         return crate::exporter::tonic::gen_trace_error(status, timeout);
                     }
                     Ok(())
@@ -264,32 +539,205 @@ impl SpanExporter for TraceExporter {
             }
             #[cfg(feature = "grpcio")]
             TraceExporter::Grpcio(exporter) => {
+                use protobuf::Message;
+
+                let (in_flight, client) = match exporter.start_export() {
+                    Ok(started) => started,
+                    Err(e) => return future::ready(Err(e.into())).boxed(),
+                };
+                let spans = batch.len();
                 let request = exporter.gen_request(batch);
-                let client = exporter.client().clone();
+                let recorder = exporter.config().metrics().map(|metrics| {
+                    let size = request.compute_size() as usize;
+                    metrics.start("otlp_grpc_span_exporter", exporter.config(), spans, size)
+                });
                 let call_option = ::grpcio::CallOption::default()
                     .timeout(exporter.config().timeout())
                     .headers(exporter.metadata().clone());
                 async move {
-                    let response = client
-                        .export_async_opt(&request, call_option)
-                        .map_err(OtlpExporterError::from)?;
-                    response.await.map_err(OtlpExporterError::from)?;
+                    let _in_flight = in_flight;
+                    let result = match client.export_async_opt(&request, call_option) {
+                        Ok(response) => response.await.map(drop),
+                        Err(e) => Err(e),
+                    };
+                    if let Some(recorder) = recorder {
+                        recorder.finish(result.as_ref().err().map(|e| match e {
+                            ::grpcio::Error::RpcFailure(status) => {
+                                format!("{:?}", status.code())
+                            }
+                            _ => "_OTHER".to_owned(),
+                        }));
+                    }
+                    result.map_err(OtlpExporterError::from)?;
                     Ok(())
                 }
                 .boxed()
             }
             #[cfg(feature = "http")]
             TraceExporter::Http(exporter) => {
-                let request_builder = exporter.gen_request_builder(batch);
+                let in_flight = match exporter.start_export() {
+                    Ok(in_flight) => in_flight,
+                    Err(e) => return future::ready(Err(e.into())).boxed(),
+                };
+                let spans = batch.len();
+                let (client, request) = match exporter.gen_request_builder(batch) {
+                    Ok(request_builder) => request_builder.build_split(),
+                    Err(e) => return future::ready(Err(e.into())).boxed(),
+                };
+                let recorder = match (&request, exporter.config().metrics()) {
+                    (Ok(request), Some(metrics)) => {
+                        let size = request
+                            .body()
+                            .and_then(reqwest::Body::as_bytes)
+                            .map_or(0, <[u8]>::len);
+                        Some(metrics.start(
+                            "otlp_http_span_exporter",
+                            exporter.config(),
+                            spans,
+                            size,
+                        ))
+                    }
+                    _ => None,
+                };
                 async move {
-                    request_builder?
-                        .send()
+                    let _in_flight = in_flight;
+                    let request = request.map_err(OtlpExporterError::from)?;
+                    let result = client
+                        .execute(request)
                         .await
-                        .map_err(OtlpExporterError::from)?;
+                        .and_then(reqwest::Response::error_for_status);
+                    if let Some(recorder) = recorder {
+                        recorder.finish(result.as_ref().err().map(|e| match e.status() {
+                            Some(status) => status.as_u16().to_string(),
+                            None if e.is_timeout() => "timeout".to_owned(),
+                            None => "_OTHER".to_owned(),
+                        }));
+                    }
+                    result.map_err(OtlpExporterError::from)?;
                     Ok(())
                 }
                 .boxed()
             }
+            TraceExporter::FanOut(exporter) => exporter.export(batch),
+            TraceExporter::Failover(exporter) => exporter.export(batch),
+            TraceExporter::LoadBalancing(exporter) => exporter.export(batch),
+            TraceExporter::RateLimited(exporter) => exporter.export(batch),
+            TraceExporter::CircuitBreaker(exporter) => exporter.export(batch),
+            TraceExporter::Redacting(exporter) => exporter.export(batch),
+            TraceExporter::Truncating(exporter) => exporter.export(batch),
+            TraceExporter::TailSampling(exporter) => exporter.export(batch),
+            #[cfg(feature = "metrics")]
+            TraceExporter::SpanMetrics(exporter) => exporter.export(batch),
+            #[cfg(feature = "testing")]
+            TraceExporter::InMemory(exporter) => {
+                exporter.record(crate::testing::InMemoryTraceExporter::gen_request(batch));
+                future::ready(Ok(())).boxed()
+            }
+        }
+    }
+
+    pub(crate) fn is_shut_down(&self) -> bool {
+        match self {
+            #[cfg(feature = "tonic")]
+            TraceExporter::Tonic(exporter) => exporter.is_shut_down(),
+            #[cfg(feature = "grpcio")]
+            TraceExporter::Grpcio(exporter) => exporter.is_shut_down(),
+            #[cfg(feature = "http")]
+            TraceExporter::Http(exporter) => exporter.is_shut_down(),
+            TraceExporter::FanOut(exporter) => exporter.destinations().all(Self::is_shut_down),
+            TraceExporter::Failover(exporter) => exporter.endpoints().all(Self::is_shut_down),
+            TraceExporter::LoadBalancing(exporter) => exporter.backends().all(Self::is_shut_down),
+            TraceExporter::RateLimited(exporter) => exporter.inner().is_shut_down(),
+            TraceExporter::CircuitBreaker(exporter) => exporter.inner().is_shut_down(),
+            TraceExporter::Redacting(exporter) => exporter.inner().is_shut_down(),
+            TraceExporter::Truncating(exporter) => exporter.inner().is_shut_down(),
+            TraceExporter::TailSampling(exporter) => exporter.inner().is_shut_down(),
+            #[cfg(feature = "metrics")]
+            TraceExporter::SpanMetrics(exporter) => exporter.inner().is_shut_down(),
+            #[cfg(feature = "testing")]
+            TraceExporter::InMemory(_) => false,
         }
     }
 }
+
+#[cfg(all(test, feature = "mock-collector", feature = "http"))]
+mod tests {
+    use std::time::Duration;
+
+    use opentelemetry_sdk::export::trace::SpanExporter;
+
+    use super::TraceExporter;
+    use crate::{
+        config::Protocol,
+        testing::{
+            collector::{MockBehavior, MockCollector},
+            test_span,
+        },
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shutdown() {
+        let collector = MockCollector::builder()
+            .with_behavior(MockBehavior::default().with_delay(Duration::from_millis(200)))
+            .start()
+            .await
+            .unwrap();
+        let mut exporter = TraceExporter::try_from(
+            collector
+                .config_builder(Protocol::HttpProtobuf)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let in_flight = tokio::spawn(exporter.export(vec![test_span("in-flight")]));
+        // outside the runtime, the in-flight export is drained before shutdown returns
+        let mut exporter = tokio::task::block_in_place(|| {
+            std::thread::spawn(move || {
+                exporter.shutdown();
+                exporter
+            })
+            .join()
+            .unwrap()
+        });
+        assert_eq!(collector.spans().len(), 1);
+        assert!(in_flight.await.unwrap().is_ok());
+
+        let err = exporter
+            .export(vec![test_span("rejected")])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("shut down"), "{err}");
+        assert!(exporter.force_flush().await.is_err());
+        // it is safe to shut down twice
+        exporter.shutdown();
+        assert_eq!(collector.spans().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_in_current_thread_runtime() {
+        let collector = MockCollector::builder()
+            .with_behavior(MockBehavior::default().with_delay(Duration::from_millis(200)))
+            .start()
+            .await
+            .unwrap();
+        let mut exporter = TraceExporter::try_from(
+            collector
+                .config_builder(Protocol::HttpProtobuf)
+                .with_timeout(Duration::from_secs(5))
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+
+        let in_flight = tokio::spawn(exporter.export(vec![test_span("in-flight")]));
+        // it doesn't block the only thread which drives the export and the collector
+        let start = std::time::Instant::now();
+        exporter.shutdown();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(exporter.export(vec![test_span("rejected")]).await.is_err());
+
+        assert!(in_flight.await.unwrap().is_ok());
+        assert_eq!(collector.spans().len(), 1);
+    }
+}
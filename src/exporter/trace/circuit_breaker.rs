@@ -0,0 +1,300 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
+use opentelemetry_api::{global, trace::TraceError};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::{
+    config::Config,
+    error::{OtlpExporterError, OtlpExporterResult},
+};
+
+use super::TraceExporter;
+
+/// State of a [`CircuitBreakerTraceExporter`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CircuitState {
+    /// Batches are exported.
+    Closed,
+    /// Batches fail fast without being sent.
+    Open,
+    /// A batch is sent to probe the endpoint, others fail fast until it finishes. If the probe is
+    /// dropped before it finishes, e.g. by the export timeout of the batch span processor, the
+    /// circuit opens again.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: usize,
+    opened_at: Option<Instant>,
+}
+
+/// An exporter which fails fast after consecutive failures of the inner exporter, instead of
+/// waiting out the timeout for a dead endpoint.
+///
+/// After `open_duration`, a batch is sent to probe the endpoint. The circuit closes if the probe
+/// succeeds, and opens again otherwise. State changes are reported through the global error
+/// handler.
+#[derive(Debug)]
+pub struct CircuitBreakerTraceExporter {
+    inner: Box<TraceExporter>,
+    endpoint: String,
+    failure_threshold: usize,
+    open_duration: Duration,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CircuitBreakerTraceExporter {
+    /// `config` must have a circuit breaker, the inner exporter is built without it.
+    pub(crate) fn try_new(config: Config) -> OtlpExporterResult<Self> {
+        let circuit_breaker = config.circuit_breaker().cloned().unwrap_or_default();
+        let endpoint = config.endpoint().to_string();
+        Ok(Self {
+            inner: Box::new(config.without_circuit_breaker().try_into()?),
+            endpoint,
+            failure_threshold: circuit_breaker.failure_threshold(),
+            open_duration: circuit_breaker.open_duration(),
+            state: Arc::new(Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            })),
+        })
+    }
+
+    pub fn inner(&self) -> &TraceExporter {
+        &self.inner
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).state
+    }
+
+    pub(super) fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let mut probe = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            match state.state {
+                CircuitState::Closed => false,
+                CircuitState::Open
+                    if state
+                        .opened_at
+                        .is_none_or(|t| t.elapsed() >= self.open_duration) =>
+                {
+                    state.state = CircuitState::HalfOpen;
+                    true
+                }
+                CircuitState::Open | CircuitState::HalfOpen => {
+                    return future::ready(Err(OtlpExporterError::CircuitBreakerError(format!(
+                        "circuit of endpoint[{}] is open, the batch is not sent",
+                        self.endpoint
+                    ))
+                    .into()))
+                    .boxed();
+                }
+            }
+        }
+        .then(|| ProbeGuard {
+            state: self.state.clone(),
+            endpoint: self.endpoint.clone(),
+            finished: false,
+        });
+
+        let export = self.inner.export(batch);
+        let endpoint = self.endpoint.clone();
+        let failure_threshold = self.failure_threshold;
+        let state = self.state.clone();
+        async move {
+            let result = export.await;
+            if let Some(probe) = &mut probe {
+                probe.finished = true;
+            }
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            match (probe.is_some(), result.is_ok()) {
+                (true, true) => {
+                    report(format!(
+                        "endpoint[{endpoint}] is recovered, circuit is closed"
+                    ));
+                    state.state = CircuitState::Closed;
+                    state.consecutive_failures = 0;
+                }
+                (true, false) => {
+                    report(format!(
+                        "probe of endpoint[{endpoint}] failed, circuit is open"
+                    ));
+                    state.state = CircuitState::Open;
+                    state.opened_at = Some(Instant::now());
+                }
+                // results of batches sent before the circuit opened don't change it
+                (false, _) if state.state != CircuitState::Closed => {}
+                (false, true) => state.consecutive_failures = 0,
+                (false, false) => {
+                    state.consecutive_failures += 1;
+                    if state.consecutive_failures >= failure_threshold {
+                        report(format!(
+                            "endpoint[{endpoint}] failed {} times in a row, circuit is open",
+                            state.consecutive_failures
+                        ));
+                        state.state = CircuitState::Open;
+                        state.opened_at = Some(Instant::now());
+                        state.consecutive_failures = 0;
+                    }
+                }
+            }
+            result
+        }
+        .boxed()
+    }
+}
+
+impl From<CircuitBreakerTraceExporter> for TraceExporter {
+    fn from(exporter: CircuitBreakerTraceExporter) -> Self {
+        TraceExporter::CircuitBreaker(exporter)
+    }
+}
+
+/// Open the circuit again if the probe is dropped before it finishes, otherwise the circuit would
+/// stay half-open and fail every batch fast.
+struct ProbeGuard {
+    state: Arc<Mutex<BreakerState>>,
+    endpoint: String,
+    finished: bool,
+}
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        report(format!(
+            "probe of endpoint[{}] was cancelled, circuit is open",
+            self.endpoint
+        ));
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.state = CircuitState::Open;
+        state.opened_at = Some(Instant::now());
+    }
+}
+
+fn report(message: String) {
+    global::handle_error(TraceError::from(OtlpExporterError::CircuitBreakerError(
+        message,
+    )));
+}
+
+#[cfg(all(test, feature = "mock-collector", feature = "http"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CircuitBreakerTraceExporter, CircuitState};
+    use crate::{
+        config::{CircuitBreaker, Protocol},
+        exporter::trace::TraceExporter,
+        testing::{
+            collector::{MockBehavior, MockCollector},
+            test_span,
+        },
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_circuit_breaker() {
+        let collector = MockCollector::start().await.unwrap();
+        collector.set_behavior(
+            MockBehavior::default().with_http_status(http::StatusCode::SERVICE_UNAVAILABLE),
+        );
+        let open_duration = Duration::from_millis(100);
+        let config = collector
+            .config_builder(Protocol::HttpProtobuf)
+            .with_circuit_breaker(
+                CircuitBreaker::default()
+                    .with_failure_threshold(2)
+                    .with_open_duration(open_duration),
+            )
+            .build()
+            .unwrap();
+        assert!(matches!(
+            TraceExporter::try_from(config.clone()).unwrap(),
+            TraceExporter::CircuitBreaker(_)
+        ));
+        let mut exporter = CircuitBreakerTraceExporter::try_new(config).unwrap();
+
+        assert!(exporter.export(vec![test_span("1")]).await.is_err());
+        assert_eq!(exporter.state(), CircuitState::Closed);
+        assert!(exporter.export(vec![test_span("2")]).await.is_err());
+        assert_eq!(exporter.state(), CircuitState::Open);
+
+        // fail fast without sending
+        let err = exporter.export(vec![test_span("3")]).await.unwrap_err();
+        assert!(err.to_string().contains("open"), "{err}");
+        assert_eq!(collector.requests().len(), 2);
+
+        // the probe fails
+        tokio::time::sleep(open_duration).await;
+        assert!(exporter.export(vec![test_span("4")]).await.is_err());
+        assert_eq!(collector.requests().len(), 3);
+        assert_eq!(exporter.state(), CircuitState::Open);
+
+        collector.set_behavior(MockBehavior::default());
+        assert!(exporter.export(vec![test_span("5")]).await.is_err());
+        tokio::time::sleep(open_duration).await;
+        assert!(exporter.export(vec![test_span("6")]).await.is_ok());
+        assert_eq!(exporter.state(), CircuitState::Closed);
+        assert!(exporter.export(vec![test_span("7")]).await.is_ok());
+        // 3 and 5 are not sent
+        assert_eq!(collector.requests().len(), 5);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_circuit_breaker_dropped_probe() {
+        let collector = MockCollector::start().await.unwrap();
+        collector.set_behavior(
+            MockBehavior::default().with_http_status(http::StatusCode::SERVICE_UNAVAILABLE),
+        );
+        let open_duration = Duration::from_millis(100);
+        let config = collector
+            .config_builder(Protocol::HttpProtobuf)
+            .with_circuit_breaker(
+                CircuitBreaker::default()
+                    .with_failure_threshold(1)
+                    .with_open_duration(open_duration),
+            )
+            .build()
+            .unwrap();
+        let mut exporter = CircuitBreakerTraceExporter::try_new(config).unwrap();
+        assert!(exporter.export(vec![test_span("1")]).await.is_err());
+        assert_eq!(exporter.state(), CircuitState::Open);
+
+        // the probe is cancelled by a timeout shorter than the delay of the collector
+        collector.set_behavior(MockBehavior::default().with_delay(Duration::from_secs(5)));
+        tokio::time::sleep(open_duration).await;
+        let probe = exporter.export(vec![test_span("2")]);
+        assert_eq!(exporter.state(), CircuitState::HalfOpen);
+        assert!(tokio::time::timeout(Duration::from_millis(50), probe)
+            .await
+            .is_err());
+        assert_eq!(exporter.state(), CircuitState::Open);
+
+        // a probe which is never polled
+        tokio::time::sleep(open_duration).await;
+        drop(exporter.export(vec![test_span("3")]));
+        assert_eq!(exporter.state(), CircuitState::Open);
+
+        // the next probe is sent after `open_duration`
+        collector.set_behavior(MockBehavior::default());
+        assert!(exporter.export(vec![test_span("4")]).await.is_err());
+        tokio::time::sleep(open_duration).await;
+        assert!(exporter.export(vec![test_span("5")]).await.is_ok());
+        assert_eq!(exporter.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use opentelemetry_api::{global, trace::TraceError};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::{
+    config::Config,
+    error::{OtlpExporterError, OtlpExporterResult},
+};
+
+use super::{timer, TraceExporter};
+
+#[derive(Debug, Default)]
+struct FailoverState {
+    current: usize,
+    consecutive_failures: usize,
+    last_probe: Option<Instant>,
+    probing: bool,
+}
+
+/// An exporter which switches to the next endpoint after consecutive failures.
+///
+/// After failing over, an empty request is sent to the primary endpoint in the background every
+/// `failback_probe_interval`, batches don't wait for it. It switches back to the primary endpoint
+/// once the probe succeeds.
+/// Failovers and failbacks are reported through the global error handler.
+#[derive(Debug)]
+pub struct FailoverTraceExporter {
+    endpoints: Vec<(String, TraceExporter)>,
+    failover_threshold: usize,
+    failback_probe_interval: Duration,
+    state: Arc<Mutex<FailoverState>>,
+}
+
+impl FailoverTraceExporter {
+    pub(crate) fn try_new(config: Config) -> OtlpExporterResult<Self> {
+        let endpoints = config
+            .split_failover_endpoints()
+            .into_iter()
+            .map(|config| Ok((config.endpoint().to_string(), config.try_into()?)))
+            .collect::<OtlpExporterResult<_>>()?;
+        Ok(Self {
+            endpoints,
+            failover_threshold: config.failover_threshold(),
+            failback_probe_interval: config.failback_probe_interval(),
+            state: Default::default(),
+        })
+    }
+
+    pub fn endpoints(&self) -> impl Iterator<Item = &TraceExporter> {
+        self.endpoints.iter().map(|(_, exporter)| exporter)
+    }
+
+    /// The endpoint batches are exported to.
+    pub fn current_endpoint(&self) -> &str {
+        let current = self.state.lock().unwrap_or_else(|e| e.into_inner()).current;
+        &self.endpoints[current].0
+    }
+
+    pub(super) fn shutdown(&mut self) {
+        for (_, exporter) in &mut self.endpoints {
+            exporter.shutdown();
+        }
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let (current, probe) = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let probe = state.current != 0
+                && !state.probing
+                && state
+                    .last_probe
+                    .map(|t| t.elapsed() >= self.failback_probe_interval)
+                    .unwrap_or(true);
+            if probe {
+                state.probing = true;
+                state.last_probe = Some(Instant::now());
+            }
+            (state.current, probe)
+        };
+
+        let endpoints: Vec<_> = self
+            .endpoints
+            .iter()
+            .map(|(endpoint, _)| endpoint.clone())
+            .collect();
+        if probe {
+            let probe = self.endpoints[0].1.export(Vec::new());
+            let state = self.state.clone();
+            let endpoints = endpoints.clone();
+            timer::spawn(async move {
+                let result = probe.await;
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                state.probing = false;
+                if result.is_ok() && state.current != 0 {
+                    report(format!(
+                        "primary endpoint[{}] is recovered, switch back from endpoint[{}]",
+                        endpoints[0], endpoints[state.current]
+                    ));
+                    state.current = 0;
+                    state.consecutive_failures = 0;
+                }
+            });
+        }
+
+        let export = self.endpoints[current].1.export(batch);
+        let failover_threshold = self.failover_threshold;
+        let state = self.state.clone();
+        async move {
+            let result = export.await;
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.current != current {
+                return result;
+            }
+            if result.is_ok() {
+                state.consecutive_failures = 0;
+                return result;
+            }
+            state.consecutive_failures += 1;
+            if state.consecutive_failures >= failover_threshold {
+                let next = (current + 1) % endpoints.len();
+                report(format!(
+                    "endpoint[{}] failed {} times in a row, switch to endpoint[{}]",
+                    endpoints[current], state.consecutive_failures, endpoints[next]
+                ));
+                state.current = next;
+                state.consecutive_failures = 0;
+                state.last_probe = Some(Instant::now());
+            }
+            result
+        }
+        .boxed()
+    }
+}
+
+fn report(message: String) {
+    global::handle_error(TraceError::from(OtlpExporterError::FailoverError(message)));
+}
+
+#[cfg(all(test, feature = "mock-collector", feature = "http"))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::FailoverTraceExporter;
+    use crate::{
+        config::{DataType, Protocol},
+        exporter::trace::TraceExporter,
+        testing::{
+            collector::{MockBehavior, MockCollector},
+            test_span,
+        },
+    };
+
+    /// Wait for the probe running in the background to finish.
+    async fn wait_for_probe(exporter: &FailoverTraceExporter) {
+        for _ in 0..300 {
+            if !exporter.state.lock().unwrap().probing {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("the probe is not finished in time");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failover_and_failback() {
+        let primary = MockCollector::start().await.unwrap();
+        let secondary = MockCollector::start().await.unwrap();
+        primary.set_behavior(
+            MockBehavior::default().with_http_status(http::StatusCode::SERVICE_UNAVAILABLE),
+        );
+
+        let config = primary
+            .config_builder(Protocol::HttpProtobuf)
+            .add_failover_signal_endpoint(secondary.http_traces_endpoint())
+            .with_failover_threshold(2)
+            .with_failback_probe_interval(Duration::ZERO)
+            .build()
+            .unwrap();
+        let mut exporter = FailoverTraceExporter::try_new(config).unwrap();
+        assert!(matches!(
+            TraceExporter::try_from(
+                primary
+                    .config_builder(Protocol::HttpProtobuf)
+                    .add_failover_signal_endpoint(secondary.http_traces_endpoint())
+                    .build()
+                    .unwrap()
+            )
+            .unwrap(),
+            TraceExporter::Failover(_)
+        ));
+
+        assert!(exporter.export(vec![test_span("1")]).await.is_err());
+        assert!(exporter.export(vec![test_span("2")]).await.is_err());
+        assert_eq!(
+            exporter.current_endpoint(),
+            secondary.http_traces_endpoint()
+        );
+
+        // the primary is still down, the probe fails
+        assert!(exporter.export(vec![test_span("3")]).await.is_ok());
+        wait_for_probe(&exporter).await;
+        assert_eq!(secondary.spans().len(), 1);
+        assert_eq!(primary.requests().len(), 3);
+        assert_eq!(
+            exporter.current_endpoint(),
+            secondary.http_traces_endpoint()
+        );
+
+        primary.set_behavior(MockBehavior::default());
+        assert!(exporter.export(vec![test_span("4")]).await.is_ok());
+        wait_for_probe(&exporter).await;
+        assert_eq!(secondary.spans().len(), 2);
+        assert_eq!(exporter.current_endpoint(), primary.http_traces_endpoint());
+
+        assert!(exporter.export(vec![test_span("5")]).await.is_ok());
+        assert_eq!(primary.spans().len(), 3);
+        assert_eq!(primary.spans()[2].name, "5");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_probe_in_background() {
+        let primary = MockCollector::start().await.unwrap();
+        let secondary = MockCollector::start().await.unwrap();
+        primary.set_behavior(
+            MockBehavior::default().with_http_status(http::StatusCode::SERVICE_UNAVAILABLE),
+        );
+        let config = primary
+            .config_builder(Protocol::HttpProtobuf)
+            .add_failover_signal_endpoint(secondary.http_traces_endpoint())
+            .with_failover_threshold(1)
+            .with_failback_probe_interval(Duration::ZERO)
+            .build()
+            .unwrap();
+        let mut exporter = FailoverTraceExporter::try_new(config).unwrap();
+        assert!(exporter.export(vec![test_span("1")]).await.is_err());
+
+        // the batch doesn't wait for the slow probe of the primary endpoint
+        primary.set_behavior(MockBehavior::default().with_delay(Duration::from_secs(1)));
+        let start = Instant::now();
+        assert!(exporter.export(vec![test_span("2")]).await.is_ok());
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(
+            exporter.current_endpoint(),
+            secondary.http_traces_endpoint()
+        );
+
+        wait_for_probe(&exporter).await;
+        assert_eq!(exporter.current_endpoint(), primary.http_traces_endpoint());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failover_generic_endpoint() {
+        let primary = MockCollector::start().await.unwrap();
+        let secondary = MockCollector::start().await.unwrap();
+        primary.set_behavior(
+            MockBehavior::default().with_http_status(http::StatusCode::SERVICE_UNAVAILABLE),
+        );
+
+        // the signal path is appended to the failover endpoint like the primary one
+        let config = primary
+            .config_builder(Protocol::HttpProtobuf)
+            .with_data_type(DataType::Trace)
+            .add_failover_endpoint(secondary.http_endpoint())
+            .with_failover_threshold(1)
+            .build()
+            .unwrap();
+        let mut exporter = FailoverTraceExporter::try_new(config).unwrap();
+
+        assert!(exporter.export(vec![test_span("1")]).await.is_err());
+        assert_eq!(
+            exporter.current_endpoint(),
+            secondary.http_traces_endpoint()
+        );
+        assert!(exporter.export(vec![test_span("2")]).await.is_ok());
+        assert_eq!(secondary.spans().len(), 1);
+        assert_eq!(secondary.spans()[0].name, "2");
+    }
+}
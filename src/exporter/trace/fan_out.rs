@@ -0,0 +1,268 @@
+use std::{fmt::Write, future::Future};
+
+use futures::{
+    future::{self, BoxFuture},
+    stream::FuturesUnordered,
+    FutureExt, StreamExt,
+};
+use opentelemetry_api::{global, trace::TraceError};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+use super::TraceExporter;
+
+/// Decide whether a batch is exported successfully when it is sent to multiple destinations.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FanOutPolicy {
+    /// All destinations must accept the batch.
+    #[default]
+    All,
+    /// At least one destination must accept the batch, the export finishes as soon as one
+    /// accepts it.
+    Any,
+    /// The primary destination(the first one) must accept the batch, the export finishes as soon
+    /// as the primary one finishes.
+    Primary,
+}
+
+impl FanOutPolicy {
+    /// Whether the batch is accepted, or `None` if it depends on unfinished destinations.
+    fn accepted(&self, results: &[Option<ExportResult>]) -> Option<bool> {
+        let finished = || results.iter().all(Option::is_some);
+        match self {
+            FanOutPolicy::All => {
+                finished().then(|| results.iter().all(|r| matches!(r, Some(Ok(_)))))
+            }
+            FanOutPolicy::Any => {
+                if results.iter().any(|r| matches!(r, Some(Ok(_)))) {
+                    Some(true)
+                } else {
+                    finished().then_some(false)
+                }
+            }
+            FanOutPolicy::Primary => results.first()?.as_ref().map(Result::is_ok),
+        }
+    }
+}
+
+/// An exporter sends each batch to all destinations concurrently.
+///
+/// Destinations still exporting when the policy decides keep running on the tokio runtime, and
+/// their failures are reported through the global error handler.
+#[derive(Debug)]
+pub struct FanOutTraceExporter {
+    destinations: Vec<(String, TraceExporter)>,
+    policy: FanOutPolicy,
+}
+
+impl FanOutTraceExporter {
+    /// Each destination is named by its endpoint, the first one is the primary destination.
+    pub(crate) fn try_new(
+        destinations: Vec<(String, TraceExporter)>,
+        policy: FanOutPolicy,
+    ) -> OtlpExporterResult<Self> {
+        if destinations.is_empty() {
+            return Err(OtlpExporterError::ConfigError(
+                "there should be at least one destination".to_owned(),
+            ));
+        }
+        Ok(Self {
+            destinations,
+            policy,
+        })
+    }
+
+    pub fn destinations(&self) -> impl Iterator<Item = &TraceExporter> {
+        self.destinations.iter().map(|(_, exporter)| exporter)
+    }
+
+    pub(super) fn shutdown(&mut self) {
+        for (_, exporter) in &mut self.destinations {
+            exporter.shutdown();
+        }
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let endpoints: Vec<_> = self
+            .destinations
+            .iter()
+            .map(|(endpoint, _)| endpoint.clone())
+            .collect();
+        let exports: Vec<_> = self
+            .destinations
+            .iter_mut()
+            .map(|(_, exporter)| exporter.export(batch.clone()))
+            .collect();
+        let policy = self.policy;
+        async move {
+            let mut results: Vec<Option<ExportResult>> = exports.iter().map(|_| None).collect();
+            let mut pending: FuturesUnordered<_> = exports
+                .into_iter()
+                .enumerate()
+                .map(|(i, export)| export.map(move |result| (i, result)))
+                .collect();
+            let accepted = loop {
+                let Some((i, result)) = pending.next().await else {
+                    unreachable!("the policy decides when all destinations finish");
+                };
+                results[i] = Some(result);
+                if let Some(accepted) = policy.accepted(&results) {
+                    break accepted;
+                }
+            };
+            if !pending.is_empty() {
+                report_pending(pending, endpoints.clone()).await;
+            }
+
+            if accepted {
+                // the batch is accepted, report failed destinations only.
+                for (endpoint, result) in endpoints.iter().zip(results) {
+                    if let Some(Err(e)) = result {
+                        report_failure(endpoint, e);
+                    }
+                }
+                return Ok(());
+            }
+
+            let mut summary = String::new();
+            for (i, (endpoint, result)) in endpoints.iter().zip(results).enumerate() {
+                if i > 0 {
+                    summary.push_str(", ");
+                }
+                let _ = match result {
+                    Some(Ok(_)) => write!(summary, "destination[{endpoint}] succeeded"),
+                    Some(Err(e)) => write!(summary, "destination[{endpoint}] failed: {e}"),
+                    None => write!(summary, "destination[{endpoint}] is still exporting"),
+                };
+            }
+            Err(OtlpExporterError::FanOutError(format!(
+                "batch is not accepted with policy {policy:?}: {summary}"
+            ))
+            .into())
+        }
+        .boxed()
+    }
+}
+
+/// Report failures of destinations which are still exporting after the policy decided. Inside a
+/// tokio runtime they keep running in a task, otherwise they are waited for.
+async fn report_pending(
+    pending: FuturesUnordered<impl Future<Output = (usize, ExportResult)> + Send + 'static>,
+    endpoints: Vec<String>,
+) {
+    let report = pending.for_each(move |(i, result)| {
+        if let Err(e) = result {
+            report_failure(&endpoints[i], e);
+        }
+        future::ready(())
+    });
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => drop(handle.spawn(report)),
+        Err(_) => report.await,
+    }
+}
+
+fn report_failure(endpoint: &str, e: TraceError) {
+    global::handle_error(TraceError::from(OtlpExporterError::FanOutError(format!(
+        "destination[{endpoint}] failed: {e}"
+    ))));
+}
+
+#[cfg(all(test, feature = "mock-collector", feature = "http"))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use opentelemetry_sdk::export::trace::SpanExporter;
+
+    use super::{FanOutPolicy, FanOutTraceExporter};
+    use crate::{
+        config::Protocol,
+        exporter::trace::TraceExporter,
+        testing::{
+            collector::{MockBehavior, MockCollector},
+            test_span,
+        },
+    };
+
+    fn new_exporter(
+        policy: FanOutPolicy,
+        primary: &MockCollector,
+        secondary: &MockCollector,
+    ) -> TraceExporter {
+        TraceExporter::from(
+            FanOutTraceExporter::try_new(
+                [primary, secondary]
+                    .into_iter()
+                    .map(|collector| {
+                        let config = collector
+                            .config_builder(Protocol::HttpProtobuf)
+                            .build()
+                            .unwrap();
+                        (config.endpoint().to_string(), config.try_into().unwrap())
+                    })
+                    .collect(),
+                policy,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fan_out_policy() {
+        let primary = MockCollector::start().await.unwrap();
+        let secondary = MockCollector::start().await.unwrap();
+        secondary.set_behavior(
+            MockBehavior::default().with_http_status(http::StatusCode::SERVICE_UNAVAILABLE),
+        );
+
+        let mut exporter = new_exporter(FanOutPolicy::All, &primary, &secondary);
+        let err = exporter.export(vec![test_span("all")]).await.unwrap_err();
+        assert!(err.to_string().contains("succeeded"));
+        assert_eq!(primary.spans().len(), 1);
+        assert_eq!(secondary.requests().len(), 1);
+
+        let mut exporter = new_exporter(FanOutPolicy::Any, &primary, &secondary);
+        assert!(exporter.export(vec![test_span("any")]).await.is_ok());
+
+        let mut exporter = new_exporter(FanOutPolicy::Primary, &primary, &secondary);
+        assert!(exporter.export(vec![test_span("primary")]).await.is_ok());
+
+        let mut exporter = new_exporter(FanOutPolicy::Primary, &secondary, &primary);
+        assert!(exporter.export(vec![test_span("primary")]).await.is_err());
+
+        // exports to other destinations may still be running
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(primary.spans().len(), 4);
+        assert_eq!(secondary.requests().len(), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fan_out_slow_destination() {
+        let fast = MockCollector::start().await.unwrap();
+        let slow = MockCollector::start().await.unwrap();
+        let delay = Duration::from_secs(1);
+        slow.set_behavior(MockBehavior::default().with_delay(delay));
+
+        // the export finishes without waiting for the slow destination
+        for (policy, primary, secondary) in [
+            (FanOutPolicy::Primary, &fast, &slow),
+            (FanOutPolicy::Any, &slow, &fast),
+        ] {
+            let mut exporter = new_exporter(policy, primary, secondary);
+            let start = Instant::now();
+            assert!(exporter.export(vec![test_span("fast")]).await.is_ok());
+            assert!(start.elapsed() < delay, "{policy:?}");
+        }
+        assert_eq!(fast.spans().len(), 2);
+
+        let mut exporter = new_exporter(FanOutPolicy::All, &fast, &slow);
+        let start = Instant::now();
+        assert!(exporter.export(vec![test_span("all")]).await.is_ok());
+        assert!(start.elapsed() >= delay);
+
+        // the slow destination still receives the batches
+        tokio::time::sleep(delay).await;
+        assert_eq!(slow.spans().len(), 3);
+    }
+}
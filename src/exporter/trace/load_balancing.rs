@@ -0,0 +1,631 @@
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{Arc, Condvar, Mutex, OnceLock, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+use futures::{
+    future::{self, join_all, BoxFuture},
+    FutureExt,
+};
+use opentelemetry_api::{global, trace::TraceError, Key};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::{
+    config::{Config, ConfigBuilder},
+    error::{OtlpExporterError, OtlpExporterResult},
+};
+
+use super::TraceExporter;
+
+/// Default interval of resolving the dns name of the collector pool.
+pub const DEFAULT_RESOLVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of points on the hash ring for each backend.
+const VIRTUAL_NODES: usize = 128;
+
+/// What spans are routed by.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RoutingKey {
+    /// Spans of the same trace are sent to the same backend.
+    #[default]
+    TraceId,
+    /// Spans of the same service are sent to the same backend.
+    ServiceName,
+}
+
+#[derive(Clone, Debug)]
+enum Discovery {
+    Static(Vec<String>),
+    Dns { hostname: String, port: u16 },
+}
+
+/// Settings of load balancing spans across a pool of collectors.
+///
+/// The config of the pipeline is used as a template for each backend, only the endpoint is
+/// replaced.
+#[derive(Clone, Debug)]
+pub struct LoadBalancing {
+    discovery: Discovery,
+    resolve_interval: Duration,
+    routing_key: RoutingKey,
+}
+
+impl LoadBalancing {
+    /// Balance across a static list of endpoints, they are used as is.
+    pub fn with_endpoints<I, E>(endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<String>,
+    {
+        Self {
+            discovery: Discovery::Static(endpoints.into_iter().map(Into::into).collect()),
+            resolve_interval: DEFAULT_RESOLVE_INTERVAL,
+            routing_key: Default::default(),
+        }
+    }
+
+    /// Balance across all addresses of `hostname`, it is resolved once when the exporter is
+    /// built, then every `resolve_interval` by a background thread shared by all exporters.
+    /// Batches fail only if the first resolution fails, until the hostname is resolved. The
+    /// scheme and path of endpoints are the same as the ones of the template config, `hostname`
+    /// is still used for TLS and the authority of requests.
+    pub fn with_dns(hostname: impl Into<String>, port: u16) -> Self {
+        Self {
+            discovery: Discovery::Dns {
+                hostname: hostname.into(),
+                port,
+            },
+            resolve_interval: DEFAULT_RESOLVE_INTERVAL,
+            routing_key: Default::default(),
+        }
+    }
+
+    pub fn with_resolve_interval(mut self, resolve_interval: Duration) -> Self {
+        self.resolve_interval = resolve_interval;
+        self
+    }
+
+    pub fn with_routing_key(mut self, routing_key: RoutingKey) -> Self {
+        self.routing_key = routing_key;
+        self
+    }
+}
+
+/// A hostname resolved by the resolver thread.
+#[derive(Debug)]
+struct DnsTarget {
+    hostname: String,
+    port: u16,
+    interval: Duration,
+    /// Addresses resolved since the last export, taken on the next export.
+    resolved: Mutex<Option<Vec<SocketAddr>>>,
+}
+
+/// Targets of all exporters with the time they are resolved next. Targets of dropped exporters
+/// are removed.
+#[derive(Default)]
+struct Resolver {
+    targets: Mutex<Vec<(Instant, Weak<DnsTarget>)>>,
+    changed: Condvar,
+}
+
+impl Resolver {
+    /// The resolver thread is started on the first call.
+    fn get() -> OtlpExporterResult<&'static Resolver> {
+        static RESOLVER: OnceLock<Resolver> = OnceLock::new();
+        static STARTED: OnceLock<Result<(), String>> = OnceLock::new();
+        let resolver = RESOLVER.get_or_init(Default::default);
+        STARTED
+            .get_or_init(|| {
+                thread::Builder::new()
+                    .name("otlp-exporter-resolver".to_owned())
+                    .spawn(|| resolver.run())
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            })
+            .clone()
+            .map_err(|e| {
+                OtlpExporterError::LoadBalancingError(format!(
+                    "failed to start the resolver thread: {e}"
+                ))
+            })?;
+        Ok(resolver)
+    }
+
+    /// Resolve `target` at `due` and every interval after it until it is dropped.
+    fn register(&self, target: &Arc<DnsTarget>, due: Instant) {
+        lock(&self.targets).push((due, Arc::downgrade(target)));
+        self.changed.notify_one();
+    }
+
+    fn run(&self) {
+        loop {
+            let target = {
+                let mut targets = lock(&self.targets);
+                loop {
+                    targets.retain(|(_, target)| target.strong_count() > 0);
+                    let now = Instant::now();
+                    let next = targets.iter_mut().min_by_key(|(due, _)| *due);
+                    match next {
+                        Some((due, target)) if *due <= now => {
+                            let Some(target) = target.upgrade() else {
+                                continue;
+                            };
+                            *due = now + target.interval;
+                            break target;
+                        }
+                        Some((due, _)) => {
+                            let timeout = *due - now;
+                            targets = self
+                                .changed
+                                .wait_timeout(targets, timeout)
+                                .unwrap_or_else(|e| e.into_inner())
+                                .0;
+                        }
+                        None => {
+                            targets = self
+                                .changed
+                                .wait(targets)
+                                .unwrap_or_else(|e| e.into_inner());
+                        }
+                    }
+                }
+            };
+            // resolve without the lock, exporters can register meanwhile
+            target.resolve();
+        }
+    }
+}
+
+impl DnsTarget {
+    /// Whether the hostname is resolved, failures are reported through the global error handler.
+    fn resolve(&self) -> bool {
+        match (self.hostname.as_str(), self.port).to_socket_addrs() {
+            Ok(addresses) => {
+                let addresses: Vec<_> = addresses.collect();
+                if addresses.is_empty() {
+                    global::handle_error(TraceError::from(OtlpExporterError::LoadBalancingError(
+                        format!("{} is resolved to nothing", self.hostname),
+                    )));
+                    return false;
+                }
+                *lock(&self.resolved) = Some(addresses);
+                true
+            }
+            Err(e) => {
+                global::handle_error(TraceError::from(OtlpExporterError::LoadBalancingError(
+                    format!("failed to resolve {}: {e}", self.hostname),
+                )));
+                false
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A backend of the pool, named by its endpoint, or by the resolved address in place of the
+/// hostname.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct Member {
+    name: String,
+    address: Option<SocketAddr>,
+}
+
+/// A consistent hash ring, a change of members only moves keys of the changed members.
+#[derive(Debug, Default)]
+struct Ring {
+    points: Vec<(u64, usize)>,
+}
+
+impl Ring {
+    fn new(members: &[String]) -> Self {
+        let mut points: Vec<_> = members
+            .iter()
+            .enumerate()
+            .flat_map(|(i, member)| {
+                (0..VIRTUAL_NODES).map(move |v| (hash(format!("{member}#{v}").as_bytes()), i))
+            })
+            .collect();
+        points.sort_unstable();
+        Self { points }
+    }
+
+    fn get(&self, key: &[u8]) -> Option<usize> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let h = hash(key);
+        let idx = self.points.partition_point(|(point, _)| *point < h);
+        Some(self.points[idx % self.points.len()].1)
+    }
+}
+
+/// FNV-1a with a splitmix64 finalizer, it is stable across processes.
+fn hash(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58476d1ce4e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+/// An exporter which splits each batch across backends by consistent hashing.
+#[derive(Debug)]
+pub struct LoadBalancingTraceExporter {
+    template: ConfigBuilder,
+    routing_key: RoutingKey,
+    backends: Vec<(String, TraceExporter)>,
+    ring: Ring,
+    /// The hostname resolved by the resolver thread, with the scheme and path of endpoints.
+    dns: Option<(Arc<DnsTarget>, String, String)>,
+}
+
+impl LoadBalancingTraceExporter {
+    /// With DNS, the hostname is resolved before it returns, the resolver thread retries at once
+    /// if it fails.
+    pub(crate) fn try_new(
+        template: Config,
+        load_balancing: LoadBalancing,
+    ) -> OtlpExporterResult<Self> {
+        let LoadBalancing {
+            discovery,
+            resolve_interval,
+            routing_key,
+        } = load_balancing;
+        let scheme = template
+            .endpoint()
+            .scheme_str()
+            .unwrap_or("http")
+            .to_owned();
+        let path = template.endpoint().path().to_owned();
+        let mut exporter = Self {
+            template: template.into(),
+            routing_key,
+            backends: Vec::new(),
+            ring: Default::default(),
+            dns: None,
+        };
+        match discovery {
+            Discovery::Static(endpoints) => exporter.update_members(
+                endpoints
+                    .into_iter()
+                    .map(|name| Member {
+                        name,
+                        address: None,
+                    })
+                    .collect(),
+            )?,
+            Discovery::Dns { hostname, port } => {
+                let target = Arc::new(DnsTarget {
+                    hostname,
+                    port,
+                    interval: resolve_interval,
+                    resolved: Default::default(),
+                });
+                let due = if target.resolve() {
+                    Instant::now() + resolve_interval
+                } else {
+                    Instant::now()
+                };
+                Resolver::get()?.register(&target, due);
+                exporter.dns = Some((target, scheme, path));
+                if let Some(members) = exporter.take_resolved() {
+                    exporter.update_members(members)?;
+                }
+            }
+        }
+        Ok(exporter)
+    }
+
+    pub fn backends(&self) -> impl Iterator<Item = &TraceExporter> {
+        self.backends.iter().map(|(_, exporter)| exporter)
+    }
+
+    pub fn endpoints(&self) -> impl Iterator<Item = &str> {
+        self.backends.iter().map(|(endpoint, _)| endpoint.as_str())
+    }
+
+    /// Replace backends with `members`, exporters of unchanged members are kept.
+    fn update_members(&mut self, mut members: Vec<Member>) -> OtlpExporterResult<()> {
+        members.sort_unstable();
+        members.dedup();
+        if members.iter().map(|m| m.name.as_str()).eq(self.endpoints()) {
+            return Ok(());
+        }
+        if members.is_empty() {
+            return Err(OtlpExporterError::ConfigError(
+                "there should be at least one backend".to_owned(),
+            ));
+        }
+
+        let mut created = HashMap::new();
+        for member in &members {
+            if !self.backends.iter().any(|(e, _)| *e == member.name) {
+                let config = self.member_config(member).build()?;
+                created.insert(member.name.clone(), TraceExporter::try_from(config)?);
+            }
+        }
+        let mut existing: HashMap<_, _> = self.backends.drain(..).collect();
+        self.backends = members
+            .into_iter()
+            .filter_map(|Member { name, .. }| {
+                let exporter = existing.remove(&name).or_else(|| created.remove(&name))?;
+                Some((name, exporter))
+            })
+            .collect();
+        // close removed backends, their in-flight exports are tracked like on shutdown
+        for exporter in existing.values_mut() {
+            exporter.shutdown();
+        }
+        let members: Vec<_> = self.endpoints().map(ToOwned::to_owned).collect();
+        self.ring = Ring::new(&members);
+        Ok(())
+    }
+
+    /// A resolved member keeps the hostname in its endpoint and connects to its address.
+    fn member_config(&self, member: &Member) -> ConfigBuilder {
+        match (&self.dns, member.address) {
+            (Some((target, scheme, path)), Some(address)) => self
+                .template
+                .clone()
+                .with_signal_endpoint(format!(
+                    "{scheme}://{}:{}{path}",
+                    target.hostname, target.port
+                ))
+                .with_connect_address(address),
+            _ => self.template.clone().with_signal_endpoint(&member.name),
+        }
+    }
+
+    /// Members of addresses resolved since the last export.
+    fn take_resolved(&self) -> Option<Vec<Member>> {
+        let (target, scheme, path) = self.dns.as_ref()?;
+        let addresses = lock(&target.resolved).take()?;
+        Some(
+            addresses
+                .into_iter()
+                .map(|address| Member {
+                    name: format!("{scheme}://{address}{path}"),
+                    address: Some(address),
+                })
+                .collect(),
+        )
+    }
+
+    fn routing_key(&self, span: &SpanData) -> Vec<u8> {
+        match self.routing_key {
+            RoutingKey::TraceId => span.span_context.trace_id().to_bytes().to_vec(),
+            RoutingKey::ServiceName => span
+                .resource
+                .get(Key::from_static_str("service.name"))
+                .map(|v| v.as_str().into_owned())
+                .unwrap_or_default()
+                .into_bytes(),
+        }
+    }
+
+    /// Stop resolving members, and shut down all backends.
+    pub(super) fn shutdown(&mut self) {
+        // the resolver thread forgets the target once it is dropped
+        self.dns = None;
+        for (_, exporter) in &mut self.backends {
+            exporter.shutdown();
+        }
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        if let Some(members) = self.take_resolved() {
+            if let Err(e) = self.update_members(members) {
+                global::handle_error(TraceError::from(e));
+            }
+        }
+
+        let mut batches: Vec<Vec<SpanData>> = self.backends.iter().map(|_| Vec::new()).collect();
+        for span in batch {
+            match self.ring.get(&self.routing_key(&span)) {
+                Some(idx) => batches[idx].push(span),
+                None => {
+                    let message = match &self.dns {
+                        Some((target, ..)) => format!("{} is not resolved yet", target.hostname),
+                        None => "there is no backend".to_owned(),
+                    };
+                    return future::ready(Err(
+                        OtlpExporterError::LoadBalancingError(message).into()
+                    ))
+                    .boxed();
+                }
+            }
+        }
+
+        let exports: Vec<_> = self
+            .backends
+            .iter_mut()
+            .zip(batches)
+            .filter(|(_, batch)| !batch.is_empty())
+            .map(|((endpoint, exporter), batch)| {
+                let endpoint = endpoint.clone();
+                exporter.export(batch).map(|result| (endpoint, result))
+            })
+            .collect();
+        async move {
+            let failures: Vec<_> = join_all(exports)
+                .await
+                .into_iter()
+                .filter_map(|(endpoint, result)| {
+                    result
+                        .err()
+                        .map(|e| format!("backend[{endpoint}] failed: {e}"))
+                })
+                .collect();
+            if failures.is_empty() {
+                Ok(())
+            } else {
+                Err(OtlpExporterError::LoadBalancingError(failures.join(", ")).into())
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ring;
+
+    #[test]
+    fn test_ring_minimal_disruption() {
+        let members: Vec<_> = (0..3).map(|i| format!("http://backend-{i}")).collect();
+        let ring = Ring::new(&members);
+        let shrunk = Ring::new(&members[..2]);
+
+        let mut counts = [0; 3];
+        for key in 0u32..3000 {
+            let key = key.to_be_bytes();
+            let idx = ring.get(&key).unwrap();
+            counts[idx] += 1;
+            // keys of remaining backends are not moved
+            if idx < 2 {
+                assert_eq!(shrunk.get(&key), Some(idx));
+            }
+        }
+        assert!(counts.iter().all(|count| *count > 500), "{counts:?}");
+        assert_eq!(Ring::default().get(b"key"), None);
+    }
+
+    #[cfg(all(feature = "mock-collector", feature = "http"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_split_batch_by_trace_id() {
+        use opentelemetry_api::trace::{SpanContext, SpanId, TraceFlags, TraceId, TraceState};
+        use opentelemetry_sdk::export::trace::SpanExporter;
+
+        use super::{LoadBalancing, LoadBalancingTraceExporter};
+        use crate::{
+            config::Protocol,
+            exporter::trace::TraceExporter,
+            testing::{collector::MockCollector, test_span},
+        };
+
+        let collectors = [
+            MockCollector::start().await.unwrap(),
+            MockCollector::start().await.unwrap(),
+        ];
+        let mut exporter = TraceExporter::from(
+            LoadBalancingTraceExporter::try_new(
+                collectors[0]
+                    .config_builder(Protocol::HttpProtobuf)
+                    .build()
+                    .unwrap(),
+                LoadBalancing::with_endpoints(
+                    collectors.iter().map(MockCollector::http_traces_endpoint),
+                ),
+            )
+            .unwrap(),
+        );
+
+        let batch = || {
+            (0u128..50)
+                .map(|i| {
+                    let mut span = test_span("balanced");
+                    span.span_context = SpanContext::new(
+                        TraceId::from_bytes(i.to_be_bytes()),
+                        SpanId::from_bytes(1u64.to_be_bytes()),
+                        TraceFlags::SAMPLED,
+                        false,
+                        TraceState::default(),
+                    );
+                    span
+                })
+                .collect::<Vec<_>>()
+        };
+        exporter.export(batch()).await.unwrap();
+        exporter.export(batch()).await.unwrap();
+
+        let mut total = 0;
+        for collector in &collectors {
+            let spans = collector.spans();
+            assert!(!spans.is_empty());
+            assert_eq!(collector.requests().len(), 2);
+            // both exports of the same trace go to the same backend
+            let (first, second) = spans.split_at(spans.len() / 2);
+            assert_eq!(
+                first.iter().map(|s| &s.trace_id).collect::<Vec<_>>(),
+                second.iter().map(|s| &s.trace_id).collect::<Vec<_>>()
+            );
+            total += spans.len();
+        }
+        assert_eq!(total, 100);
+    }
+
+    #[cfg(all(feature = "mock-collector", feature = "http"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_resolve_dns() {
+        use opentelemetry_api::trace::{SpanContext, SpanId, TraceFlags, TraceId, TraceState};
+        use opentelemetry_sdk::export::trace::SpanExporter;
+
+        use super::{LoadBalancing, LoadBalancingTraceExporter};
+        use crate::{
+            config::Protocol,
+            exporter::trace::TraceExporter,
+            testing::{collector::MockCollector, test_span},
+        };
+
+        let collector = MockCollector::start().await.unwrap();
+        let port = collector
+            .http_endpoint()
+            .rsplit(':')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut exporter = TraceExporter::from(
+            LoadBalancingTraceExporter::try_new(
+                collector
+                    .config_builder(Protocol::HttpProtobuf)
+                    .build()
+                    .unwrap(),
+                LoadBalancing::with_dns("localhost", port),
+            )
+            .unwrap(),
+        );
+        // the hostname is resolved before the first batch, `localhost` may also be resolved to
+        // an ipv6 address the collector doesn't listen on
+        let TraceExporter::LoadBalancing(load_balancing) = &exporter else {
+            unreachable!();
+        };
+        assert!(load_balancing.endpoints().next().is_some());
+        for trace_id in 0u128..16 {
+            let mut span = test_span("resolved");
+            span.span_context = SpanContext::new(
+                TraceId::from_bytes(trace_id.to_be_bytes()),
+                SpanId::from_bytes(1u64.to_be_bytes()),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            );
+            let _ = exporter.export(vec![span]).await;
+        }
+        assert!(!collector.requests().is_empty());
+
+        // the hostname is kept in requests sent to the resolved address
+        assert_eq!(
+            collector.requests()[0].headers[http::header::HOST],
+            format!("localhost:{port}")
+        );
+        let TraceExporter::LoadBalancing(exporter) = &exporter else {
+            unreachable!();
+        };
+        assert!(exporter
+            .endpoints()
+            .all(|endpoint| !endpoint.contains("localhost")));
+    }
+}
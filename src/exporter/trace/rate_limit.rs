@@ -0,0 +1,418 @@
+use std::time::{Duration, Instant};
+
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
+use opentelemetry_api::{
+    global,
+    trace::{Status, TraceError, TraceId},
+    Array, Key, KeyValue, Value,
+};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::{
+    config::{Config, OverflowPolicy, RateLimit},
+    error::OtlpExporterError,
+};
+
+use super::{timer::sleep, TraceExporter};
+
+/// Estimated bytes of ids, timestamps, kind and flags of a span.
+const SPAN_OVERHEAD: u64 = 64;
+/// Estimated bytes of the timestamp and counters of an event.
+const EVENT_OVERHEAD: u64 = 16;
+/// Estimated bytes of ids and the trace state of a link.
+const LINK_OVERHEAD: u64 = 32;
+
+#[derive(Debug)]
+struct TokenBucket {
+    /// Tokens per second.
+    rate: f64,
+    capacity: f64,
+    /// It is negative if tokens are borrowed by delayed exports.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, burst: Duration) -> Self {
+        let rate = rate as f64;
+        let capacity = rate * burst.as_secs_f64();
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Tokens can be borrowed as long as they are paid off in `max_delay`.
+    fn available(&self, max_delay: Duration) -> f64 {
+        self.tokens + self.rate * max_delay.as_secs_f64()
+    }
+
+    /// How long to wait until borrowed tokens are paid off.
+    fn delay(&self) -> Duration {
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// An exporter limits spans and bytes exported per second with token buckets.
+///
+/// Spans exceeding the limit are handled by the [`OverflowPolicy`], dropped ones are reported
+/// through the global error handler and the metrics of the config. Delayed batches are in flight
+/// while waiting.
+#[derive(Debug)]
+pub struct RateLimitedTraceExporter {
+    inner: Box<TraceExporter>,
+    config: Config,
+    spans: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl RateLimitedTraceExporter {
+    /// Limit `inner` with the rate limit of `config`.
+    pub(crate) fn new(inner: TraceExporter, config: Config) -> Self {
+        let rate_limit = config.rate_limit().cloned().unwrap_or_default();
+        Self {
+            inner: Box::new(inner),
+            spans: rate_limit
+                .spans_per_second()
+                .map(|rate| TokenBucket::new(rate, rate_limit.burst())),
+            bytes: rate_limit
+                .bytes_per_second()
+                .map(|rate| TokenBucket::new(rate, rate_limit.burst())),
+            config,
+        }
+    }
+
+    pub fn inner(&self) -> &TraceExporter {
+        &self.inner
+    }
+
+    pub fn rate_limit(&self) -> Option<&RateLimit> {
+        self.config.rate_limit()
+    }
+
+    pub(super) fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let now = Instant::now();
+        for bucket in self.spans.iter_mut().chain(&mut self.bytes) {
+            bucket.refill(now);
+        }
+        let overflow = self
+            .rate_limit()
+            .map(|rate_limit| rate_limit.overflow().clone())
+            .unwrap_or_default();
+        let max_delay = match overflow {
+            OverflowPolicy::Delay { max_delay } => max_delay,
+            _ => Duration::ZERO,
+        };
+
+        let total = batch.len();
+        let mut spans: Vec<_> = batch
+            .into_iter()
+            .map(|span| {
+                let size = self.bytes.as_ref().map_or(0, |_| estimate_size(&span));
+                (span, size as f64)
+            })
+            .collect();
+        if overflow == OverflowPolicy::Sample {
+            let ratio = self.budget_ratio(&spans);
+            if ratio < 1.0 {
+                spans.retain(|(span, _)| sampled(span.span_context.trace_id(), ratio));
+            }
+        }
+
+        let mut admitted = Vec::with_capacity(spans.len());
+        for (span, size) in spans {
+            let fits = |bucket: &Option<TokenBucket>, cost| {
+                bucket
+                    .as_ref()
+                    .is_none_or(|bucket| bucket.available(max_delay) >= cost)
+            };
+            if !fits(&self.spans, 1.0) || !fits(&self.bytes, size) {
+                break;
+            }
+            if let Some(bucket) = &mut self.spans {
+                bucket.tokens -= 1.0;
+            }
+            if let Some(bucket) = &mut self.bytes {
+                bucket.tokens -= size;
+            }
+            admitted.push(span);
+        }
+
+        let dropped = total - admitted.len();
+        if dropped > 0 {
+            global::handle_error(TraceError::from(OtlpExporterError::RateLimitError(
+                format!("{dropped} of {total} spans are dropped"),
+            )));
+            if let Some(metrics) = self.config.metrics() {
+                metrics.record_rate_limited(&self.config, dropped);
+            }
+            if admitted.is_empty() {
+                return future::ready(Ok(())).boxed();
+            }
+        }
+
+        let delay = self
+            .spans
+            .iter()
+            .chain(&self.bytes)
+            .map(TokenBucket::delay)
+            .max()
+            .unwrap_or_default();
+        let export = self.inner.export(admitted);
+        if delay.is_zero() {
+            return export;
+        }
+        sleep(delay).then(|_| export).boxed()
+    }
+
+    /// The ratio of the budget to what `spans` need.
+    fn budget_ratio(&self, spans: &[(SpanData, f64)]) -> f64 {
+        let needed = [
+            (&self.spans, spans.len() as f64),
+            (&self.bytes, spans.iter().map(|(_, size)| size).sum()),
+        ];
+        needed
+            .into_iter()
+            .filter_map(|(bucket, needed)| {
+                let bucket = bucket.as_ref()?;
+                Some(bucket.tokens.max(0.0) / needed)
+            })
+            .fold(1.0, f64::min)
+    }
+}
+
+impl From<RateLimitedTraceExporter> for TraceExporter {
+    fn from(exporter: RateLimitedTraceExporter) -> Self {
+        TraceExporter::RateLimited(exporter)
+    }
+}
+
+/// Whether `trace_id` falls in `ratio`, like `Sampler::TraceIdRatioBased`.
+pub(super) fn sampled(trace_id: TraceId, ratio: f64) -> bool {
+    let upper_bound = (ratio.max(0.0) * (1u64 << 63) as f64) as u64;
+    let bytes = trace_id.to_bytes();
+    let mut low = [0; 8];
+    low.copy_from_slice(&bytes[8..]);
+    (u64::from_be_bytes(low) >> 1) < upper_bound
+}
+
+/// Estimate the encoded size of `span` from its strings and values.
+fn estimate_size(span: &SpanData) -> u64 {
+    let status = match &span.status {
+        Status::Error { description } => description.len() as u64,
+        _ => 0,
+    };
+    SPAN_OVERHEAD
+        + span.name.len() as u64
+        + status
+        + span
+            .attributes
+            .iter()
+            .map(|(key, value)| attribute_size(key, value))
+            .sum::<u64>()
+        + span
+            .events
+            .iter()
+            .map(|event| {
+                EVENT_OVERHEAD + event.name.len() as u64 + attributes_size(&event.attributes)
+            })
+            .sum::<u64>()
+        + span
+            .links
+            .iter()
+            .map(|link| LINK_OVERHEAD + attributes_size(&link.attributes))
+            .sum::<u64>()
+}
+
+fn attributes_size(attributes: &[KeyValue]) -> u64 {
+    attributes
+        .iter()
+        .map(|kv| attribute_size(&kv.key, &kv.value))
+        .sum()
+}
+
+fn attribute_size(key: &Key, value: &Value) -> u64 {
+    let value = match value {
+        Value::Bool(_) => 1,
+        Value::I64(_) | Value::F64(_) => 8,
+        Value::String(s) => s.as_str().len(),
+        Value::Array(Array::Bool(values)) => values.len(),
+        Value::Array(Array::I64(values)) => values.len() * 8,
+        Value::Array(Array::F64(values)) => values.len() * 8,
+        Value::Array(Array::String(values)) => values.iter().map(|s| s.as_str().len()).sum(),
+    };
+    (key.as_str().len() + value) as u64
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use opentelemetry_api::{
+        trace::{SpanContext, TraceId},
+        KeyValue,
+    };
+    use opentelemetry_sdk::export::trace::{SpanData, SpanExporter};
+
+    use super::RateLimitedTraceExporter;
+    use crate::{
+        config::{ConfigBuilder, ExportLimits, OverflowPolicy, RateLimit},
+        exporter::trace::{TraceExporter, TruncatingTraceExporter},
+        testing::{test_span, InMemoryTraceExporter},
+        ExporterMetrics,
+    };
+
+    fn new_exporter(
+        rate_limit: RateLimit,
+        metrics: &ExporterMetrics,
+    ) -> (RateLimitedTraceExporter, InMemoryTraceExporter) {
+        let in_memory = InMemoryTraceExporter::default();
+        let config = ConfigBuilder::default()
+            .with_rate_limit(rate_limit)
+            .with_metrics(metrics.clone())
+            .build()
+            .unwrap();
+        (
+            RateLimitedTraceExporter::new(in_memory.clone().into(), config),
+            in_memory,
+        )
+    }
+
+    /// Spans named by their index, each in its own trace.
+    fn batch(len: u64) -> Vec<SpanData> {
+        (0..len)
+            .map(|i| {
+                let mut span = test_span("span");
+                span.name = i.to_string().into();
+                let trace_id = u128::from(i.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+                span.span_context = SpanContext::new(
+                    TraceId::from_bytes(trace_id.to_be_bytes()),
+                    span.span_context.span_id(),
+                    span.span_context.trace_flags(),
+                    false,
+                    Default::default(),
+                );
+                span
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let metrics = ExporterMetrics::new();
+        let (mut exporter, in_memory) = new_exporter(
+            RateLimit::default()
+                .with_spans_per_second(2)
+                .with_burst(Duration::from_secs(1)),
+            &metrics,
+        );
+        exporter.export(batch(5)).await.unwrap();
+        let names: Vec<_> = in_memory.spans().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["0", "1"]);
+        assert_eq!(metrics.snapshot().rate_limited_spans, 3);
+
+        // the budget runs out, but empty batches pass
+        exporter.export(batch(1)).await.unwrap();
+        exporter.export(Vec::new()).await.unwrap();
+        assert_eq!(in_memory.spans().len(), 2);
+        assert_eq!(in_memory.requests().len(), 2);
+        assert_eq!(metrics.snapshot().rate_limited_spans, 4);
+
+        // a span is more than 64 bytes
+        let (mut exporter, in_memory) =
+            new_exporter(RateLimit::default().with_bytes_per_second(100), &metrics);
+        exporter.export(batch(2)).await.unwrap();
+        assert_eq!(in_memory.spans().len(), 1);
+
+        let (mut exporter, in_memory) = new_exporter(
+            RateLimit::default()
+                .with_spans_per_second(10)
+                .with_overflow(OverflowPolicy::Sample),
+            &metrics,
+        );
+        exporter.export(batch(100)).await.unwrap();
+        let sampled = in_memory.spans();
+        assert!(!sampled.is_empty() && sampled.len() <= 10, "{sampled:?}");
+        // sampled spans are spread over the batch rather than the first ones
+        assert!(sampled
+            .iter()
+            .any(|span| span.name.parse::<u64>().unwrap() >= 10));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_delay() {
+        let (mut exporter, in_memory) = new_exporter(
+            RateLimit::default()
+                .with_spans_per_second(10)
+                .with_overflow(OverflowPolicy::Delay {
+                    max_delay: Duration::from_millis(500),
+                }),
+            &ExporterMetrics::new(),
+        );
+        let start = Instant::now();
+        exporter.export(batch(20)).await.unwrap();
+        // 5 spans are borrowed from the next 500ms
+        assert_eq!(in_memory.spans().len(), 15);
+        assert!(start.elapsed() >= Duration::from_millis(450));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_after_truncation() {
+        let config = ConfigBuilder::default()
+            .with_rate_limit(RateLimit::default().with_bytes_per_second(1000))
+            .with_export_limits(ExportLimits::default().with_attribute_value_length(16))
+            .build()
+            .unwrap();
+        let TraceExporter::Truncating(truncating) =
+            TraceExporter::try_from(config.clone()).unwrap()
+        else {
+            panic!("truncation should be outside the rate limit");
+        };
+        assert!(matches!(truncating.inner(), TraceExporter::RateLimited(_)));
+
+        let in_memory = InMemoryTraceExporter::default();
+        let mut exporter = TraceExporter::from(TruncatingTraceExporter::new(
+            RateLimitedTraceExporter::new(in_memory.clone().into(), config.clone()).into(),
+            config.export_limits().cloned().unwrap(),
+        ));
+        let mut spans = batch(2);
+        for span in &mut spans {
+            span.attributes
+                .insert(KeyValue::new("http.response.body", "x".repeat(1 << 20)));
+        }
+        // bytes of truncated values are charged
+        exporter.export(spans).await.unwrap();
+        assert_eq!(in_memory.spans().len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_rate_limit() {
+        assert!(ConfigBuilder::default()
+            .with_rate_limit(RateLimit::default().with_spans_per_second(0))
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::default()
+            .with_rate_limit(RateLimit::default().with_burst(Duration::ZERO))
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::{collections::HashMap, sync::Arc};
+
+use futures::future::BoxFuture;
+use opentelemetry_api::{
+    trace::{Event, Link, SpanContext},
+    Key, KeyValue, Value,
+};
+use opentelemetry_sdk::{
+    export::trace::{ExportResult, SpanData, SpanExporter},
+    trace::{EvictedHashMap, EvictedQueue},
+};
+
+use crate::config::RedactionHook;
+
+use super::TraceExporter;
+
+/// An exporter which redacts attributes of spans, span events and span links before they are
+/// encoded. Removed attributes are counted as dropped ones.
+#[derive(Debug)]
+pub struct RedactingTraceExporter {
+    inner: Box<TraceExporter>,
+    hooks: Vec<Arc<dyn RedactionHook>>,
+}
+
+impl RedactingTraceExporter {
+    /// Apply `hooks` in order to spans passed to `inner`.
+    pub(crate) fn new(inner: TraceExporter, hooks: Vec<Arc<dyn RedactionHook>>) -> Self {
+        Self {
+            inner: Box::new(inner),
+            hooks,
+        }
+    }
+
+    pub fn inner(&self) -> &TraceExporter {
+        &self.inner
+    }
+
+    pub(super) fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let batch = batch
+            .into_iter()
+            .map(|span| self.redact_span(span))
+            .collect();
+        self.inner.export(batch)
+    }
+
+    fn redact_span(&self, mut span: SpanData) -> SpanData {
+        let attributes = std::mem::replace(&mut span.attributes, EvictedHashMap::new(0, 0));
+        let dropped = attributes.dropped_count();
+        let (attributes, removed) = self.redact_attributes(
+            attributes
+                .into_iter()
+                .map(|(key, value)| KeyValue { key, value })
+                .collect(),
+        );
+        span.attributes = rebuild_map(attributes, dropped + removed);
+
+        let events = std::mem::replace(&mut span.events, EvictedQueue::new(0));
+        let dropped = events.dropped_count();
+        let events = events
+            .into_iter()
+            .map(|mut event| {
+                let (attributes, removed) = self.redact_attributes(event.attributes);
+                event.attributes = attributes;
+                event.dropped_attributes_count += removed;
+                event
+            })
+            .collect();
+        span.events = rebuild_queue(events, dropped, || Event::with_name(""));
+
+        let links = std::mem::replace(&mut span.links, EvictedQueue::new(0));
+        let dropped = links.dropped_count();
+        let links = links
+            .into_iter()
+            .map(|mut link| {
+                let (attributes, removed) = self.redact_attributes(link.attributes);
+                link.attributes = attributes;
+                link.dropped_attributes_count += removed;
+                link
+            })
+            .collect();
+        span.links = rebuild_queue(links, dropped, || {
+            Link::new(SpanContext::empty_context(), Vec::new())
+        });
+        span
+    }
+
+    /// Return redacted attributes and the number of removed ones.
+    fn redact_attributes(&self, attributes: Vec<KeyValue>) -> (Vec<KeyValue>, u32) {
+        let len = attributes.len();
+        let redacted: Vec<_> = attributes
+            .into_iter()
+            .filter_map(|attribute| {
+                self.hooks
+                    .iter()
+                    .try_fold(attribute, |attribute, hook| hook.redact(attribute))
+            })
+            .collect();
+        let removed = (len - redacted.len()) as u32;
+        (redacted, removed)
+    }
+}
+
+impl From<RedactingTraceExporter> for TraceExporter {
+    fn from(exporter: RedactingTraceExporter) -> Self {
+        TraceExporter::Redacting(exporter)
+    }
+}
+
+// Neither `EvictedHashMap` nor `EvictedQueue` can be modified in place or have its dropped count
+// set, so they are rebuilt with placeholders inserted first, which are evicted by real items.
+
+/// Attributes with the same key, e.g. renamed by a hook, are de-duplicated before the map is
+/// sized, the last one is kept and the others are counted as dropped.
+pub(super) fn rebuild_map(attributes: Vec<KeyValue>, dropped: u32) -> EvictedHashMap {
+    let len = attributes.len();
+    let attributes: HashMap<Key, Value> = attributes
+        .into_iter()
+        .map(|attribute| (attribute.key, attribute.value))
+        .collect();
+    let dropped = dropped + (len - attributes.len()) as u32;
+    let mut map = EvictedHashMap::new(attributes.len() as u32, attributes.len());
+    for i in 0..dropped {
+        map.insert(KeyValue::new(
+            Key::from(format!("\0placeholder.{i}")),
+            false,
+        ));
+    }
+    for (key, value) in attributes {
+        map.insert(KeyValue { key, value });
+    }
+    map
+}
+
+pub(super) fn rebuild_queue<T>(
+    items: Vec<T>,
+    dropped: u32,
+    placeholder: impl Fn() -> T,
+) -> EvictedQueue<T> {
+    let mut queue = EvictedQueue::new(items.len() as u32);
+    queue.extend((0..dropped).map(|_| placeholder()));
+    queue.extend(items);
+    queue
+}
+
+#[cfg(all(test, feature = "testing", feature = "redaction"))]
+mod tests {
+    use std::sync::Arc;
+
+    use opentelemetry_api::{
+        trace::{Event, Link, SpanContext},
+        KeyValue, Value,
+    };
+    use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue};
+    use opentelemetry_sdk::export::trace::SpanExporter;
+
+    use crate::{
+        config::{ConfigBuilder, MaskRule, RedactionHook, RedactionRules},
+        exporter::trace::TraceExporter,
+        testing::{test_span, InMemoryTraceExporter},
+    };
+
+    /// Upper-case all string values.
+    #[derive(Debug)]
+    struct UpperCase;
+
+    impl RedactionHook for UpperCase {
+        fn redact(&self, attribute: KeyValue) -> Option<KeyValue> {
+            match attribute.value {
+                Value::String(s) => Some(KeyValue::new(attribute.key, s.as_str().to_uppercase())),
+                _ => Some(attribute),
+            }
+        }
+    }
+
+    /// Rename `user.email` and `user.name` to `user`.
+    #[derive(Debug)]
+    struct Rename;
+
+    impl RedactionHook for Rename {
+        fn redact(&self, attribute: KeyValue) -> Option<KeyValue> {
+            match attribute.key.as_str() {
+                "user.email" | "user.name" => Some(KeyValue::new("user", attribute.value)),
+                _ => Some(attribute),
+            }
+        }
+    }
+
+    fn string_value(s: &str) -> AnyValue {
+        AnyValue {
+            value: Some(any_value::Value::StringValue(s.to_owned())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redaction() {
+        let config = ConfigBuilder::default()
+            .with_redaction(
+                RedactionRules::default()
+                    .add_deny_key("password")
+                    .add_mask(MaskRule::new(r"\d{16}")),
+            )
+            .with_redaction_hook(UpperCase)
+            .build()
+            .unwrap();
+        let in_memory = InMemoryTraceExporter::default();
+        let mut exporter = TraceExporter::from(super::RedactingTraceExporter {
+            inner: Box::new(in_memory.clone().into()),
+            hooks: config.redaction_hooks().unwrap(),
+        });
+
+        let mut span = test_span("span");
+        span.attributes.insert(KeyValue::new("password", "secret"));
+        span.attributes
+            .insert(KeyValue::new("card", "4111111111111111"));
+        span.events.extend([Event::new(
+            "event",
+            std::time::SystemTime::now(),
+            vec![
+                KeyValue::new("password", "secret"),
+                KeyValue::new("note", "ok"),
+            ],
+            1,
+        )]);
+        span.links.extend([Link::new(
+            SpanContext::empty_context(),
+            vec![KeyValue::new("password", "secret")],
+        )]);
+        exporter.export(vec![span]).await.unwrap();
+
+        let span = in_memory.find_span("span").unwrap();
+        assert_eq!(span.attributes.len(), 1);
+        assert_eq!(span.attributes[0].key, "card");
+        assert_eq!(span.attributes[0].value, Some(string_value("[REDACTED]")));
+        assert_eq!(span.dropped_attributes_count, 1);
+        assert_eq!(span.events[0].attributes.len(), 1);
+        assert_eq!(span.events[0].attributes[0].value, Some(string_value("OK")));
+        assert_eq!(span.events[0].dropped_attributes_count, 2);
+        assert!(span.links[0].attributes.is_empty());
+        assert_eq!(span.links[0].dropped_attributes_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_redaction_renamed_keys() {
+        let in_memory = InMemoryTraceExporter::default();
+        let mut exporter = TraceExporter::from(super::RedactingTraceExporter {
+            inner: Box::new(in_memory.clone().into()),
+            hooks: vec![Arc::new(Rename)],
+        });
+
+        let mut span = test_span("span");
+        span.attributes.insert(KeyValue::new("user.email", "a@b.c"));
+        span.attributes.insert(KeyValue::new("user.name", "a"));
+        span.attributes.insert(KeyValue::new("http.method", "GET"));
+        exporter.export(vec![span]).await.unwrap();
+
+        let span = in_memory.find_span("span").unwrap();
+        let mut keys: Vec<_> = span.attributes.iter().map(|a| a.key.as_str()).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["http.method", "user"]);
+        assert_eq!(span.dropped_attributes_count, 1);
+    }
+}
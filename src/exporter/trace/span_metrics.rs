@@ -0,0 +1,248 @@
+use futures::future::BoxFuture;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::exporter::span_metrics::SpanMetrics;
+
+use super::TraceExporter;
+
+/// An exporter which records [`SpanMetrics`] of every span, then passes the batch to the inner
+/// exporter.
+#[derive(Debug)]
+pub struct SpanMetricsTraceExporter {
+    inner: Box<TraceExporter>,
+    metrics: SpanMetrics,
+}
+
+impl SpanMetricsTraceExporter {
+    /// Record `metrics` for spans passed to `inner`.
+    pub(crate) fn new(inner: TraceExporter, metrics: SpanMetrics) -> Self {
+        Self {
+            inner: Box::new(inner),
+            metrics,
+        }
+    }
+
+    pub fn inner(&self) -> &TraceExporter {
+        &self.inner
+    }
+
+    pub fn metrics(&self) -> &SpanMetrics {
+        &self.metrics
+    }
+
+    pub(super) fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+
+    pub(super) fn force_flush(&mut self) -> BoxFuture<'static, ExportResult> {
+        self.inner.force_flush()
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        for span in &batch {
+            self.metrics.record(span);
+        }
+        self.inner.export(batch)
+    }
+}
+
+impl From<SpanMetricsTraceExporter> for TraceExporter {
+    fn from(exporter: SpanMetricsTraceExporter) -> Self {
+        TraceExporter::SpanMetrics(exporter)
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::sync::{Arc, Weak};
+
+    use opentelemetry_api::{
+        metrics::{MeterProvider as _, Result},
+        Context, KeyValue,
+    };
+    use opentelemetry_sdk::{
+        export::trace::SpanExporter,
+        metrics::{
+            data::{Histogram, ResourceMetrics, Sum, Temporality},
+            reader::{AggregationSelector, MetricProducer, MetricReader, TemporalitySelector},
+            Aggregation, InstrumentKind, ManualReader, MeterProvider, Pipeline,
+        },
+        AttributeSet, Resource,
+    };
+
+    use super::SpanMetricsTraceExporter;
+    use crate::{
+        config::{ConfigBuilder, RedactionHook, SamplingPolicy, TailSampling},
+        exporter::trace::{RedactingTraceExporter, TailSamplingTraceExporter, TraceExporter},
+        testing::{test_span, InMemoryTraceExporter},
+        SpanMetrics,
+    };
+
+    /// A reader shared with the meter provider, which takes the ownership of its readers.
+    #[derive(Clone, Debug)]
+    struct SharedReader(Arc<ManualReader>);
+
+    impl AggregationSelector for SharedReader {
+        fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+            self.0.aggregation(kind)
+        }
+    }
+
+    impl TemporalitySelector for SharedReader {
+        fn temporality(&self, kind: InstrumentKind) -> Temporality {
+            self.0.temporality(kind)
+        }
+    }
+
+    impl MetricReader for SharedReader {
+        fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+            self.0.register_pipeline(pipeline)
+        }
+
+        fn register_producer(&self, producer: Box<dyn MetricProducer>) {
+            self.0.register_producer(producer)
+        }
+
+        fn collect(&self, rm: &mut ResourceMetrics) -> Result<()> {
+            self.0.collect(rm)
+        }
+
+        fn force_flush(&self, cx: &Context) -> Result<()> {
+            self.0.force_flush(cx)
+        }
+
+        fn shutdown(&self) -> Result<()> {
+            self.0.shutdown()
+        }
+    }
+
+    /// Span metrics recorded into a manual reader.
+    fn span_metrics() -> (SpanMetrics, SharedReader, MeterProvider) {
+        let reader = SharedReader(Arc::new(ManualReader::builder().build()));
+        let provider = MeterProvider::builder().with_reader(reader.clone()).build();
+        let metrics = SpanMetrics::new(&provider.meter("test")).add_dimension("user.email");
+        (metrics, reader, provider)
+    }
+
+    /// Calls and duration counts of each data point, with their attributes.
+    type Points = Vec<(u64, Vec<KeyValue>)>;
+
+    fn collect(reader: &SharedReader) -> (Points, Points) {
+        let mut rm = ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: Vec::new(),
+        };
+        reader.collect(&mut rm).unwrap();
+        let attributes = |attributes: &AttributeSet| {
+            attributes
+                .iter()
+                .map(|(key, value)| KeyValue::new(key.clone(), value.clone()))
+                .collect()
+        };
+        let (mut calls, mut durations) = (Vec::new(), Vec::new());
+        for metric in rm.scope_metrics.iter().flat_map(|scope| &scope.metrics) {
+            let data = metric.data.as_any();
+            match metric.name.as_ref() {
+                "traces.span.metrics.calls" => {
+                    let sum = data.downcast_ref::<Sum<u64>>().unwrap();
+                    calls.extend(
+                        sum.data_points
+                            .iter()
+                            .map(|point| (point.value, attributes(&point.attributes))),
+                    );
+                }
+                "traces.span.metrics.duration" => {
+                    let histogram = data.downcast_ref::<Histogram<f64>>().unwrap();
+                    durations.extend(
+                        histogram
+                            .data_points
+                            .iter()
+                            .map(|point| (point.count, attributes(&point.attributes))),
+                    );
+                }
+                _ => {}
+            }
+        }
+        (calls, durations)
+    }
+
+    #[tokio::test]
+    async fn test_span_metrics_before_tail_sampling() {
+        let in_memory = InMemoryTraceExporter::default();
+        let config = ConfigBuilder::default()
+            .with_tail_sampling(
+                TailSampling::default().add_policy(SamplingPolicy::Probabilistic { ratio: 1.0 }),
+            )
+            .build()
+            .unwrap();
+        let (metrics, reader, _provider) = span_metrics();
+        let mut exporter = TraceExporter::from(SpanMetricsTraceExporter::new(
+            TailSamplingTraceExporter::new(in_memory.clone().into(), config).into(),
+            metrics,
+        ));
+
+        let mut spans = [test_span("span"), test_span("span")];
+        for span in &mut spans {
+            span.attributes.insert(KeyValue::new("user.email", "a@b.c"));
+        }
+        exporter.export(spans.to_vec()).await.unwrap();
+        assert!(in_memory.spans().is_empty());
+        // spans are recorded before they are buffered by tail sampling
+        let (calls, durations) = collect(&reader);
+        let email = KeyValue::new("user.email", "a@b.c");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, 2);
+        assert!(calls[0].1.contains(&email));
+        assert_eq!(durations.len(), 1);
+        assert_eq!(durations[0].0, 2);
+        assert!(durations[0].1.contains(&email));
+
+        // the flush reaches the buffer of tail sampling
+        exporter.force_flush().await.unwrap();
+        assert_eq!(in_memory.spans().len(), 2);
+    }
+
+    /// Remove `user.email`.
+    #[derive(Debug)]
+    struct RemoveEmail;
+
+    impl RedactionHook for RemoveEmail {
+        fn redact(&self, attribute: KeyValue) -> Option<KeyValue> {
+            (attribute.key.as_str() != "user.email").then_some(attribute)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_span_metrics_after_redaction() {
+        let (metrics, reader, _provider) = span_metrics();
+        let config = ConfigBuilder::default()
+            .with_span_metrics(metrics.clone())
+            .with_redaction_hook(RemoveEmail)
+            .build()
+            .unwrap();
+        let TraceExporter::Redacting(redacting) = TraceExporter::try_from(config.clone()).unwrap()
+        else {
+            panic!("redaction should be outermost");
+        };
+        assert!(matches!(redacting.inner(), TraceExporter::SpanMetrics(_)));
+
+        let in_memory = InMemoryTraceExporter::default();
+        let mut exporter = TraceExporter::from(RedactingTraceExporter::new(
+            SpanMetricsTraceExporter::new(in_memory.clone().into(), metrics).into(),
+            config.redaction_hooks().unwrap(),
+        ));
+        let mut span = test_span("span");
+        span.attributes.insert(KeyValue::new("user.email", "a@b.c"));
+        span.attributes.insert(KeyValue::new("user.id", 1));
+        exporter.export(vec![span]).await.unwrap();
+
+        // the dimension is omitted rather than recorded with the unredacted value
+        let (calls, durations) = collect(&reader);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, 1);
+        assert!(calls[0].1.contains(&KeyValue::new("span.name", "span")));
+        assert!(calls[0].1.iter().all(|kv| kv.key.as_str() != "user.email"));
+        assert_eq!(durations.len(), 1);
+        assert_eq!(durations[0].0, 1);
+    }
+}
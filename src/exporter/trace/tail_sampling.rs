@@ -0,0 +1,666 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex, MutexGuard, Weak},
+    time::{Duration, Instant},
+};
+
+use futures::{
+    future::{self, BoxFuture, Either},
+    FutureExt,
+};
+use opentelemetry_api::{
+    global,
+    trace::{SpanId, Status, TraceError, TraceId},
+};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+
+use crate::{
+    config::{Config, SamplingPolicy, SpanStatusCode, TailSampling},
+    error::OtlpExporterError,
+};
+
+use super::{
+    rate_limit::sampled,
+    timer::{self, sleep},
+    TraceExporter,
+};
+
+#[derive(Debug)]
+struct PendingTrace {
+    first_seen: Instant,
+    spans: Vec<SpanData>,
+}
+
+/// Spans kept by a rate limiting policy in the current second.
+#[derive(Clone, Debug, Default)]
+struct RateWindow {
+    start: Option<Instant>,
+    spans: u64,
+}
+
+impl RateWindow {
+    fn admit(&mut self, spans: usize, spans_per_second: u64, now: Instant) -> bool {
+        if self
+            .start
+            .is_none_or(|start| now.saturating_duration_since(start) >= Duration::from_secs(1))
+        {
+            self.start = Some(now);
+            self.spans = 0;
+        }
+        if self.spans + spans as u64 > spans_per_second {
+            return false;
+        }
+        self.spans += spans as u64;
+        true
+    }
+}
+
+/// Buffered traces and decisions, shared with the task deciding traces in the background.
+#[derive(Debug)]
+struct Buffer {
+    config: Config,
+    tail_sampling: TailSampling,
+    pending: HashMap<TraceId, PendingTrace>,
+    /// Trace ids of `pending` in the order their first spans arrived.
+    order: VecDeque<TraceId>,
+    pending_spans: usize,
+    /// Decisions of recently decided traces, for their late spans.
+    decisions: HashMap<TraceId, bool>,
+    decision_order: VecDeque<TraceId>,
+    /// A window for each policy, only rate limiting ones use it.
+    windows: Vec<RateWindow>,
+    /// Whether a task is deciding traces in the background.
+    deciding: bool,
+}
+
+/// An exporter which buffers spans by their trace ids and exports only traces kept by the
+/// policies of [`TailSampling`].
+///
+/// Traces are decided in the background once their decision windows end, so an idle service
+/// still exports its kept traces. The background task runs on the tokio runtime if there is one,
+/// otherwise on its own thread. Buffered spans count as exported for the span processor.
+/// [`SpanExporter::force_flush`] and shutdown decide all buffered traces at once. Spans dropped
+/// and traces evicted are recorded in the metrics of the config, evictions and failed exports of
+/// kept traces are reported through the global error handler too.
+#[derive(Debug)]
+pub struct TailSamplingTraceExporter {
+    inner: Arc<Mutex<TraceExporter>>,
+    tail_sampling: TailSampling,
+    buffer: Arc<Mutex<Buffer>>,
+}
+
+impl TailSamplingTraceExporter {
+    /// Sample traces for `inner` with tail sampling of `config`.
+    pub(crate) fn new(inner: TraceExporter, config: Config) -> Self {
+        let tail_sampling = config.tail_sampling().cloned().unwrap_or_default();
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            tail_sampling: tail_sampling.clone(),
+            buffer: Arc::new(Mutex::new(Buffer {
+                windows: vec![RateWindow::default(); tail_sampling.policies().len()],
+                tail_sampling,
+                config,
+                pending: HashMap::new(),
+                order: VecDeque::new(),
+                pending_spans: 0,
+                decisions: HashMap::new(),
+                decision_order: VecDeque::new(),
+                deciding: false,
+            })),
+        }
+    }
+
+    pub fn inner(&self) -> MutexGuard<'_, TraceExporter> {
+        lock(&self.inner)
+    }
+
+    pub fn tail_sampling(&self) -> &TailSampling {
+        &self.tail_sampling
+    }
+
+    /// Number of traces waiting for decisions.
+    pub fn pending_traces(&self) -> usize {
+        lock(&self.buffer).pending.len()
+    }
+
+    /// Decide buffered traces and export kept ones before shutting down the inner exporter.
+    /// Outside a tokio runtime it waits for the export up to the timeout of the config. Inside a
+    /// runtime, blocking the thread may stop the runtime from driving the export, so it keeps
+    /// running on the runtime like other in-flight exports.
+    pub(super) fn shutdown(&mut self) {
+        let kept = lock(&self.buffer).decide_all();
+        if !kept.is_empty() && !self.inner().is_shut_down() {
+            let export = self.inner().export(kept);
+            if tokio::runtime::Handle::try_current().is_ok() {
+                timer::spawn(export.map(report_failure));
+            } else {
+                let timeout = lock(&self.buffer).config.timeout();
+                match futures::executor::block_on(future::select(export, sleep(timeout))) {
+                    Either::Left((result, _)) => report_failure(result),
+                    Either::Right(_) => report_failure(Err(OtlpExporterError::TailSamplingError(
+                        "timed out exporting kept traces on shutdown".to_owned(),
+                    )
+                    .into())),
+                }
+            }
+        }
+        self.inner().shutdown();
+    }
+
+    /// Decide buffered traces now and export kept ones.
+    pub(super) fn force_flush(&mut self) -> BoxFuture<'static, ExportResult> {
+        let kept = lock(&self.buffer).decide_all();
+        export_kept(&self.inner, kept)
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        if self.inner().is_shut_down() {
+            return self.inner().export(batch);
+        }
+        let (kept, decide) = {
+            let mut buffer = lock(&self.buffer);
+            let kept = buffer.add(batch, Instant::now());
+            let decide = !buffer.deciding && !buffer.pending.is_empty();
+            buffer.deciding |= decide;
+            (kept, decide)
+        };
+        if decide {
+            timer::spawn(decide_in_background(
+                Arc::downgrade(&self.buffer),
+                Arc::downgrade(&self.inner),
+            ));
+        }
+        export_kept(&self.inner, kept)
+    }
+}
+
+impl From<TailSamplingTraceExporter> for TraceExporter {
+    fn from(exporter: TailSamplingTraceExporter) -> Self {
+        TraceExporter::TailSampling(exporter)
+    }
+}
+
+impl Buffer {
+    /// Buffer spans of `batch`, and decide traces whose decision windows end or which don't fit
+    /// in the buffer. Return spans to export.
+    fn add(&mut self, batch: Vec<SpanData>, now: Instant) -> Vec<SpanData> {
+        let mut kept = Vec::new();
+        let mut dropped = 0;
+        for span in batch {
+            let trace_id = span.span_context.trace_id();
+            match self.decisions.get(&trace_id) {
+                Some(true) => kept.push(span),
+                Some(false) => dropped += 1,
+                None => self.buffer(trace_id, span, now),
+            }
+        }
+
+        let mut evicted = 0;
+        while self.pending.len() > self.tail_sampling.max_traces()
+            || self.pending_spans > self.tail_sampling.max_spans()
+        {
+            let Some(spans) = self.decide_oldest(now, &mut kept) else {
+                break;
+            };
+            dropped += spans;
+            evicted += 1;
+        }
+        self.record(dropped, evicted);
+        kept.extend(self.decide_expired(now));
+        kept
+    }
+
+    fn buffer(&mut self, trace_id: TraceId, span: SpanData, now: Instant) {
+        self.pending_spans += 1;
+        self.pending
+            .entry(trace_id)
+            .or_insert_with(|| {
+                self.order.push_back(trace_id);
+                PendingTrace {
+                    first_seen: now,
+                    spans: Vec::new(),
+                }
+            })
+            .spans
+            .push(span);
+    }
+
+    /// When the decision window of the oldest buffered trace ends.
+    fn next_deadline(&self) -> Option<Instant> {
+        let oldest = self.order.front().and_then(|id| self.pending.get(id))?;
+        Some(oldest.first_seen + self.tail_sampling.decision_wait())
+    }
+
+    /// Decide traces whose decision windows end, return spans of kept ones.
+    fn decide_expired(&mut self, now: Instant) -> Vec<SpanData> {
+        let mut kept = Vec::new();
+        let mut dropped = 0;
+        while self.next_deadline().is_some_and(|deadline| deadline <= now) {
+            dropped += self.decide_oldest(now, &mut kept).unwrap_or_default();
+        }
+        self.record(dropped, 0);
+        kept
+    }
+
+    fn decide_all(&mut self) -> Vec<SpanData> {
+        let now = Instant::now();
+        let mut kept = Vec::new();
+        let mut dropped = 0;
+        while let Some(spans) = self.decide_oldest(now, &mut kept) {
+            dropped += spans;
+        }
+        self.record(dropped, 0);
+        kept
+    }
+
+    /// Decide the oldest trace, spans of it are pushed to `kept` if it is kept. Return the number
+    /// of dropped spans, or `None` if there is no buffered trace.
+    fn decide_oldest(&mut self, now: Instant, kept: &mut Vec<SpanData>) -> Option<usize> {
+        let trace_id = self.order.pop_front()?;
+        let Some(trace) = self.pending.remove(&trace_id) else {
+            return Some(0);
+        };
+        self.pending_spans -= trace.spans.len();
+        let keep = self.decide(trace_id, &trace.spans, now);
+
+        self.decisions.insert(trace_id, keep);
+        self.decision_order.push_back(trace_id);
+        if self.decision_order.len() > self.tail_sampling.max_traces() {
+            if let Some(forgotten) = self.decision_order.pop_front() {
+                self.decisions.remove(&forgotten);
+            }
+        }
+
+        if keep {
+            kept.extend(trace.spans);
+            Some(0)
+        } else {
+            Some(trace.spans.len())
+        }
+    }
+
+    fn decide(&mut self, trace_id: TraceId, spans: &[SpanData], now: Instant) -> bool {
+        self.tail_sampling
+            .policies()
+            .iter()
+            .zip(&mut self.windows)
+            .any(|(policy, window)| match policy {
+                SamplingPolicy::StatusCode { status_codes } => spans
+                    .iter()
+                    .any(|span| status_codes.contains(&status_code(&span.status))),
+                SamplingPolicy::Latency { threshold } => duration(spans) >= *threshold,
+                SamplingPolicy::Attribute { key, values } => spans.iter().any(|span| {
+                    span.attributes.iter().any(|(k, v)| {
+                        k.as_str() == key
+                            && (values.is_empty()
+                                || values.iter().any(|value| *value == v.as_str()))
+                    })
+                }),
+                SamplingPolicy::Probabilistic { ratio } => sampled(trace_id, *ratio),
+                SamplingPolicy::RateLimiting { spans_per_second } => {
+                    window.admit(spans.len(), *spans_per_second, now)
+                }
+            })
+    }
+
+    fn record(&self, dropped: usize, evicted: usize) {
+        if evicted > 0 {
+            global::handle_error(TraceError::from(OtlpExporterError::TailSamplingError(
+                format!(
+                    "{evicted} traces are decided before their decision windows end, the buffer is full"
+                ),
+            )));
+        }
+        if dropped > 0 || evicted > 0 {
+            if let Some(metrics) = self.config.metrics() {
+                metrics.record_tail_sampling(&self.config, dropped, evicted);
+            }
+        }
+    }
+}
+
+/// Decide traces as their decision windows end until the buffer is empty, or the exporter is
+/// dropped or shut down.
+async fn decide_in_background(buffer: Weak<Mutex<Buffer>>, inner: Weak<Mutex<TraceExporter>>) {
+    loop {
+        let Some(deadline) = buffer.upgrade().and_then(|buffer| {
+            let mut buffer = lock(&buffer);
+            let deadline = buffer.next_deadline();
+            buffer.deciding = deadline.is_some();
+            deadline
+        }) else {
+            return;
+        };
+        sleep(deadline.saturating_duration_since(Instant::now())).await;
+
+        let (Some(buffer), Some(inner)) = (buffer.upgrade(), inner.upgrade()) else {
+            return;
+        };
+        let kept = lock(&buffer).decide_expired(Instant::now());
+        if lock(&inner).is_shut_down() {
+            lock(&buffer).deciding = false;
+            return;
+        }
+        let export = export_kept(&inner, kept);
+        drop((buffer, inner));
+        report_failure(export.await);
+    }
+}
+
+fn export_kept(
+    inner: &Mutex<TraceExporter>,
+    kept: Vec<SpanData>,
+) -> BoxFuture<'static, ExportResult> {
+    if kept.is_empty() {
+        return future::ready(Ok(())).boxed();
+    }
+    lock(inner).export(kept)
+}
+
+/// Kept traces exported apart from batches have no caller to return failures to.
+fn report_failure(result: ExportResult) {
+    if let Err(e) = result {
+        global::handle_error(e);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn status_code(status: &Status) -> SpanStatusCode {
+    match status {
+        Status::Unset => SpanStatusCode::Unset,
+        Status::Ok => SpanStatusCode::Ok,
+        Status::Error { .. } => SpanStatusCode::Error,
+    }
+}
+
+/// The duration of the root span, whose parent is invalid or not in the trace. Without one, it is
+/// from the earliest start to the latest end of `spans`.
+fn duration(spans: &[SpanData]) -> Duration {
+    let span_ids: HashSet<_> = spans
+        .iter()
+        .map(|span| span.span_context.span_id())
+        .collect();
+    let root = spans
+        .iter()
+        .find(|span| span.parent_span_id == SpanId::INVALID)
+        .or_else(|| {
+            spans
+                .iter()
+                .find(|span| !span_ids.contains(&span.parent_span_id))
+        });
+    let (start, end) = match root {
+        Some(root) => (Some(root.start_time), Some(root.end_time)),
+        None => (
+            spans.iter().map(|span| span.start_time).min(),
+            spans.iter().map(|span| span.end_time).max(),
+        ),
+    };
+    match (start, end) {
+        (Some(start), Some(end)) => end.duration_since(start).unwrap_or_default(),
+        _ => Duration::ZERO,
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::time::Duration;
+
+    use opentelemetry_api::{
+        trace::{SpanContext, SpanId, Status, TraceId},
+        KeyValue,
+    };
+    use opentelemetry_sdk::export::trace::{SpanData, SpanExporter};
+
+    use super::TailSamplingTraceExporter;
+    use crate::{
+        config::{ConfigBuilder, SamplingPolicy, SpanStatusCode, TailSampling},
+        exporter::trace::TraceExporter,
+        testing::{test_span, InMemoryTraceExporter},
+        ExporterMetrics,
+    };
+
+    fn new_exporter(
+        tail_sampling: TailSampling,
+        metrics: &ExporterMetrics,
+    ) -> (TraceExporter, InMemoryTraceExporter) {
+        let in_memory = InMemoryTraceExporter::default();
+        let config = ConfigBuilder::default()
+            .with_tail_sampling(tail_sampling)
+            .with_metrics(metrics.clone())
+            .build()
+            .unwrap();
+        let exporter = TailSamplingTraceExporter::new(in_memory.clone().into(), config);
+        (exporter.into(), in_memory)
+    }
+
+    fn span(trace_id: u128, name: &'static str) -> SpanData {
+        let mut span = test_span(name);
+        span.span_context = SpanContext::new(
+            TraceId::from_bytes(trace_id.to_be_bytes()),
+            span.span_context.span_id(),
+            span.span_context.trace_flags(),
+            false,
+            Default::default(),
+        );
+        span
+    }
+
+    fn names(in_memory: &InMemoryTraceExporter) -> Vec<String> {
+        let mut names: Vec<_> = in_memory.spans().into_iter().map(|s| s.name).collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_tail_sampling() {
+        let metrics = ExporterMetrics::new();
+        let decision_wait = Duration::from_millis(100);
+        let (mut exporter, in_memory) = new_exporter(
+            TailSampling::default()
+                .with_decision_wait(decision_wait)
+                .add_policy(SamplingPolicy::StatusCode {
+                    status_codes: vec![SpanStatusCode::Error],
+                })
+                .add_policy(SamplingPolicy::Latency {
+                    threshold: Duration::from_secs(1),
+                })
+                .add_policy(SamplingPolicy::Attribute {
+                    key: "keep".to_owned(),
+                    values: Vec::new(),
+                }),
+            &metrics,
+        );
+
+        let mut error = span(2, "error");
+        error.status = Status::error("failed");
+        let mut slow = span(3, "slow");
+        slow.end_time = slow.start_time + Duration::from_secs(2);
+        let mut marked = span(4, "marked");
+        marked.attributes.insert(KeyValue::new("keep", true));
+        exporter
+            .export(vec![
+                span(1, "ok"),
+                span(2, "error.child"),
+                error,
+                slow,
+                marked,
+            ])
+            .await
+            .unwrap();
+        assert!(in_memory.requests().is_empty());
+
+        tokio::time::sleep(decision_wait).await;
+        exporter.export(vec![span(5, "late")]).await.unwrap();
+        assert_eq!(
+            names(&in_memory),
+            ["error", "error.child", "marked", "slow"]
+        );
+        assert_eq!(metrics.snapshot().sampled_out_spans, 1);
+
+        // late spans follow decisions
+        exporter
+            .export(vec![span(2, "error.late"), span(1, "ok.late")])
+            .await
+            .unwrap();
+        assert!(names(&in_memory).contains(&"error.late".to_owned()));
+        assert_eq!(metrics.snapshot().sampled_out_spans, 2);
+
+        // the pending trace 5 is decided on flush
+        exporter.force_flush().await.unwrap();
+        assert_eq!(in_memory.spans().len(), 5);
+        assert_eq!(metrics.snapshot().sampled_out_spans, 3);
+        assert_eq!(metrics.snapshot().evicted_traces, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tail_sampling_limits() {
+        let metrics = ExporterMetrics::new();
+        let (mut exporter, in_memory) = new_exporter(
+            TailSampling::default()
+                .with_max_traces(2)
+                .add_policy(SamplingPolicy::RateLimiting {
+                    spans_per_second: 2,
+                }),
+            &metrics,
+        );
+        exporter
+            .export(vec![span(1, "1"), span(2, "2"), span(3, "3")])
+            .await
+            .unwrap();
+        // trace 1 is evicted and decided early
+        assert_eq!(names(&in_memory), ["1"]);
+        assert_eq!(metrics.snapshot().evicted_traces, 1);
+
+        exporter.force_flush().await.unwrap();
+        // the rate limit allows 2 spans per second
+        assert_eq!(names(&in_memory), ["1", "2"]);
+        assert_eq!(metrics.snapshot().sampled_out_spans, 1);
+
+        let (mut exporter, in_memory) = new_exporter(
+            TailSampling::default().add_policy(SamplingPolicy::Probabilistic { ratio: 0.5 }),
+            &metrics,
+        );
+        exporter
+            .export(
+                (0..100u64)
+                    .map(|i| span(u128::from(i.wrapping_mul(0x9e37_79b9_7f4a_7c15)), "span"))
+                    .collect(),
+            )
+            .await
+            .unwrap();
+        exporter.force_flush().await.unwrap();
+        let kept = in_memory.spans().len();
+        assert!(kept > 0 && kept < 100, "{kept}");
+
+        assert!(ConfigBuilder::default()
+            .with_tail_sampling(TailSampling::default())
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::default()
+            .with_tail_sampling(
+                TailSampling::default().add_policy(SamplingPolicy::Probabilistic { ratio: 2.0 })
+            )
+            .build()
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tail_sampling_when_idle() {
+        let metrics = ExporterMetrics::new();
+        let decision_wait = Duration::from_millis(50);
+        let (mut exporter, in_memory) = new_exporter(
+            TailSampling::default()
+                .with_decision_wait(decision_wait)
+                .add_policy(SamplingPolicy::Probabilistic { ratio: 1.0 }),
+            &metrics,
+        );
+        exporter.export(vec![span(1, "1")]).await.unwrap();
+        tokio::time::sleep(decision_wait / 2).await;
+        exporter.export(vec![span(2, "2")]).await.unwrap();
+        assert!(in_memory.requests().is_empty());
+
+        // no batch arrives after them, they are decided in the background
+        let spans = in_memory.wait_for_spans(2, Duration::from_secs(1)).unwrap();
+        assert_eq!(spans.len(), 2);
+        match &exporter {
+            TraceExporter::TailSampling(exporter) => assert_eq!(exporter.pending_traces(), 0),
+            _ => unreachable!(),
+        }
+
+        // it starts deciding again for later batches
+        exporter.export(vec![span(3, "3")]).await.unwrap();
+        assert_eq!(
+            in_memory
+                .wait_for_spans(3, Duration::from_secs(1))
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tail_sampling_latency_of_root() {
+        let metrics = ExporterMetrics::new();
+        let (mut exporter, in_memory) = new_exporter(
+            TailSampling::default().add_policy(SamplingPolicy::Latency {
+                threshold: Duration::from_secs(1),
+            }),
+            &metrics,
+        );
+        let child = |trace_id, name, parent: &SpanData| {
+            let mut child = span(trace_id, name);
+            child.span_context = SpanContext::new(
+                child.span_context.trace_id(),
+                SpanId::from_bytes(2u64.to_be_bytes()),
+                child.span_context.trace_flags(),
+                false,
+                Default::default(),
+            );
+            child.parent_span_id = parent.span_context.span_id();
+            child
+        };
+
+        // the root is fast, a child ending after it doesn't make the trace slow
+        let fast = span(1, "fast");
+        let mut outliving = child(1, "fast.outliving", &fast);
+        outliving.end_time = fast.start_time + Duration::from_secs(2);
+        let mut slow = span(2, "slow");
+        slow.end_time = slow.start_time + Duration::from_secs(2);
+        let quick = child(2, "slow.quick", &slow);
+        // without the root, the span whose parent is missing is the root
+        let mut orphan = child(3, "orphan", &fast);
+        orphan.end_time = orphan.start_time + Duration::from_secs(2);
+        exporter
+            .export(vec![fast, outliving, slow, quick, orphan])
+            .await
+            .unwrap();
+        exporter.force_flush().await.unwrap();
+        assert_eq!(names(&in_memory), ["orphan", "slow", "slow.quick"]);
+    }
+
+    #[tokio::test]
+    async fn test_tail_sampling_shutdown_in_runtime() {
+        let metrics = ExporterMetrics::new();
+        let (mut exporter, in_memory) = new_exporter(
+            TailSampling::default().add_policy(SamplingPolicy::Probabilistic { ratio: 1.0 }),
+            &metrics,
+        );
+        exporter.export(vec![span(1, "flushed")]).await.unwrap();
+        exporter.force_flush().await.unwrap();
+        exporter.export(vec![span(2, "buffered")]).await.unwrap();
+
+        // it returns at once without blocking the runtime, the buffered trace is still exported
+        let start = std::time::Instant::now();
+        exporter.shutdown();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        in_memory.wait_for_spans(2, Duration::from_secs(1)).unwrap();
+        assert_eq!(names(&in_memory), ["buffered", "flushed"]);
+        match &exporter {
+            TraceExporter::TailSampling(exporter) => assert_eq!(exporter.pending_traces(), 0),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    future::Future,
+    sync::{Condvar, Mutex, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{self, BoxFuture},
+    FutureExt,
+};
+
+/// A waiting future, ordered by its deadline.
+struct Deadline(Instant, oneshot::Sender<()>);
+
+impl PartialEq for Deadline {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Deadline {}
+
+impl PartialOrd for Deadline {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Deadline {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+/// A single thread waking futures of all exporters at their deadlines, exporters don't depend
+/// on a specific async runtime.
+#[derive(Default)]
+struct Timer {
+    deadlines: Mutex<BinaryHeap<Reverse<Deadline>>>,
+    changed: Condvar,
+}
+
+impl Timer {
+    /// The timer thread is started on the first call, `None` if it fails to start.
+    fn get() -> Option<&'static Timer> {
+        static TIMER: OnceLock<Timer> = OnceLock::new();
+        static STARTED: OnceLock<bool> = OnceLock::new();
+        let timer = TIMER.get_or_init(Default::default);
+        let started = STARTED.get_or_init(|| {
+            thread::Builder::new()
+                .name("otlp-exporter-timer".to_owned())
+                .spawn(|| timer.run())
+                .is_ok()
+        });
+        started.then_some(timer)
+    }
+
+    fn run(&self) {
+        let mut deadlines = self.deadlines.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let now = Instant::now();
+            while deadlines
+                .peek()
+                .is_some_and(|Reverse(deadline)| deadline.0 <= now)
+            {
+                if let Some(Reverse(Deadline(_, tx))) = deadlines.pop() {
+                    let _ = tx.send(());
+                }
+            }
+            deadlines = match deadlines.peek() {
+                Some(Reverse(deadline)) => {
+                    let timeout = deadline.0 - now;
+                    self.changed
+                        .wait_timeout(deadlines, timeout)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self
+                    .changed
+                    .wait(deadlines)
+                    .unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+/// Resolve after `duration`, or immediately if the timer thread fails to start.
+pub(super) fn sleep(duration: Duration) -> BoxFuture<'static, ()> {
+    let Some(timer) = Timer::get() else {
+        return future::ready(()).boxed();
+    };
+    let (tx, rx) = oneshot::channel();
+    timer
+        .deadlines
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Reverse(Deadline(Instant::now() + duration, tx)));
+    timer.changed.notify_one();
+    rx.map(drop).boxed()
+}
+
+/// Run `future` in the background without waiting for it, in a task inside a tokio runtime,
+/// otherwise on its own thread.
+pub(super) fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => drop(handle.spawn(future)),
+        Err(_) => {
+            let _ = thread::Builder::new()
+                .name("otlp-exporter-background".to_owned())
+                .spawn(move || futures::executor::block_on(future));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::sleep;
+
+    #[test]
+    fn test_sleep() {
+        let start = Instant::now();
+        // deadlines are woken in order, whatever order they are added in
+        let long = sleep(Duration::from_millis(100));
+        let short = sleep(Duration::from_millis(10));
+        futures::executor::block_on(short);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        futures::executor::block_on(long);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use opentelemetry_api::{global, trace::TraceError};
+
+use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    in_flight: usize,
+    shut_down: bool,
+}
+
+/// Track in-flight exports of an exporter, so that shutdown can wait for them.
+#[derive(Debug, Default)]
+pub(crate) struct ExportTracker {
+    state: Mutex<TrackerState>,
+    drained: Condvar,
+}
+
+impl ExportTracker {
+    /// Start an export, it fails after shutdown. The export is finished once the returned guard
+    /// is dropped.
+    pub(crate) fn start(self: &Arc<Self>) -> OtlpExporterResult<InFlight> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.shut_down {
+            return Err(shut_down_error());
+        }
+        state.in_flight += 1;
+        Ok(InFlight(self.clone()))
+    }
+
+    pub(crate) fn is_shut_down(&self) -> bool {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .shut_down
+    }
+
+    /// Reject further exports, and wait for in-flight ones until `timeout`. In-flight exports
+    /// which are not finished in time are reported through the global error handler.
+    ///
+    /// Inside a tokio runtime, e.g. in the task of the batch span processor, it doesn't wait,
+    /// since blocking the thread may stop the runtime from driving the in-flight exports. They
+    /// keep running on the runtime with their own clients.
+    pub(crate) fn shutdown(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.shut_down = true;
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return;
+        }
+        while state.in_flight > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                global::handle_error(TraceError::from(OtlpExporterError::ShutdownError(format!(
+                    "{} in-flight exports are not finished in {timeout:?}",
+                    state.in_flight
+                ))));
+                return;
+            }
+            state = self
+                .drained
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+/// An in-flight export.
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<ExportTracker>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.in_flight -= 1;
+        if state.in_flight == 0 {
+            self.0.drained.notify_all();
+        }
+    }
+}
+
+pub(crate) fn shut_down_error() -> OtlpExporterError {
+    OtlpExporterError::ShutdownError("exporter is already shut down".to_owned())
+}
@@ -0,0 +1,283 @@
+use futures::future::BoxFuture;
+use opentelemetry_api::{
+    trace::{Event, Link, SpanContext},
+    Array, KeyValue, StringValue, Value,
+};
+use opentelemetry_sdk::{
+    export::trace::{ExportResult, SpanData, SpanExporter},
+    trace::{EvictedHashMap, EvictedQueue},
+};
+
+use crate::config::ExportLimits;
+
+use super::{
+    redaction::{rebuild_map, rebuild_queue},
+    TraceExporter,
+};
+
+/// An exporter which truncates attributes, events and links of spans over [`ExportLimits`]
+/// before they are encoded. Removed items are counted as dropped ones, span attributes are kept in
+/// order of their keys.
+#[derive(Debug)]
+pub struct TruncatingTraceExporter {
+    inner: Box<TraceExporter>,
+    limits: ExportLimits,
+}
+
+impl TruncatingTraceExporter {
+    pub(crate) fn new(inner: TraceExporter, limits: ExportLimits) -> Self {
+        Self {
+            inner: Box::new(inner),
+            limits,
+        }
+    }
+
+    pub fn inner(&self) -> &TraceExporter {
+        &self.inner
+    }
+
+    pub fn limits(&self) -> &ExportLimits {
+        &self.limits
+    }
+
+    pub(super) fn shutdown(&mut self) {
+        self.inner.shutdown();
+    }
+
+    pub(super) fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let batch = batch
+            .into_iter()
+            .map(|span| self.truncate_span(span))
+            .collect();
+        self.inner.export(batch)
+    }
+
+    fn truncate_span(&self, mut span: SpanData) -> SpanData {
+        let value_length = self.limits.attribute_value_length();
+
+        let attributes = std::mem::replace(&mut span.attributes, EvictedHashMap::new(0, 0));
+        let dropped = attributes.dropped_count();
+        // the map iterates in hash order, sort it so that the same attributes are kept every time
+        let mut attributes: Vec<_> = attributes
+            .into_iter()
+            .map(|(key, value)| KeyValue { key, value })
+            .collect();
+        attributes.sort_by(|a, b| a.key.as_str().cmp(b.key.as_str()));
+        let (attributes, removed) =
+            truncate_attributes(attributes, self.limits.attribute_count(), value_length);
+        span.attributes = rebuild_map(attributes, dropped + removed);
+
+        let events = std::mem::replace(&mut span.events, EvictedQueue::new(0));
+        let dropped = events.dropped_count();
+        let (events, removed) =
+            keep_latest(events.into_iter().collect(), self.limits.event_count());
+        let events = events
+            .into_iter()
+            .map(|mut event| {
+                let (attributes, removed) = truncate_attributes(
+                    event.attributes,
+                    self.limits.event_attribute_count(),
+                    value_length,
+                );
+                event.attributes = attributes;
+                event.dropped_attributes_count += removed;
+                event
+            })
+            .collect();
+        span.events = rebuild_queue(events, dropped + removed, || Event::with_name(""));
+
+        let links = std::mem::replace(&mut span.links, EvictedQueue::new(0));
+        let dropped = links.dropped_count();
+        let (links, removed) = keep_latest(links.into_iter().collect(), self.limits.link_count());
+        let links = links
+            .into_iter()
+            .map(|mut link| {
+                let (attributes, removed) = truncate_attributes(
+                    link.attributes,
+                    self.limits.link_attribute_count(),
+                    value_length,
+                );
+                link.attributes = attributes;
+                link.dropped_attributes_count += removed;
+                link
+            })
+            .collect();
+        span.links = rebuild_queue(links, dropped + removed, || {
+            Link::new(SpanContext::empty_context(), Vec::new())
+        });
+        span
+    }
+}
+
+impl From<TruncatingTraceExporter> for TraceExporter {
+    fn from(exporter: TruncatingTraceExporter) -> Self {
+        TraceExporter::Truncating(exporter)
+    }
+}
+
+/// Return the first `count` attributes with values truncated to `value_length`, and the number
+/// of removed ones.
+fn truncate_attributes(
+    mut attributes: Vec<KeyValue>,
+    count: Option<usize>,
+    value_length: Option<usize>,
+) -> (Vec<KeyValue>, u32) {
+    let len = attributes.len();
+    if let Some(count) = count {
+        attributes.truncate(count);
+    }
+    if let Some(value_length) = value_length {
+        for attribute in &mut attributes {
+            truncate_value(&mut attribute.value, value_length);
+        }
+    }
+    let removed = (len - attributes.len()) as u32;
+    (attributes, removed)
+}
+
+/// Keep the last `count` items like the SDK, which evicts the oldest ones, and return the number
+/// of removed ones.
+fn keep_latest<T>(mut items: Vec<T>, count: Option<usize>) -> (Vec<T>, u32) {
+    let removed = count.map_or(0, |count| items.len().saturating_sub(count));
+    items.drain(..removed);
+    (items, removed as u32)
+}
+
+fn truncate_value(value: &mut Value, length: usize) {
+    match value {
+        Value::String(s) => truncate_string(s, length),
+        Value::Array(Array::String(strings)) => {
+            for s in strings {
+                truncate_string(s, length);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Truncate `s` to `length` characters.
+fn truncate_string(s: &mut StringValue, length: usize) {
+    if let Some((idx, _)) = s.as_str().char_indices().nth(length) {
+        *s = s.as_str()[..idx].to_owned().into();
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use opentelemetry_api::{
+        trace::{Event, Link, SpanContext},
+        Array, KeyValue, StringValue, Value,
+    };
+    use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, ArrayValue};
+    use opentelemetry_sdk::export::trace::SpanExporter;
+
+    use super::TruncatingTraceExporter;
+    use crate::{
+        config::ExportLimits,
+        exporter::trace::TraceExporter,
+        testing::{test_span, InMemoryTraceExporter},
+    };
+
+    fn string_value(s: &str) -> AnyValue {
+        AnyValue {
+            value: Some(any_value::Value::StringValue(s.to_owned())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncation() {
+        let in_memory = InMemoryTraceExporter::default();
+        let mut exporter = TraceExporter::from(TruncatingTraceExporter::new(
+            in_memory.clone().into(),
+            ExportLimits::default()
+                .with_attribute_value_length(3)
+                .with_attribute_count(1)
+                .with_event_count(1)
+                .with_event_attribute_count(0)
+                .with_link_count(2)
+                .with_link_attribute_count(1),
+        ));
+
+        let mut span = test_span("span");
+        span.attributes.insert(KeyValue::new(
+            "db.statement",
+            Value::Array(Array::String(vec![
+                StringValue::from("SELECT"),
+                StringValue::from("日本語です"),
+            ])),
+        ));
+        span.events.extend(["first", "second"].map(|name| {
+            Event::new(
+                name,
+                std::time::SystemTime::now(),
+                vec![KeyValue::new("body", "response")],
+                1,
+            )
+        }));
+        span.links.extend([Link::new(
+            SpanContext::empty_context(),
+            vec![KeyValue::new("a", "abcd"), KeyValue::new("b", 1)],
+        )]);
+        exporter.export(vec![span.clone()]).await.unwrap();
+
+        let exported = in_memory.find_span("span").unwrap();
+        assert_eq!(exported.attributes.len(), 1);
+        assert_eq!(
+            exported.attributes[0].value,
+            Some(AnyValue {
+                value: Some(any_value::Value::ArrayValue(ArrayValue {
+                    values: vec![string_value("SEL"), string_value("日本語")],
+                })),
+            })
+        );
+        assert_eq!(exported.dropped_attributes_count, 0);
+        assert_eq!(exported.events.len(), 1);
+        assert_eq!(exported.events[0].name, "second");
+        assert!(exported.events[0].attributes.is_empty());
+        assert_eq!(exported.events[0].dropped_attributes_count, 2);
+        assert_eq!(exported.dropped_events_count, 1);
+        assert_eq!(exported.links.len(), 1);
+        assert_eq!(exported.links[0].attributes.len(), 1);
+        assert_eq!(exported.links[0].dropped_attributes_count, 1);
+        assert_eq!(exported.dropped_links_count, 0);
+
+        // attributes over the count are dropped on top of the ones dropped by the SDK
+        span.attributes.insert(KeyValue::new("http.method", "GET"));
+        exporter.export(vec![span]).await.unwrap();
+        let exported = &in_memory.spans()[1];
+        assert_eq!(exported.attributes.len(), 1);
+        assert_eq!(exported.dropped_attributes_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_truncation_keeps_first_keys() {
+        let in_memory = InMemoryTraceExporter::default();
+        let mut exporter = TraceExporter::from(TruncatingTraceExporter::new(
+            in_memory.clone().into(),
+            ExportLimits::default().with_attribute_count(3),
+        ));
+
+        let mut span = test_span("span");
+        for key in [
+            "http.url",
+            "db.system",
+            "net.peer.name",
+            "code.function",
+            "http.method",
+        ] {
+            span.attributes.insert(KeyValue::new(key, "value"));
+        }
+        exporter.export(vec![span]).await.unwrap();
+
+        let exported = in_memory.find_span("span").unwrap();
+        // the kept attributes are put back into a map, so they are exported in hash order
+        let mut keys: Vec<_> = exported
+            .attributes
+            .iter()
+            .map(|kv| kv.key.as_str())
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["code.function", "db.system", "http.method"]);
+        assert_eq!(exported.dropped_attributes_count, 2);
+    }
+}
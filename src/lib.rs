@@ -4,7 +4,30 @@ pub mod config;
 pub mod error;
 mod exporter;
 mod pipeline;
+#[cfg(feature = "testing")]
+pub mod testing;
 
+pub use exporter::self_metrics::{ExporterMetrics, ExporterMetricsSnapshot};
+#[cfg(all(feature = "traces", feature = "metrics"))]
+pub use exporter::span_metrics::SpanMetrics;
+#[cfg(feature = "traces")]
+pub use exporter::suppression::SuppressionSampler;
+pub use exporter::suppression::{is_telemetry_suppressed, SuppressTelemetry};
+#[cfg(all(feature = "traces", feature = "grpcio"))]
+pub use exporter::trace::GrpcioTraceExporter;
+#[cfg(all(feature = "traces", feature = "http"))]
+pub use exporter::trace::HttpTraceExporter;
+#[cfg(all(feature = "traces", feature = "metrics"))]
+pub use exporter::trace::SpanMetricsTraceExporter;
+#[cfg(all(feature = "traces", feature = "tonic"))]
+pub use exporter::trace::TonicTraceExporter;
+#[cfg(feature = "traces")]
+pub use exporter::trace::{
+    CircuitBreakerTraceExporter, CircuitState, FailoverTraceExporter, FanOutPolicy,
+    FanOutTraceExporter, LoadBalancing, LoadBalancingTraceExporter, RateLimitedTraceExporter,
+    RedactingTraceExporter, RoutingKey, TailSamplingTraceExporter, TraceExporter,
+    TruncatingTraceExporter, DEFAULT_RESOLVE_INTERVAL,
+};
 #[cfg(feature = "metrics")]
 pub use pipeline::metric::MetricPipeline;
 #[cfg(feature = "traces")]
@@ -1,7 +1,7 @@
 use std::mem;
 
 use crate::{
-    config::{ConfigBuilder, DataType},
+    config::{ConfigBuilder, DataType, EnvSource, ProcessEnv},
     Pipeline,
 };
 
@@ -10,8 +10,15 @@ pub struct MetricPipeline {
 }
 
 impl MetricPipeline {
-    pub fn with_env(mut self) -> MetricPipeline {
-        self.config_builder = self.config_builder.with_env(Some(DataType::Metric));
+    pub fn with_env(self) -> MetricPipeline {
+        self.with_source(&ProcessEnv)
+    }
+
+    /// Like [`MetricPipeline::with_env`], but read from `source` instead of the process env.
+    pub fn with_source(mut self, source: &(impl EnvSource + ?Sized)) -> MetricPipeline {
+        self.config_builder = self
+            .config_builder
+            .with_source(source, Some(DataType::Metric));
         self
     }
 
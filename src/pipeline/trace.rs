@@ -1,29 +1,80 @@
-use std::mem;
+use std::{borrow::Cow, mem};
 
-use opentelemetry_api::global;
+use opentelemetry_api::{global, Key, KeyValue};
 use opentelemetry_sdk::{
+    propagation::TextMapCompositePropagator,
     runtime::RuntimeChannel,
     trace::{
-        BatchMessage, Builder as TracerProviderBuilder, Config as TracerConfig, Tracer,
-        TracerProvider,
+        BatchConfig, BatchMessage, BatchSpanProcessor, Builder as TracerProviderBuilder,
+        Config as TracerConfig, Sampler, Tracer, TracerProvider,
     },
+    Resource,
 };
 
 use crate::{
-    config::{ConfigBuilder, DataType, Protocol},
+    config::{self, ConfigBuilder, DataType, EnvSource, ProcessEnv},
     error::OtlpExporterResult,
-    exporter::trace::TraceExporter,
+    exporter::{
+        self_metrics::ExporterMetrics,
+        suppression::SuppressionSampler,
+        trace::{
+            FanOutPolicy, FanOutTraceExporter, LoadBalancing, LoadBalancingTraceExporter,
+            TraceExporter,
+        },
+    },
     Pipeline,
 };
 
 pub struct TracePipeline {
     config_builder: ConfigBuilder,
+    load_balancing: Option<LoadBalancing>,
+    destinations: Vec<ConfigBuilder>,
+    fan_out_policy: FanOutPolicy,
     tracer_config: Option<TracerConfig>,
+    /// Resource from env, it is overridden by the resource of `tracer_config`.
+    env_resource: Option<Resource>,
+    /// Sampler from env, it is used if `tracer_config` is not set.
+    env_sampler: Option<Sampler>,
+    /// Installed as the global propagator.
+    env_propagator: Option<TextMapCompositePropagator>,
+    batch_config: Option<BatchConfig>,
+    /// If it is true, no exporter is built, spans are not recorded and the propagator is not
+    /// installed.
+    disabled: bool,
+    /// Name of the tracer returned by `install_*`, it is the crate name if it is not set.
+    tracer_name: Option<Cow<'static, str>>,
+    tracer_version: Option<Cow<'static, str>>,
+    schema_url: Option<Cow<'static, str>>,
+    /// Set to the config builder and each destination.
+    metrics: Option<ExporterMetrics>,
+    #[cfg(feature = "testing")]
+    in_memory_exporter: Option<crate::testing::InMemoryTraceExporter>,
 }
 
 impl TracePipeline {
-    pub fn with_env(mut self) -> Self {
-        self.config_builder = self.config_builder.with_env(Some(DataType::Trace));
+    /// Read exporter settings, `OTEL_SDK_DISABLED`, `OTEL_SERVICE_NAME`,
+    /// `OTEL_RESOURCE_ATTRIBUTES`, `OTEL_BSP_*`, `OTEL_TRACES_SAMPLER` and `OTEL_PROPAGATORS` from
+    /// env. The propagator of `OTEL_PROPAGATORS` is installed globally by `install_*` unless
+    /// `OTEL_SDK_DISABLED` is true.
+    pub fn with_env(self) -> Self {
+        self.with_source(&ProcessEnv)
+    }
+
+    /// Like [`TracePipeline::with_env`], but read from `source` instead of the process env.
+    pub fn with_source(mut self, source: &(impl EnvSource + ?Sized)) -> Self {
+        let mut diagnostics = Vec::new();
+        self.disabled |= config::sdk_disabled_from_source(source);
+        self.env_resource = Some(config::resource_from_source(source));
+        self.env_sampler = config::sampler_with_diagnostics(source, &mut diagnostics);
+        self.env_propagator = config::propagator_from_source(source);
+        self.batch_config = Some(config::batch_config_with_diagnostics(
+            source,
+            self.batch_config.take().unwrap_or_default(),
+            &mut diagnostics,
+        ));
+        self.config_builder = mem::take(&mut self.config_builder)
+            .with_source(source, Some(DataType::Trace))
+            .with_diagnostics(diagnostics);
         self
     }
 
@@ -41,34 +92,170 @@ impl TracePipeline {
         self
     }
 
+    /// Balance spans across a pool of collectors, the config of
+    /// [`TracePipeline::with_config_builder`] is used as the template of each collector.
+    pub fn with_load_balancing(mut self, load_balancing: LoadBalancing) -> Self {
+        self.load_balancing = Some(load_balancing);
+        self
+    }
+
+    /// Send each batch to the destination of `config_builder` too. The destination of
+    /// [`TracePipeline::with_config_builder`] is the primary one.
+    pub fn add_destination(mut self, config_builder: ConfigBuilder) -> Self {
+        self.destinations.push(config_builder);
+        self
+    }
+
+    /// Decide whether a batch is exported successfully when there are multiple destinations.
+    pub fn with_fan_out_policy(mut self, fan_out_policy: FanOutPolicy) -> Self {
+        self.fan_out_policy = fan_out_policy;
+        self
+    }
+
+    /// Settings of the batch span processor used by [`TracePipeline::install_batch`].
+    pub fn with_batch_config(mut self, batch_config: BatchConfig) -> Self {
+        self.batch_config = Some(batch_config);
+        self
+    }
+
     pub fn with_tracer_config(mut self, tracer_config: TracerConfig) -> Self {
         self.tracer_config = Some(tracer_config);
         self
     }
 
-    fn install(
+    /// Name of the tracer returned by `install_*`, the crate name and version of otlp-exporter
+    /// are used by default.
+    pub fn with_tracer_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.tracer_name = Some(name.into());
+        self
+    }
+
+    /// Version of the tracer returned by `install_*`. If the name is set by
+    /// [`TracePipeline::with_tracer_name`], there is no version by default.
+    pub fn with_tracer_version(mut self, version: impl Into<Cow<'static, str>>) -> Self {
+        self.tracer_version = Some(version.into());
+        self
+    }
+
+    /// Schema url of the tracer returned by `install_*`.
+    pub fn with_schema_url(mut self, schema_url: impl Into<Cow<'static, str>>) -> Self {
+        self.schema_url = Some(schema_url.into());
+        self
+    }
+
+    /// Record self-observability metrics of all exporters into `metrics`, including additional
+    /// destinations.
+    pub fn with_metrics(mut self, metrics: ExporterMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record RED metrics of spans into `span_metrics`, spans are counted once even if there are
+    /// additional destinations.
+    #[cfg(feature = "metrics")]
+    pub fn with_span_metrics(mut self, span_metrics: crate::SpanMetrics) -> Self {
+        self.config_builder = mem::take(&mut self.config_builder).with_span_metrics(span_metrics);
+        self
+    }
+
+    #[cfg(feature = "_config-file")]
+    fn with_trace_settings(mut self, settings: crate::config::file::TraceSettings) -> Self {
+        let mut config_builders = settings.config_builders.into_iter();
+        if let Some(config_builder) = config_builders.next() {
+            self.config_builder = config_builder;
+        }
+        self.destinations = config_builders.collect();
+        self.tracer_config = Some(settings.tracer_config);
+        self.batch_config = settings.batch_config;
+        self.disabled = settings.disabled;
+        self
+    }
+
+    /// Export spans to `exporter` instead of the endpoint in the config, nothing will be sent
+    /// over the network.
+    #[cfg(feature = "testing")]
+    pub fn with_in_memory_exporter(
+        mut self,
+        exporter: crate::testing::InMemoryTraceExporter,
+    ) -> Self {
+        self.in_memory_exporter = Some(exporter);
+        self
+    }
+
+    fn build(
         self,
-        builder_creator: impl FnOnce(
-            Protocol,
-            TraceExporter,
-        ) -> OtlpExporterResult<TracerProviderBuilder>,
-    ) -> OtlpExporterResult<Tracer> {
+        builder_creator: impl FnOnce(TraceExporter) -> OtlpExporterResult<TracerProviderBuilder>,
+    ) -> OtlpExporterResult<TracerProvider> {
         let Self {
-            config_builder,
+            mut config_builder,
+            load_balancing,
+            mut destinations,
+            fan_out_policy,
             tracer_config,
+            env_resource,
+            env_sampler,
+            env_propagator: _,
+            batch_config: _,
+            disabled,
+            tracer_name: _,
+            tracer_version: _,
+            schema_url: _,
+            metrics,
+            #[cfg(feature = "testing")]
+            in_memory_exporter,
         } = self;
-        let config = config_builder.build()?;
-        let mut builder = builder_creator(config.protocol(), TryFrom::try_from(config)?)?;
-        if let Some(tracer_config) = tracer_config {
-            builder = builder.with_config(tracer_config);
+        if let Some(metrics) = metrics {
+            destinations = destinations
+                .into_iter()
+                .map(|destination| destination.with_metrics(metrics.clone()))
+                .collect();
+            config_builder = config_builder.with_metrics(metrics);
+        }
+        if disabled {
+            // neither an exporter nor a span processor is built, spans are not recorded at all
+            return Ok(TracerProvider::builder()
+                .with_config(TracerConfig::default().with_sampler(Sampler::AlwaysOff))
+                .build());
+        }
+        #[cfg(feature = "testing")]
+        let exporter = match in_memory_exporter {
+            Some(exporter) => exporter.into(),
+            None => build_exporter(config_builder, load_balancing, destinations, fan_out_policy)?,
+        };
+        #[cfg(not(feature = "testing"))]
+        let exporter =
+            build_exporter(config_builder, load_balancing, destinations, fan_out_policy)?;
+        let mut builder = builder_creator(exporter)?;
+        let mut tracer_config =
+            merge_tracer_config(tracer_config, env_resource, env_sampler).unwrap_or_default();
+        // spans of instrumented clients called by the exporter are dropped
+        tracer_config.sampler = Box::new(SuppressionSampler::new(tracer_config.sampler));
+        builder = builder.with_config(tracer_config);
+        Ok(builder.build())
+    }
+
+    fn install(
+        mut self,
+        builder_creator: impl FnOnce(TraceExporter) -> OtlpExporterResult<TracerProviderBuilder>,
+    ) -> OtlpExporterResult<Tracer> {
+        let propagator = self.env_propagator.take();
+        let (name, version) = match self.tracer_name.take() {
+            Some(name) => (name, self.tracer_version.take()),
+            None => (
+                Cow::Borrowed(env!("CARGO_PKG_NAME")),
+                self.tracer_version
+                    .take()
+                    .or(Some(Cow::Borrowed(env!("CARGO_PKG_VERSION")))),
+            ),
+        };
+        let schema_url = self.schema_url.take();
+        let disabled = self.disabled;
+        let provider = self.build(builder_creator)?;
+        if let Some(propagator) = propagator.filter(|_| !disabled) {
+            global::set_text_map_propagator(propagator);
         }
-        let provider = builder.build();
         let tracer = opentelemetry_api::trace::TracerProvider::versioned_tracer(
-            &provider,
-            env!("CARGO_PKG_NAME"),
-            Some(env!("CARGO_PKG_VERSION")),
-            None::<&'static str>,
-            None,
+            &provider, name, version, schema_url, None,
         );
         global::set_tracer_provider(provider);
         Ok(tracer)
@@ -76,39 +263,359 @@ impl TracePipeline {
 
     /// build the tracer
     pub fn install_simple(self) -> OtlpExporterResult<Tracer> {
-        self.install(|protocol, exporter| {
-            match protocol {
-                #[cfg(feature = "http")]
-                Protocol::HttpProtobuf => {
-                    let _ = exporter;
-                    Err(crate::error::OtlpExporterError::Unsupported(format!("install_simple can't be worked with http/protobuf, use install_batch with tokio instead")))
-                },
-                #[cfg(feature = "http-json")]
-                Protocol::HttpJson => {
-                    let _ = exporter;
-                    Err(crate::error::OtlpExporterError::Unsupported(format!("install_simple can't be worked with http/json, use install_batch with tokio instead")))
-                },
-                #[cfg(feature = "_grpc")]
-                _ => Ok(TracerProvider::builder().with_simple_exporter(exporter)),
-            }
-        })
+        self.install(simple_builder)
     }
 
     pub fn install_batch<R: RuntimeChannel<BatchMessage>>(
-        self,
+        mut self,
         runtime: R,
     ) -> OtlpExporterResult<Tracer> {
-        self.install(move |_, exporter| {
-            Ok(TracerProvider::builder().with_batch_exporter(exporter, runtime))
-        })
+        let batch_config = self.batch_config.take();
+        self.install(move |exporter| batch_builder(exporter, runtime, batch_config))
+    }
+
+    /// Build a tracer provider with a batch span processor, like
+    /// [`TracePipeline::install_batch`] but without global side effects: neither the tracer
+    /// provider nor the propagator of `OTEL_PROPAGATORS` is installed globally. Shut down the
+    /// provider to flush spans.
+    pub fn build_provider<R: RuntimeChannel<BatchMessage>>(
+        mut self,
+        runtime: R,
+    ) -> OtlpExporterResult<TracerProvider> {
+        let batch_config = self.batch_config.take();
+        self.build(move |exporter| batch_builder(exporter, runtime, batch_config))
+    }
+
+    /// Like [`TracePipeline::build_provider`], but with a simple span processor.
+    pub fn build_simple_provider(self) -> OtlpExporterResult<TracerProvider> {
+        self.build(simple_builder)
+    }
+}
+
+fn simple_builder(exporter: TraceExporter) -> OtlpExporterResult<TracerProviderBuilder> {
+    #[cfg(feature = "http")]
+    if let Some(protocol) = unsupported_by_simple(&exporter) {
+        return Err(crate::error::OtlpExporterError::Unsupported(format!(
+            "install_simple can't be worked with {protocol}, use install_batch with tokio instead"
+        )));
+    }
+    Ok(TracerProvider::builder().with_simple_exporter(exporter))
+}
+
+fn batch_builder<R: RuntimeChannel<BatchMessage>>(
+    exporter: TraceExporter,
+    runtime: R,
+    batch_config: Option<BatchConfig>,
+) -> OtlpExporterResult<TracerProviderBuilder> {
+    let mut processor = BatchSpanProcessor::builder(exporter, runtime);
+    if let Some(batch_config) = batch_config {
+        processor = processor.with_batch_config(batch_config);
+    }
+    Ok(TracerProvider::builder().with_span_processor(processor.build()))
+}
+
+fn merge_tracer_config(
+    tracer_config: Option<TracerConfig>,
+    env_resource: Option<Resource>,
+    env_sampler: Option<Sampler>,
+) -> Option<TracerConfig> {
+    let tracer_config = match (tracer_config, env_sampler) {
+        (None, Some(env_sampler)) => Some(TracerConfig::default().with_sampler(env_sampler)),
+        (tracer_config, _) => tracer_config,
+    };
+    match (tracer_config, env_resource) {
+        (Some(mut tracer_config), Some(env_resource)) => {
+            let resource = without_default_service_name(&tracer_config.resource);
+            tracer_config.resource = Cow::Owned(env_resource.merge(&resource));
+            Some(tracer_config)
+        }
+        (None, Some(env_resource)) => {
+            let mut tracer_config = TracerConfig::default();
+            tracer_config.resource = Cow::Owned(tracer_config.resource.merge(&env_resource));
+            Some(tracer_config)
+        }
+        (tracer_config, None) => tracer_config,
+    }
+}
+
+/// Remove `service.name` from `resource` if it is the one detected by the SDK, e.g. in
+/// `TracerConfig::default()`, so that it doesn't override the one from env.
+fn without_default_service_name(resource: &Resource) -> Resource {
+    let key = Key::from_static_str("service.name");
+    let service_name = resource.get(key.clone());
+    if service_name.is_none() || service_name != Resource::default().get(key.clone()) {
+        return resource.clone();
+    }
+    let attributes = resource
+        .iter()
+        .filter(|(k, _)| **k != key)
+        .map(|(k, v)| KeyValue::new(k.clone(), v.clone()));
+    match resource.schema_url() {
+        Some(schema_url) => Resource::from_schema_url(attributes, schema_url.to_owned()),
+        None => Resource::new(attributes),
+    }
+}
+
+fn build_exporter(
+    config_builder: ConfigBuilder,
+    load_balancing: Option<LoadBalancing>,
+    destinations: Vec<ConfigBuilder>,
+    fan_out_policy: FanOutPolicy,
+) -> OtlpExporterResult<TraceExporter> {
+    let config = config_builder.with_data_type(DataType::Trace).build()?;
+    let endpoint = config.endpoint().to_string();
+    let primary = match load_balancing {
+        // the stages apply to the pool as a whole
+        Some(load_balancing) => TraceExporter::try_with_stages(config, |config| {
+            Ok(LoadBalancingTraceExporter::try_new(config, load_balancing)?.into())
+        })?,
+        None => config.try_into()?,
+    };
+    if destinations.is_empty() {
+        return Ok(primary);
+    }
+    let destinations = std::iter::once(Ok((endpoint, primary)))
+        .chain(destinations.into_iter().map(|config_builder| {
+            let config = config_builder.with_data_type(DataType::Trace).build()?;
+            Ok((config.endpoint().to_string(), config.try_into()?))
+        }))
+        .collect::<OtlpExporterResult<_>>()?;
+    Ok(FanOutTraceExporter::try_new(destinations, fan_out_policy)?.into())
+}
+
+/// Return the protocol if `exporter` depends on tokio, which can't be used in
+/// `install_simple`.
+#[cfg(feature = "http")]
+fn unsupported_by_simple(exporter: &TraceExporter) -> Option<crate::config::Protocol> {
+    match exporter {
+        #[cfg(feature = "http")]
+        TraceExporter::Http(exporter) => Some(exporter.config().protocol()),
+        TraceExporter::FanOut(exporter) => exporter.destinations().find_map(unsupported_by_simple),
+        TraceExporter::Failover(exporter) => exporter.endpoints().find_map(unsupported_by_simple),
+        TraceExporter::LoadBalancing(exporter) => {
+            exporter.backends().find_map(unsupported_by_simple)
+        }
+        TraceExporter::RateLimited(exporter) => unsupported_by_simple(exporter.inner()),
+        TraceExporter::CircuitBreaker(exporter) => unsupported_by_simple(exporter.inner()),
+        TraceExporter::Redacting(exporter) => unsupported_by_simple(exporter.inner()),
+        TraceExporter::Truncating(exporter) => unsupported_by_simple(exporter.inner()),
+        TraceExporter::TailSampling(exporter) => unsupported_by_simple(&exporter.inner()),
+        #[cfg(feature = "metrics")]
+        TraceExporter::SpanMetrics(exporter) => unsupported_by_simple(exporter.inner()),
+        #[allow(unreachable_patterns)]
+        _ => None,
     }
 }
 
 impl Pipeline {
+    /// Load the trace pipeline from a declarative config file, see [`crate::config::file`].
+    ///
+    /// Processors should be either all batch or all simple, call
+    /// [`TracePipeline::install_batch`] or [`TracePipeline::install_simple`] accordingly. The
+    /// exporter of the first processor is the primary destination, the settings of the first
+    /// batch processor are used.
+    #[cfg(feature = "_config-file")]
+    pub fn from_config_file(
+        self,
+        path: impl AsRef<std::path::Path>,
+    ) -> OtlpExporterResult<TracePipeline> {
+        let settings = crate::config::file::load_trace_settings(path.as_ref(), &ProcessEnv)?;
+        Ok(self.trace().with_trace_settings(settings))
+    }
+
+    /// Load the trace pipeline from the file of env `OTEL_CONFIG_FILE`, `None` if it is not set.
+    #[cfg(feature = "_config-file")]
+    pub fn from_env_config_file(self) -> OtlpExporterResult<Option<TracePipeline>> {
+        let settings = crate::config::file::load_trace_settings_from_env()?;
+        Ok(settings.map(|settings| self.trace().with_trace_settings(settings)))
+    }
+
     pub fn trace(self) -> TracePipeline {
         TracePipeline {
             config_builder: Default::default(),
+            load_balancing: None,
+            destinations: Vec::new(),
+            fan_out_policy: Default::default(),
             tracer_config: None,
+            env_resource: None,
+            env_sampler: None,
+            env_propagator: None,
+            batch_config: None,
+            disabled: false,
+            tracer_name: None,
+            tracer_version: None,
+            schema_url: None,
+            metrics: None,
+            #[cfg(feature = "testing")]
+            in_memory_exporter: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_api::{Key, Value};
+    use opentelemetry_sdk::{trace::Config as TracerConfig, Resource};
+
+    use super::merge_tracer_config;
+    use crate::config::{self, OTEL_RESOURCE_ATTRIBUTES, OTEL_SDK_DISABLED, OTEL_SERVICE_NAME};
+
+    #[test]
+    fn test_resource_from_env() {
+        temp_env::with_vars(
+            [
+                (
+                    OTEL_RESOURCE_ATTRIBUTES,
+                    Some(
+                        "service.name=from-attrs,team=a%20b%2Cc,invalid,deployment.environment=dev",
+                    ),
+                ),
+                (OTEL_SERVICE_NAME, Some("from-name")),
+                (OTEL_SDK_DISABLED, Some("TRUE")),
+            ],
+            || {
+                assert!(config::sdk_disabled_from_env());
+                let env_resource = config::resource_from_env();
+                assert_eq!(env_resource.len(), 3);
+                assert_eq!(
+                    env_resource.get(Key::from_static_str("service.name")),
+                    Some(Value::from("from-name"))
+                );
+                assert_eq!(
+                    env_resource.get(Key::from_static_str("team")),
+                    Some(Value::from("a b,c"))
+                );
+
+                let user_config = TracerConfig::default().with_resource(Resource::new([
+                    opentelemetry_api::KeyValue::new("deployment.environment", "prod"),
+                ]));
+                let merged =
+                    merge_tracer_config(Some(user_config), Some(env_resource.clone()), None)
+                        .unwrap();
+                assert_eq!(
+                    merged
+                        .resource
+                        .get(Key::from_static_str("deployment.environment")),
+                    Some(Value::from("prod"))
+                );
+                assert_eq!(
+                    merged.resource.get(Key::from_static_str("service.name")),
+                    Some(Value::from("from-name"))
+                );
+
+                let env_sampler = merge_tracer_config(
+                    None,
+                    None,
+                    Some(opentelemetry_sdk::trace::Sampler::AlwaysOff),
+                )
+                .unwrap();
+                assert!(format!("{:?}", env_sampler.sampler).contains("AlwaysOff"));
+                let user_sampler =
+                    merge_tracer_config(
+                        Some(TracerConfig::default().with_sampler(
+                            opentelemetry_sdk::trace::Sampler::TraceIdRatioBased(0.5),
+                        )),
+                        None,
+                        Some(opentelemetry_sdk::trace::Sampler::AlwaysOff),
+                    )
+                    .unwrap();
+                assert!(format!("{:?}", user_sampler.sampler).contains("TraceIdRatioBased"));
+            },
+        );
+        temp_env::with_var(OTEL_SDK_DISABLED, Some("false"), || {
+            assert!(!config::sdk_disabled_from_env());
+        });
+    }
+
+    #[test]
+    fn test_service_name_of_default_tracer_config() {
+        let env_resource =
+            Resource::new([opentelemetry_api::KeyValue::new("service.name", "from-env")]);
+        // the default resource carries the service name detected by the SDK
+        let merged = merge_tracer_config(
+            Some(TracerConfig::default()),
+            Some(env_resource.clone()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            merged.resource.get(Key::from_static_str("service.name")),
+            Some(Value::from("from-env"))
+        );
+
+        let user_config = TracerConfig::default().with_resource(Resource::new([
+            opentelemetry_api::KeyValue::new("service.name", "from-user"),
+        ]));
+        let merged = merge_tracer_config(Some(user_config), Some(env_resource), None).unwrap();
+        assert_eq!(
+            merged.resource.get(Key::from_static_str("service.name")),
+            Some(Value::from("from-user"))
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_disabled() {
+        use std::collections::HashMap;
+
+        use opentelemetry_api::trace::{Tracer, TracerProvider};
+
+        use crate::{new_pipeline, testing::InMemoryTraceExporter};
+
+        let exporter = InMemoryTraceExporter::new();
+        let provider = new_pipeline()
+            .trace()
+            .with_source(&HashMap::from([(OTEL_SDK_DISABLED, "true")]))
+            .with_in_memory_exporter(exporter.clone())
+            .build_simple_provider()
+            .unwrap();
+        assert!(provider.span_processors().is_empty());
+        provider.tracer("test").in_span("disabled", |_| {});
+        assert!(exporter.spans().is_empty());
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_build_provider() {
+        use opentelemetry_api::trace::{Tracer, TracerProvider};
+
+        use crate::{new_pipeline, testing::InMemoryTraceExporter};
+
+        let exporter = InMemoryTraceExporter::new();
+        let provider = new_pipeline()
+            .trace()
+            .with_in_memory_exporter(exporter.clone())
+            .build_simple_provider()
+            .unwrap();
+        provider.tracer("test").in_span("scoped", |_| {});
+        let spans = exporter
+            .wait_for_spans(1, std::time::Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(spans[0].name, "scoped");
+    }
+
+    #[test]
+    fn test_strict_env() {
+        use std::collections::HashMap;
+
+        use crate::{
+            config::{EnvDiagnosticKind, OTEL_BSP_MAX_QUEUE_SIZE},
+            new_pipeline,
+        };
+
+        let pipeline = new_pipeline()
+            .trace()
+            .with_source(&HashMap::from([(OTEL_BSP_MAX_QUEUE_SIZE, "abc")]));
+        assert_eq!(
+            pipeline.config_builder.diagnostics()[0].kind(),
+            EnvDiagnosticKind::InvalidLimit
+        );
+        assert!(pipeline.config_builder.clone().build().is_ok());
+        let err = pipeline
+            .with_config_builder_customizer(|builder| builder.with_strict_env(true))
+            .config_builder
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains(OTEL_BSP_MAX_QUEUE_SIZE), "{err}");
+    }
+}
@@ -0,0 +1,245 @@
+//! Helpers for asserting telemetry exported through the pipeline in tests.
+//!
+//! [`InMemoryTraceExporter`] records the exact requests the network exporters would send, so
+//! tests can check emitted spans without running a collector.
+
+use std::{
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use opentelemetry_api::Value;
+use opentelemetry_proto::tonic::{
+    collector::trace::v1::ExportTraceServiceRequest, common::v1::AnyValue, trace::v1::Span,
+};
+use opentelemetry_sdk::export::trace::SpanData;
+
+use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+#[cfg(feature = "mock-collector")]
+pub mod collector;
+
+#[derive(Default)]
+struct Records {
+    requests: Mutex<Vec<ExportTraceServiceRequest>>,
+    cond: Condvar,
+}
+
+/// An exporter which keeps every `ExportTraceServiceRequest` in memory instead of sending it.
+///
+/// It is cheap to clone, all clones share the same records. Pass one clone to
+/// [`TracePipeline::with_in_memory_exporter`](crate::TracePipeline::with_in_memory_exporter) and
+/// keep another one for assertions.
+#[derive(Clone, Default)]
+pub struct InMemoryTraceExporter {
+    records: Arc<Records>,
+}
+
+impl InMemoryTraceExporter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<ExportTraceServiceRequest>> {
+        // a panic in another test thread should not hide the records
+        self.records
+            .requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn gen_request(batch: Vec<SpanData>) -> ExportTraceServiceRequest {
+        ExportTraceServiceRequest {
+            resource_spans: batch.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub(crate) fn record(&self, request: ExportTraceServiceRequest) {
+        self.lock().push(request);
+        self.records.cond.notify_all();
+    }
+
+    /// All requests recorded so far, in export order.
+    pub fn requests(&self) -> Vec<ExportTraceServiceRequest> {
+        self.lock().clone()
+    }
+
+    /// All spans recorded so far, in export order.
+    pub fn spans(&self) -> Vec<Span> {
+        collect_spans(&self.lock())
+    }
+
+    /// Remove all records.
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    /// Find the first exported span with `name`.
+    pub fn find_span(&self, name: &str) -> Option<Span> {
+        self.spans().into_iter().find(|span| span.name == name)
+    }
+
+    /// Block until at least `count` spans are recorded, or return an error after `timeout`.
+    pub fn wait_for_spans(&self, count: usize, timeout: Duration) -> OtlpExporterResult<Vec<Span>> {
+        let deadline = Instant::now() + timeout;
+        let mut requests = self.lock();
+        loop {
+            let spans = collect_spans(&requests);
+            if spans.len() >= count {
+                return Ok(spans);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(OtlpExporterError::UnknownError(format!(
+                    "timed out after {timeout:?} waiting for {count} spans, got {}",
+                    spans.len()
+                )));
+            }
+            requests = self
+                .records
+                .cond
+                .wait_timeout(requests, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Assert the span named `name` is exported and has an attribute `key` equal to `value`.
+    pub fn assert_span_attribute(&self, name: &str, key: &str, value: impl Into<Value>) {
+        let span = self
+            .find_span(name)
+            .unwrap_or_else(|| panic!("span[{name}] is not exported"));
+        let expected = AnyValue::from(value.into());
+        match span_attribute(&span, key) {
+            Some(actual) => assert_eq!(
+                actual, &expected,
+                "attribute[{key}] of span[{name}] mismatched"
+            ),
+            None => panic!("attribute[{key}] is not found in span[{name}]"),
+        }
+    }
+
+    /// Assert both spans are exported and `child` is a direct child of `parent`.
+    pub fn assert_child_of(&self, child: &str, parent: &str) {
+        let child_span = self
+            .find_span(child)
+            .unwrap_or_else(|| panic!("span[{child}] is not exported"));
+        let parent_span = self
+            .find_span(parent)
+            .unwrap_or_else(|| panic!("span[{parent}] is not exported"));
+        assert_eq!(
+            child_span.trace_id, parent_span.trace_id,
+            "span[{child}] and span[{parent}] are in different traces"
+        );
+        assert_eq!(
+            child_span.parent_span_id, parent_span.span_id,
+            "span[{child}] is not a child of span[{parent}]"
+        );
+    }
+}
+
+impl fmt::Debug for InMemoryTraceExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryTraceExporter")
+            .field("requests", &self.lock().len())
+            .finish()
+    }
+}
+
+fn collect_spans(requests: &[ExportTraceServiceRequest]) -> Vec<Span> {
+    requests
+        .iter()
+        .flat_map(|request| &request.resource_spans)
+        .flat_map(|resource_spans| &resource_spans.scope_spans)
+        .flat_map(|scope_spans| scope_spans.spans.iter().cloned())
+        .collect()
+}
+
+/// Get the value of attribute `key` of `span`.
+pub fn span_attribute<'a>(span: &'a Span, key: &str) -> Option<&'a AnyValue> {
+    span.attributes
+        .iter()
+        .find(|kv| kv.key == key)
+        .and_then(|kv| kv.value.as_ref())
+}
+
+/// Generate a sampled span named `name` for tests.
+#[cfg(test)]
+pub(crate) fn test_span(name: &'static str) -> SpanData {
+    use std::{borrow::Cow, time::SystemTime};
+
+    use opentelemetry_api::trace::{
+        SpanContext, SpanId, SpanKind, Status, TraceFlags, TraceId, TraceState,
+    };
+    use opentelemetry_sdk::{
+        trace::{EvictedHashMap, EvictedQueue},
+        InstrumentationLibrary, Resource,
+    };
+
+    SpanData {
+        span_context: SpanContext::new(
+            TraceId::from_bytes(1u128.to_be_bytes()),
+            SpanId::from_bytes(1u64.to_be_bytes()),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        ),
+        parent_span_id: SpanId::INVALID,
+        span_kind: SpanKind::Internal,
+        name: name.into(),
+        start_time: SystemTime::now(),
+        end_time: SystemTime::now(),
+        attributes: EvictedHashMap::new(128, 0),
+        events: EvictedQueue::new(128),
+        links: EvictedQueue::new(128),
+        status: Status::Unset,
+        resource: Cow::Owned(Resource::empty()),
+        instrumentation_lib: InstrumentationLibrary::new(
+            "otlp-exporter-test",
+            None::<&'static str>,
+            None::<&'static str>,
+            None,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use opentelemetry_api::trace::{TraceContextExt, Tracer};
+
+    use super::InMemoryTraceExporter;
+
+    #[test]
+    fn test_in_memory_exporter() {
+        let exporter = InMemoryTraceExporter::new();
+        let tracer = crate::new_pipeline()
+            .trace()
+            .with_in_memory_exporter(exporter.clone())
+            .install_simple()
+            .unwrap();
+
+        tracer.in_span("parent", |cx| {
+            cx.span()
+                .set_attribute(opentelemetry_api::KeyValue::new("key", "value"));
+            tracer.in_span("child", |_cx| {});
+        });
+
+        let spans = exporter.wait_for_spans(2, Duration::from_secs(5)).unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(exporter.requests().len(), 2);
+        exporter.assert_span_attribute("parent", "key", "value");
+        exporter.assert_child_of("child", "parent");
+        assert!(exporter.find_span("missing").is_none());
+        assert!(exporter
+            .wait_for_spans(3, Duration::from_millis(10))
+            .is_err());
+
+        exporter.reset();
+        assert!(exporter.spans().is_empty());
+
+        opentelemetry_api::global::shutdown_tracer_provider();
+    }
+}
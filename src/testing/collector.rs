@@ -0,0 +1,828 @@
+//! A mock OTLP collector listening on localhost.
+//!
+//! It serves OTLP/gRPC and OTLP/HTTP(protobuf and json), records every request it receives and
+//! can be told to fail, delay or partially accept requests, so the exporters can be tested end to
+//! end without a real collector.
+
+use std::{
+    convert::Infallible,
+    fmt,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use futures::Stream;
+use http::{header::CONTENT_TYPE, HeaderMap, Method, StatusCode};
+use hyper::{server::conn::Http, service::service_fn, Body};
+use opentelemetry_proto::tonic::{
+    collector::trace::v1::{
+        trace_service_server::{TraceService, TraceServiceServer},
+        ExportTracePartialSuccess, ExportTraceServiceRequest, ExportTraceServiceResponse,
+    },
+    trace::v1::Span,
+};
+use prost::Message;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tonic::transport::Server;
+
+#[cfg(feature = "mock-collector-tls")]
+pub use self::tls::TestCertificates;
+
+mod json;
+use crate::{
+    config::{ConfigBuilder, Protocol},
+    error::{OtlpExporterError, OtlpExporterResult},
+};
+
+/// The path the collector serves trace requests on for OTLP/HTTP.
+pub const HTTP_TRACES_PATH: &str = "/v1/traces";
+
+/// How long to wait before accepting connections again after an error.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The transport a request is received from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MockTransport {
+    Grpc,
+    HttpProtobuf,
+    HttpJson,
+}
+
+/// A request received by [`MockCollector`].
+#[derive(Clone, Debug)]
+pub struct ReceivedRequest {
+    pub transport: MockTransport,
+    pub headers: HeaderMap,
+    /// The decoded request, in any transport.
+    pub request: ExportTraceServiceRequest,
+    /// The raw body of the request, it is re-encoded for grpc.
+    pub body: Vec<u8>,
+}
+
+/// How [`MockCollector`] responds to the following requests.
+#[derive(Clone, Debug, Default)]
+pub struct MockBehavior {
+    delay: Option<Duration>,
+    http_status: Option<StatusCode>,
+    grpc_code: Option<tonic::Code>,
+    partial_success: Option<(i64, String)>,
+}
+
+impl MockBehavior {
+    /// Wait `delay` before responding.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Respond OTLP/HTTP requests with `status`.
+    pub fn with_http_status(mut self, status: StatusCode) -> Self {
+        self.http_status = Some(status);
+        self
+    }
+
+    /// Respond OTLP/gRPC requests with `code`.
+    pub fn with_grpc_code(mut self, code: tonic::Code) -> Self {
+        self.grpc_code = Some(code);
+        self
+    }
+
+    /// Reject `rejected_spans` spans of each request with `message`.
+    pub fn with_partial_success(mut self, rejected_spans: i64, message: impl Into<String>) -> Self {
+        self.partial_success = Some((rejected_spans, message.into()));
+        self
+    }
+
+    fn gen_response(&self) -> ExportTraceServiceResponse {
+        ExportTraceServiceResponse {
+            partial_success: self
+                .partial_success
+                .as_ref()
+                .map(|(rejected_spans, message)| ExportTracePartialSuccess {
+                    rejected_spans: *rejected_spans,
+                    error_message: message.clone(),
+                }),
+        }
+    }
+
+    fn gen_json_response(&self) -> String {
+        match &self.partial_success {
+            Some((rejected_spans, message)) => format!(
+                r#"{{"partialSuccess":{{"rejectedSpans":"{rejected_spans}","errorMessage":{message:?}}}}}"#
+            ),
+            None => "{}".to_owned(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    requests: Mutex<Vec<ReceivedRequest>>,
+    behavior: Mutex<MockBehavior>,
+}
+
+impl State {
+    /// Record `request` and return the behavior for it.
+    fn record(&self, request: ReceivedRequest) -> MockBehavior {
+        lock(&self.requests).push(request);
+        lock(&self.behavior).clone()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Builder of [`MockCollector`].
+#[derive(Debug, Default)]
+pub struct MockCollectorBuilder {
+    behavior: MockBehavior,
+    #[cfg(feature = "mock-collector-tls")]
+    certificates: Option<TestCertificates>,
+    #[cfg(feature = "mock-collector-tls")]
+    client_auth: bool,
+}
+
+impl MockCollectorBuilder {
+    pub fn with_behavior(mut self, behavior: MockBehavior) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Serve both grpc and http over TLS with `certificates`.
+    #[cfg(feature = "mock-collector-tls")]
+    pub fn with_tls(mut self, certificates: TestCertificates) -> Self {
+        self.certificates = Some(certificates);
+        self
+    }
+
+    /// Require clients to present a certificate signed by the test ca(mTLS). It only takes
+    /// effect with TLS.
+    #[cfg(feature = "mock-collector-tls")]
+    pub fn with_client_auth(mut self, client_auth: bool) -> Self {
+        self.client_auth = client_auth;
+        self
+    }
+
+    /// Bind to random ports of localhost and serve in the current tokio runtime.
+    pub async fn start(self) -> OtlpExporterResult<MockCollector> {
+        let state = Arc::new(State {
+            requests: Default::default(),
+            behavior: Mutex::new(self.behavior),
+        });
+
+        let grpc_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let grpc_addr = grpc_listener.local_addr()?;
+        let http_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let http_addr = http_listener.local_addr()?;
+
+        #[allow(unused_mut)]
+        let mut server = Server::builder();
+        #[cfg(feature = "mock-collector-tls")]
+        if let Some(certificates) = &self.certificates {
+            server = server
+                .tls_config(certificates.tonic_server_tls_config(self.client_auth))
+                .map_err(|e| {
+                    OtlpExporterError::ConfigError(format!("invalid tls config: {e:?}"))
+                })?;
+        }
+        let router = server.add_service(TraceServiceServer::new(GrpcService {
+            state: state.clone(),
+        }));
+        let grpc_task = tokio::spawn(async move {
+            let _ = router.serve_with_incoming(incoming(grpc_listener)).await;
+        });
+
+        #[cfg(feature = "mock-collector-tls")]
+        let acceptor = match &self.certificates {
+            Some(certificates) => Some(certificates.tls_acceptor(self.client_auth)?),
+            None => None,
+        };
+        let http_task = {
+            let state = state.clone();
+            tokio::spawn(async move {
+                loop {
+                    let stream = accept(&http_listener).await;
+                    let state = state.clone();
+                    #[cfg(feature = "mock-collector-tls")]
+                    let acceptor = acceptor.clone();
+                    tokio::spawn(async move {
+                        #[cfg(feature = "mock-collector-tls")]
+                        if let Some(acceptor) = acceptor {
+                            if let Ok(stream) = acceptor.accept(stream).await {
+                                serve_http(stream, state).await;
+                            }
+                            return;
+                        }
+                        serve_http(stream, state).await;
+                    });
+                }
+            })
+        };
+
+        Ok(MockCollector {
+            grpc_addr,
+            http_addr,
+            state,
+            tasks: vec![grpc_task, http_task],
+            #[cfg(feature = "mock-collector-tls")]
+            certificates: self.certificates,
+            #[cfg(feature = "mock-collector-tls")]
+            client_auth: self.client_auth,
+        })
+    }
+}
+
+/// A mock OTLP collector, it stops serving when dropped.
+pub struct MockCollector {
+    grpc_addr: SocketAddr,
+    http_addr: SocketAddr,
+    state: Arc<State>,
+    tasks: Vec<JoinHandle<()>>,
+    #[cfg(feature = "mock-collector-tls")]
+    certificates: Option<TestCertificates>,
+    #[cfg(feature = "mock-collector-tls")]
+    client_auth: bool,
+}
+
+impl MockCollector {
+    pub fn builder() -> MockCollectorBuilder {
+        Default::default()
+    }
+
+    /// Start a collector without TLS which accepts all requests.
+    pub async fn start() -> OtlpExporterResult<Self> {
+        Self::builder().start().await
+    }
+
+    fn scheme(&self) -> &'static str {
+        #[cfg(feature = "mock-collector-tls")]
+        if self.certificates.is_some() {
+            return "https";
+        }
+        "http"
+    }
+
+    /// Endpoint of OTLP/gRPC.
+    pub fn grpc_endpoint(&self) -> String {
+        format!("{}://{}", self.scheme(), self.grpc_addr)
+    }
+
+    /// Base endpoint of OTLP/HTTP, without the signal path.
+    pub fn http_endpoint(&self) -> String {
+        format!("{}://{}", self.scheme(), self.http_addr)
+    }
+
+    /// Endpoint of OTLP/HTTP for traces.
+    pub fn http_traces_endpoint(&self) -> String {
+        format!("{}{}", self.http_endpoint(), HTTP_TRACES_PATH)
+    }
+
+    /// Generate a [`ConfigBuilder`] pointing to this collector with `protocol`. Certificates are
+    /// set if TLS is enabled.
+    pub fn config_builder(&self, protocol: Protocol) -> ConfigBuilder {
+        let endpoint = match protocol {
+            #[cfg(feature = "_grpc")]
+            Protocol::Grpc => self.grpc_endpoint(),
+            #[cfg(feature = "http")]
+            Protocol::HttpProtobuf => self.http_traces_endpoint(),
+            #[cfg(feature = "http-json")]
+            Protocol::HttpJson => self.http_traces_endpoint(),
+        };
+        #[allow(unused_mut)]
+        let mut builder = ConfigBuilder::default()
+            .with_protocol(protocol)
+            .with_signal_endpoint(endpoint);
+        #[cfg(feature = "mock-collector-tls")]
+        if let Some(certificates) = &self.certificates {
+            builder = builder
+                .with_certificate_file(certificates.ca_file().into())
+                .with_certificate_domain(tls::SERVER_NAME);
+            if self.client_auth {
+                builder = builder
+                    .with_client_key_file(certificates.client_key_file().into())
+                    .with_client_certificate_file(certificates.client_certificate_file().into());
+            }
+        }
+        builder
+    }
+
+    #[cfg(feature = "mock-collector-tls")]
+    pub fn certificates(&self) -> Option<&TestCertificates> {
+        self.certificates.as_ref()
+    }
+
+    /// Change how the collector responds to the following requests.
+    pub fn set_behavior(&self, behavior: MockBehavior) {
+        *lock(&self.state.behavior) = behavior;
+    }
+
+    /// All requests received so far.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        lock(&self.state.requests).clone()
+    }
+
+    /// All spans received so far.
+    pub fn spans(&self) -> Vec<Span> {
+        let requests: Vec<_> = lock(&self.state.requests)
+            .iter()
+            .map(|r| r.request.clone())
+            .collect();
+        super::collect_spans(&requests)
+    }
+
+    /// Remove all received requests.
+    pub fn reset(&self) {
+        lock(&self.state.requests).clear();
+    }
+
+    /// Wait until at least `count` requests are received, or return an error after `timeout`.
+    pub async fn wait_for_requests(
+        &self,
+        count: usize,
+        timeout: Duration,
+    ) -> OtlpExporterResult<Vec<ReceivedRequest>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let requests = self.requests();
+            if requests.len() >= count {
+                return Ok(requests);
+            }
+            if Instant::now() >= deadline {
+                return Err(OtlpExporterError::UnknownError(format!(
+                    "timed out after {timeout:?} waiting for {count} requests, got {}",
+                    requests.len()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+impl fmt::Debug for MockCollector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockCollector")
+            .field("grpc_addr", &self.grpc_addr)
+            .field("http_addr", &self.http_addr)
+            .finish()
+    }
+}
+
+impl Drop for MockCollector {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+fn incoming(
+    listener: TcpListener,
+) -> Pin<Box<dyn Stream<Item = std::io::Result<TcpStream>> + Send>> {
+    Box::pin(futures::stream::unfold(listener, |listener| async move {
+        let stream = accept(&listener).await;
+        Some((Ok(stream), listener))
+    }))
+}
+
+/// Accept a connection, it backs off after errors like running out of file descriptors instead
+/// of spinning.
+async fn accept(listener: &TcpListener) -> TcpStream {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => return stream,
+            Err(_) => tokio::time::sleep(ACCEPT_BACKOFF).await,
+        }
+    }
+}
+
+struct GrpcService {
+    state: Arc<State>,
+}
+
+#[tonic::async_trait]
+impl TraceService for GrpcService {
+    async fn export(
+        &self,
+        request: tonic::Request<ExportTraceServiceRequest>,
+    ) -> Result<tonic::Response<ExportTraceServiceResponse>, tonic::Status> {
+        let headers = request.metadata().clone().into_headers();
+        let request = request.into_inner();
+        let behavior = self.state.record(ReceivedRequest {
+            transport: MockTransport::Grpc,
+            headers,
+            body: request.encode_to_vec(),
+            request,
+        });
+        if let Some(delay) = behavior.delay {
+            tokio::time::sleep(delay).await;
+        }
+        if let Some(code) = behavior.grpc_code {
+            return Err(tonic::Status::new(code, "injected by mock collector"));
+        }
+        Ok(tonic::Response::new(behavior.gen_response()))
+    }
+}
+
+async fn serve_http<S>(stream: S, state: Arc<State>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |request| handle_http(state.clone(), request));
+    let _ = Http::new().serve_connection(stream, service).await;
+}
+
+async fn handle_http(
+    state: Arc<State>,
+    request: hyper::Request<Body>,
+) -> Result<hyper::Response<Body>, Infallible> {
+    fn respond(status: StatusCode, content_type: &str, body: Vec<u8>) -> hyper::Response<Body> {
+        let mut response = hyper::Response::new(Body::from(body));
+        *response.status_mut() = status;
+        if let Ok(content_type) = content_type.parse() {
+            response.headers_mut().insert(CONTENT_TYPE, content_type);
+        }
+        response
+    }
+
+    if request.method() != Method::POST || request.uri().path() != HTTP_TRACES_PATH {
+        return Ok(respond(StatusCode::NOT_FOUND, "text/plain", Vec::new()));
+    }
+    let headers = request.headers().clone();
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body.to_vec(),
+        Err(e) => {
+            return Ok(respond(
+                StatusCode::BAD_REQUEST,
+                "text/plain",
+                e.to_string().into_bytes(),
+            ))
+        }
+    };
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let (transport, decoded) = match content_type {
+        "application/x-protobuf" => (
+            MockTransport::HttpProtobuf,
+            ExportTraceServiceRequest::decode(body.as_slice()).map_err(|e| e.to_string()),
+        ),
+        "application/json" => (MockTransport::HttpJson, json::decode(&body)),
+        _ => {
+            return Ok(respond(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "text/plain",
+                Vec::new(),
+            ))
+        }
+    };
+    let decoded = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            return Ok(respond(
+                StatusCode::BAD_REQUEST,
+                "text/plain",
+                e.into_bytes(),
+            ))
+        }
+    };
+
+    let behavior = state.record(ReceivedRequest {
+        transport,
+        headers,
+        request: decoded,
+        body,
+    });
+    if let Some(delay) = behavior.delay {
+        tokio::time::sleep(delay).await;
+    }
+    if let Some(status) = behavior.http_status {
+        return Ok(respond(status, "text/plain", Vec::new()));
+    }
+    Ok(match transport {
+        MockTransport::HttpJson => respond(
+            StatusCode::OK,
+            "application/json",
+            behavior.gen_json_response().into_bytes(),
+        ),
+        _ => respond(
+            StatusCode::OK,
+            "application/x-protobuf",
+            behavior.gen_response().encode_to_vec(),
+        ),
+    })
+}
+
+#[cfg(feature = "mock-collector-tls")]
+mod tls {
+    use std::{
+        fmt, fs,
+        net::{IpAddr, Ipv4Addr},
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, SanType,
+    };
+    use tokio_rustls::{
+        rustls::{
+            server::AllowAnyAuthenticatedClient, Certificate as RustlsCertificate, PrivateKey,
+            RootCertStore, ServerConfig,
+        },
+        TlsAcceptor,
+    };
+    use tonic::transport::{Certificate as TonicCertificate, Identity, ServerTlsConfig};
+
+    use crate::error::{OtlpExporterError, OtlpExporterResult};
+
+    /// The dns name in the server certificate.
+    pub const SERVER_NAME: &str = "localhost";
+
+    /// A ca, a server certificate and a client certificate generated for tests.
+    ///
+    /// All of them are written to a temporary directory in PEM format, the directory is removed
+    /// when it is dropped.
+    pub struct TestCertificates {
+        dir: PathBuf,
+        server_certificate: String,
+        server_key: String,
+        ca: String,
+    }
+
+    impl TestCertificates {
+        /// Generate certificates. The server certificate is valid for `localhost` and
+        /// `127.0.0.1`.
+        pub fn generate() -> OtlpExporterResult<Self> {
+            fn gen_error(e: rcgen::RcgenError) -> OtlpExporterError {
+                OtlpExporterError::UnknownError(format!("failed to generate certificate: {e}"))
+            }
+
+            fn distinguished_name(common_name: &str) -> DistinguishedName {
+                let mut name = DistinguishedName::new();
+                name.push(DnType::CommonName, common_name);
+                name
+            }
+
+            let mut ca_params = CertificateParams::default();
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            ca_params.distinguished_name = distinguished_name("otlp-exporter test ca");
+            let ca = Certificate::from_params(ca_params).map_err(gen_error)?;
+
+            let mut server_params = CertificateParams::default();
+            server_params.distinguished_name = distinguished_name(SERVER_NAME);
+            server_params.subject_alt_names = vec![
+                SanType::DnsName(SERVER_NAME.to_owned()),
+                SanType::IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ];
+            let server = Certificate::from_params(server_params).map_err(gen_error)?;
+
+            let mut client_params = CertificateParams::default();
+            client_params.distinguished_name = distinguished_name("otlp-exporter test client");
+            let client = Certificate::from_params(client_params).map_err(gen_error)?;
+
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "otlp-exporter-certs-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&dir)?;
+            let certificates = Self {
+                ca: ca.serialize_pem().map_err(gen_error)?,
+                server_certificate: server.serialize_pem_with_signer(&ca).map_err(gen_error)?,
+                server_key: server.serialize_private_key_pem(),
+                dir,
+            };
+            fs::write(certificates.ca_file(), &certificates.ca)?;
+            fs::write(
+                certificates.client_certificate_file(),
+                client.serialize_pem_with_signer(&ca).map_err(gen_error)?,
+            )?;
+            fs::write(
+                certificates.client_key_file(),
+                client.serialize_private_key_pem(),
+            )?;
+            Ok(certificates)
+        }
+
+        pub fn dir(&self) -> &Path {
+            &self.dir
+        }
+
+        pub fn ca_file(&self) -> PathBuf {
+            self.dir.join("ca.pem")
+        }
+
+        pub fn client_key_file(&self) -> PathBuf {
+            self.dir.join("client.key")
+        }
+
+        pub fn client_certificate_file(&self) -> PathBuf {
+            self.dir.join("client.pem")
+        }
+
+        pub(super) fn tonic_server_tls_config(&self, client_auth: bool) -> ServerTlsConfig {
+            let mut config = ServerTlsConfig::new().identity(Identity::from_pem(
+                &self.server_certificate,
+                &self.server_key,
+            ));
+            if client_auth {
+                config = config.client_ca_root(TonicCertificate::from_pem(&self.ca));
+            }
+            config
+        }
+
+        pub(super) fn tls_acceptor(&self, client_auth: bool) -> OtlpExporterResult<TlsAcceptor> {
+            fn tls_error(e: impl fmt::Display) -> OtlpExporterError {
+                OtlpExporterError::ConfigError(format!("invalid tls config: {e}"))
+            }
+
+            let certificate_chain = rustls_pemfile::certs(&mut self.server_certificate.as_bytes())?
+                .into_iter()
+                .map(RustlsCertificate)
+                .collect();
+            let key = rustls_pemfile::pkcs8_private_keys(&mut self.server_key.as_bytes())?
+                .pop()
+                .ok_or_else(|| tls_error("no private key is found"))?;
+
+            let builder = ServerConfig::builder().with_safe_defaults();
+            let builder = if client_auth {
+                let mut roots = RootCertStore::empty();
+                for ca in rustls_pemfile::certs(&mut self.ca.as_bytes())? {
+                    roots.add(&RustlsCertificate(ca)).map_err(tls_error)?;
+                }
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            } else {
+                builder.with_no_client_auth()
+            };
+            let config = builder
+                .with_single_cert(certificate_chain, PrivateKey(key))
+                .map_err(tls_error)?;
+            Ok(TlsAcceptor::from(Arc::new(config)))
+        }
+    }
+
+    impl fmt::Debug for TestCertificates {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("TestCertificates")
+                .field("dir", &self.dir)
+                .finish()
+        }
+    }
+
+    impl Drop for TestCertificates {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_sdk::export::trace::SpanExporter;
+
+    use super::{MockBehavior, MockCollector, MockTransport};
+    use crate::{
+        config::{ConfigBuilder, Protocol},
+        exporter::trace::TraceExporter,
+        testing::test_span,
+    };
+
+    async fn export_to(collector: &MockCollector, config_builder: ConfigBuilder) {
+        let mut exporter = TraceExporter::try_from(config_builder.build().unwrap()).unwrap();
+        exporter.export(vec![test_span("ok")]).await.unwrap();
+        assert_eq!(collector.spans().len(), 1);
+        assert_eq!(collector.spans()[0].name, "ok");
+
+        collector.set_behavior(
+            MockBehavior::default()
+                .with_http_status(http::StatusCode::SERVICE_UNAVAILABLE)
+                .with_grpc_code(tonic::Code::Unavailable),
+        );
+        assert!(exporter.export(vec![test_span("failed")]).await.is_err());
+        assert_eq!(collector.requests().len(), 2);
+    }
+
+    #[cfg(feature = "tonic")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tonic() {
+        let collector = MockCollector::start().await.unwrap();
+        export_to(&collector, collector.config_builder(Protocol::Grpc)).await;
+        assert!(collector
+            .requests()
+            .iter()
+            .all(|r| r.transport == MockTransport::Grpc));
+    }
+
+    #[cfg(feature = "grpcio")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_grpcio() {
+        let collector = MockCollector::start().await.unwrap();
+        export_to(
+            &collector,
+            collector
+                .config_builder(Protocol::Grpc)
+                .with_grpc_impl(crate::config::GrpcImpl::Grpcio(Default::default())),
+        )
+        .await;
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_http_protobuf() {
+        let collector = MockCollector::start().await.unwrap();
+        export_to(&collector, collector.config_builder(Protocol::HttpProtobuf)).await;
+        let request = &collector.requests()[0];
+        assert_eq!(request.transport, MockTransport::HttpProtobuf);
+        assert!(request.headers.contains_key(http::header::USER_AGENT));
+    }
+
+    #[cfg(feature = "http")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_http_json_partial_success() {
+        let collector = MockCollector::builder()
+            .with_behavior(MockBehavior::default().with_partial_success(1, "rejected"))
+            .start()
+            .await
+            .unwrap();
+        let response = reqwest::Client::new()
+            .post(collector.http_traces_endpoint())
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(r#"{"resourceSpans":[]}"#)
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            response.text().await.unwrap(),
+            r#"{"partialSuccess":{"rejectedSpans":"1","errorMessage":"rejected"}}"#
+        );
+        let requests = collector
+            .wait_for_requests(1, std::time::Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(requests[0].transport, MockTransport::HttpJson);
+        assert!(requests[0].request.resource_spans.is_empty());
+
+        let response = reqwest::Client::new()
+            .post(collector.http_traces_endpoint())
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(
+                r#"{"resourceSpans":[{"scopeSpans":[{"spans":[
+                    {"traceId":"5b8efff798038103d269b633813fc60c","spanId":"eee19b7ec3c1b174","name":"json"}
+                ]}]}]}"#,
+            )
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(collector.spans()[0].name, "json");
+
+        // malformed requests are rejected
+        let response = reqwest::Client::new()
+            .post(collector.http_traces_endpoint())
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(r#"{"resourceSpans":{}}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(collector.requests().len(), 2);
+    }
+
+    #[cfg(all(feature = "mock-collector-tls", feature = "tonic-tls"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_tonic_mtls() {
+        let collector = MockCollector::builder()
+            .with_tls(super::TestCertificates::generate().unwrap())
+            .with_client_auth(true)
+            .start()
+            .await
+            .unwrap();
+        export_to(&collector, collector.config_builder(Protocol::Grpc)).await;
+    }
+
+    #[cfg(all(feature = "mock-collector-tls", feature = "_http-rustls-tls"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_http_mtls() {
+        let collector = MockCollector::builder()
+            .with_tls(super::TestCertificates::generate().unwrap())
+            .with_client_auth(true)
+            .start()
+            .await
+            .unwrap();
+        export_to(&collector, collector.config_builder(Protocol::HttpProtobuf)).await;
+    }
+}
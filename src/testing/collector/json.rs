@@ -0,0 +1,301 @@
+//! Decode OTLP/JSON requests, refer:
+//! https://opentelemetry.io/docs/specs/otlp/#json-protobuf-encoding
+//!
+//! Field names are in lowerCamelCase, trace ids and span ids are hex strings, enums are integers,
+//! 64 bit integers are either numbers or strings and bytes values are in base64.
+
+use base64::Engine;
+use opentelemetry_proto::tonic::{
+    collector::trace::v1::ExportTraceServiceRequest,
+    common::v1::{any_value, AnyValue, ArrayValue, InstrumentationScope, KeyValue, KeyValueList},
+    resource::v1::Resource,
+    trace::v1::{span, ResourceSpans, ScopeSpans, Span, Status},
+};
+use serde_json::{Map, Value};
+
+type Object = Map<String, Value>;
+
+pub(super) fn decode(body: &[u8]) -> Result<ExportTraceServiceRequest, String> {
+    let value: Value = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    let request = object(&value, "request")?;
+    Ok(ExportTraceServiceRequest {
+        resource_spans: list(request, "resourceSpans", resource_spans)?,
+    })
+}
+
+fn resource_spans(value: &Value) -> Result<ResourceSpans, String> {
+    let value = object(value, "resourceSpans")?;
+    Ok(ResourceSpans {
+        resource: optional(value, "resource", |value| {
+            let value = object(value, "resource")?;
+            Ok(Resource {
+                attributes: list(value, "attributes", key_value)?,
+                dropped_attributes_count: int(value, "droppedAttributesCount")?,
+            })
+        })?,
+        scope_spans: list(value, "scopeSpans", scope_spans)?,
+        schema_url: string(value, "schemaUrl")?,
+    })
+}
+
+fn scope_spans(value: &Value) -> Result<ScopeSpans, String> {
+    let value = object(value, "scopeSpans")?;
+    Ok(ScopeSpans {
+        scope: optional(value, "scope", |value| {
+            let value = object(value, "scope")?;
+            Ok(InstrumentationScope {
+                name: string(value, "name")?,
+                version: string(value, "version")?,
+                attributes: list(value, "attributes", key_value)?,
+                dropped_attributes_count: int(value, "droppedAttributesCount")?,
+            })
+        })?,
+        spans: list(value, "spans", span)?,
+        schema_url: string(value, "schemaUrl")?,
+    })
+}
+
+fn span(value: &Value) -> Result<Span, String> {
+    let value = object(value, "span")?;
+    Ok(Span {
+        trace_id: hex(value, "traceId")?,
+        span_id: hex(value, "spanId")?,
+        trace_state: string(value, "traceState")?,
+        parent_span_id: hex(value, "parentSpanId")?,
+        name: string(value, "name")?,
+        kind: int(value, "kind")?,
+        start_time_unix_nano: int(value, "startTimeUnixNano")?,
+        end_time_unix_nano: int(value, "endTimeUnixNano")?,
+        attributes: list(value, "attributes", key_value)?,
+        dropped_attributes_count: int(value, "droppedAttributesCount")?,
+        events: list(value, "events", |value| {
+            let value = object(value, "event")?;
+            Ok(span::Event {
+                time_unix_nano: int(value, "timeUnixNano")?,
+                name: string(value, "name")?,
+                attributes: list(value, "attributes", key_value)?,
+                dropped_attributes_count: int(value, "droppedAttributesCount")?,
+            })
+        })?,
+        dropped_events_count: int(value, "droppedEventsCount")?,
+        links: list(value, "links", |value| {
+            let value = object(value, "link")?;
+            Ok(span::Link {
+                trace_id: hex(value, "traceId")?,
+                span_id: hex(value, "spanId")?,
+                trace_state: string(value, "traceState")?,
+                attributes: list(value, "attributes", key_value)?,
+                dropped_attributes_count: int(value, "droppedAttributesCount")?,
+            })
+        })?,
+        dropped_links_count: int(value, "droppedLinksCount")?,
+        status: optional(value, "status", |value| {
+            let value = object(value, "status")?;
+            Ok(Status {
+                message: string(value, "message")?,
+                code: int(value, "code")?,
+            })
+        })?,
+    })
+}
+
+fn key_value(value: &Value) -> Result<KeyValue, String> {
+    let value = object(value, "attribute")?;
+    Ok(KeyValue {
+        key: string(value, "key")?,
+        value: optional(value, "value", any_value)?,
+    })
+}
+
+fn any_value(value: &Value) -> Result<AnyValue, String> {
+    let value = object(value, "value")?;
+    let Some((name, inner)) = value.iter().next() else {
+        return Ok(AnyValue { value: None });
+    };
+    let value = match name.as_str() {
+        "stringValue" => any_value::Value::StringValue(string(value, name)?),
+        "boolValue" => any_value::Value::BoolValue(
+            inner
+                .as_bool()
+                .ok_or_else(|| format!("{name} should be a bool"))?,
+        ),
+        "intValue" => any_value::Value::IntValue(int(value, name)?),
+        "doubleValue" => any_value::Value::DoubleValue(
+            inner
+                .as_f64()
+                .ok_or_else(|| format!("{name} should be a number"))?,
+        ),
+        "arrayValue" => any_value::Value::ArrayValue(ArrayValue {
+            values: list(object(inner, name)?, "values", any_value)?,
+        }),
+        "kvlistValue" => any_value::Value::KvlistValue(KeyValueList {
+            values: list(object(inner, name)?, "values", key_value)?,
+        }),
+        "bytesValue" => any_value::Value::BytesValue(
+            base64::engine::general_purpose::STANDARD
+                .decode(string(value, name)?)
+                .map_err(|e| format!("{name} should be in base64: {e}"))?,
+        ),
+        _ => return Err(format!("unknown value type {name}")),
+    };
+    Ok(AnyValue { value: Some(value) })
+}
+
+fn object<'a>(value: &'a Value, name: &str) -> Result<&'a Object, String> {
+    value
+        .as_object()
+        .ok_or_else(|| format!("{name} should be an object"))
+}
+
+/// Decode field `name`, missing fields and nulls are `None` like in protobuf.
+fn optional<T>(
+    value: &Object,
+    name: &str,
+    decode: impl Fn(&Value) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    match value.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => decode(value).map(Some),
+    }
+}
+
+fn list<T>(
+    value: &Object,
+    name: &str,
+    decode: impl Fn(&Value) -> Result<T, String>,
+) -> Result<Vec<T>, String> {
+    let values = match value.get(name) {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(Value::Array(values)) => values,
+        Some(_) => return Err(format!("{name} should be an array")),
+    };
+    values
+        .iter()
+        .map(decode)
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("invalid {name}: {e}"))
+}
+
+fn string(value: &Object, name: &str) -> Result<String, String> {
+    optional(value, name, |value| {
+        value
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| format!("{name} should be a string"))
+    })
+    .map(Option::unwrap_or_default)
+}
+
+/// Decode an integer field, it may be a number or a string.
+fn int<T: TryFrom<i64> + TryFrom<u64> + Default>(value: &Object, name: &str) -> Result<T, String> {
+    let invalid = || format!("{name} should be an integer in range");
+    optional(value, name, |value| {
+        let (signed, unsigned) = match value {
+            Value::Number(number) => (number.as_i64(), number.as_u64()),
+            Value::String(s) => (s.parse().ok(), s.parse().ok()),
+            _ => (None, None),
+        };
+        match (signed, unsigned) {
+            (_, Some(n)) => T::try_from(n).map_err(|_| invalid()),
+            (Some(n), _) => T::try_from(n).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    })
+    .map(Option::unwrap_or_default)
+}
+
+fn hex(value: &Object, name: &str) -> Result<Vec<u8>, String> {
+    let s = string(value, name)?;
+    if s.len() % 2 != 0 {
+        return Err(format!("{name} should be in hex"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("{name} should be in hex"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_proto::tonic::{
+        collector::trace::v1::ExportTraceServiceRequest, common::v1::any_value,
+    };
+
+    use super::decode;
+
+    #[test]
+    fn test_decode() {
+        let body = r#"{
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [{"key": "service.name", "value": {"stringValue": "test"}}]
+                },
+                "scopeSpans": [{
+                    "scope": {"name": "scope", "version": "1.0"},
+                    "spans": [{
+                        "traceId": "5b8efff798038103d269b633813fc60c",
+                        "spanId": "eee19b7ec3c1b174",
+                        "name": "span",
+                        "kind": 2,
+                        "startTimeUnixNano": "1544712660000000000",
+                        "endTimeUnixNano": 1544712661000000000,
+                        "attributes": [
+                            {"key": "int", "value": {"intValue": "-1"}},
+                            {"key": "bool", "value": {"boolValue": true}},
+                            {"key": "bytes", "value": {"bytesValue": "AQI="}},
+                            {"key": "array", "value": {"arrayValue": {"values": [
+                                {"doubleValue": 1.5}
+                            ]}}}
+                        ],
+                        "events": [{"timeUnixNano": "1544712660500000000", "name": "event"}],
+                        "status": {"code": 2, "message": "failed"}
+                    }]
+                }]
+            }]
+        }"#;
+        let request = decode(body.as_bytes()).unwrap();
+        let resource_spans = &request.resource_spans[0];
+        let span = &resource_spans.scope_spans[0].spans[0];
+        assert_eq!(span.trace_id.len(), 16);
+        assert_eq!(
+            span.span_id,
+            [0xee, 0xe1, 0x9b, 0x7e, 0xc3, 0xc1, 0xb1, 0x74]
+        );
+        assert!(span.parent_span_id.is_empty());
+        assert_eq!(span.kind, 2);
+        assert_eq!(span.start_time_unix_nano, 1_544_712_660_000_000_000);
+        assert_eq!(span.end_time_unix_nano, 1_544_712_661_000_000_000);
+        let values: Vec<_> = span
+            .attributes
+            .iter()
+            .map(|kv| kv.value.clone().unwrap().value.unwrap())
+            .collect();
+        assert_eq!(values[0], any_value::Value::IntValue(-1));
+        assert_eq!(values[1], any_value::Value::BoolValue(true));
+        assert_eq!(values[2], any_value::Value::BytesValue(vec![1, 2]));
+        assert!(
+            matches!(&values[3], any_value::Value::ArrayValue(array) if array.values.len() == 1)
+        );
+        assert_eq!(span.events[0].name, "event");
+        assert_eq!(span.status.as_ref().unwrap().code, 2);
+        assert_eq!(
+            resource_spans.scope_spans[0]
+                .scope
+                .as_ref()
+                .unwrap()
+                .version,
+            "1.0"
+        );
+
+        assert_eq!(decode(b"{}").unwrap(), ExportTraceServiceRequest::default());
+        assert!(
+            decode(br#"{"resourceSpans":[{"scopeSpans":[{"spans":[{"spanId":"xyz"}]}]}]}"#)
+                .is_err()
+        );
+        assert!(decode(b"[]").is_err());
+    }
+}